@@ -0,0 +1,462 @@
+use std::collections::BTreeMap;
+use anyhow::{Error, bail};
+use serde::{Deserialize, Serialize};
+use winit::{event::MouseButton, keyboard::KeyCode};
+use crate::{input::Input, math::Vec2, util::extension};
+
+// Something that can be held down.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // Every key has to be held, e.g. Chord(vec![KeyCode::ControlLeft, KeyCode::KeyS]).
+    Chord(Vec<KeyCode>),
+}
+
+impl Binding {
+    // chords are the keys of the chords currently held, see ActionMap::get_held_chords.
+    fn is_pressed(&self, input: &Input, chords: &[&[KeyCode]]) -> bool {
+        match self {
+            Binding::Key(key) => input.is_key_pressed(*key) && !completes_chord(*key, chords),
+            Binding::Mouse(button) => input.is_mouse_pressed(*button),
+            Binding::Chord(keys) => !keys.is_empty() && keys.iter().all(|key| input.is_key_pressed(*key)),
+        }
+    }
+
+    fn is_just_pressed(&self, input: &Input, chords: &[&[KeyCode]]) -> bool {
+        match self {
+            Binding::Key(key) => input.is_key_just_pressed(*key) && !completes_chord(*key, chords),
+            Binding::Mouse(button) => input.is_mouse_just_pressed(*button),
+            // Completing the chord counts, in whatever order the keys went down.
+            Binding::Chord(keys) => self.is_pressed(input, chords) && keys.iter().any(|key| input.is_key_just_pressed(*key)),
+        }
+    }
+
+    fn is_just_released(&self, input: &Input, chords: &[&[KeyCode]]) -> bool {
+        match self {
+            Binding::Key(key) => input.is_key_just_released(*key) && !completes_chord(*key, chords),
+            Binding::Mouse(button) => input.is_mouse_just_released(*button),
+            Binding::Chord(keys) => {
+                keys.iter().any(|key| input.is_key_just_released(*key))
+                    && keys.iter().all(|key| input.is_key_pressed(*key) || input.is_key_just_released(*key))
+            }
+        }
+    }
+}
+
+// The last key of a chord is the one it is about, so ctrl+S doesn't also press S.
+fn completes_chord(key: KeyCode, chords: &[&[KeyCode]]) -> bool {
+    chords.iter().any(|chord| chord.len() > 1 && chord.last() == Some(&key))
+}
+
+// Source of a value from -1 to 1, or of an unbounded one for the mouse.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    // -1 while negative is held, 1 while positive is, 0 with both.
+    Buttons { negative: Binding, positive: Binding },
+    // Cursor movement in pixels this frame.
+    MouseX,
+    MouseY,
+    // Scroll in lines this frame.
+    WheelX,
+    WheelY,
+}
+
+impl AxisBinding {
+    // Shorthand for the common key pair, e.g. AxisBinding::keys(KeyCode::KeyA, KeyCode::KeyD).
+    pub fn keys(negative: KeyCode, positive: KeyCode) -> Self {
+        AxisBinding::Buttons { negative: Binding::Key(negative), positive: Binding::Key(positive) }
+    }
+
+    fn get_value(&self, input: &Input, chords: &[&[KeyCode]]) -> f32 {
+        match self {
+            AxisBinding::Buttons { negative, positive } => positive.is_pressed(input, chords) as i32 as f32 - negative.is_pressed(input, chords) as i32 as f32,
+            AxisBinding::MouseX => input.get_mouse_delta().x as f32,
+            AxisBinding::MouseY => input.get_mouse_delta().y as f32,
+            AxisBinding::WheelX => input.get_wheel_delta().x as f32,
+            AxisBinding::WheelY => input.get_wheel_delta().y as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Axis {
+    pub bindings: Vec<AxisBinding>,
+    // Values closer to 0 than this read as 0, the rest is rescaled to start right after it.
+    pub dead_zone: f32,
+    pub sensitivity: f32,
+}
+
+impl Axis {
+    pub fn new(bindings: Vec<AxisBinding>) -> Self {
+        Self { bindings, dead_zone: 0.0, sensitivity: 1.0 }
+    }
+
+    fn get_value(&self, input: &Input, chords: &[&[KeyCode]]) -> f32 {
+        let value = self.bindings.iter().map(|binding| binding.get_value(input, chords)).sum::<f32>() * self.sensitivity;
+        apply_dead_zone(value, self.dead_zone)
+    }
+}
+
+// Source of a 2D vector, with y pointing up like the other axes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Axis2DBinding {
+    // WASD style, diagonals are normalized so they aren't faster.
+    Buttons { up: Binding, down: Binding, left: Binding, right: Binding },
+    // Cursor movement in pixels this frame, flipped so moving up is positive.
+    Mouse,
+    Wheel,
+}
+
+impl Axis2DBinding {
+    pub fn wasd() -> Self {
+        Self::keys(KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyA, KeyCode::KeyD)
+    }
+
+    pub fn arrows() -> Self {
+        Self::keys(KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight)
+    }
+
+    pub fn keys(up: KeyCode, down: KeyCode, left: KeyCode, right: KeyCode) -> Self {
+        Axis2DBinding::Buttons { up: Binding::Key(up), down: Binding::Key(down), left: Binding::Key(left), right: Binding::Key(right) }
+    }
+
+    fn get_value(&self, input: &Input, chords: &[&[KeyCode]]) -> Vec2 {
+        match self {
+            Axis2DBinding::Buttons { up, down, left, right } => {
+                let axis = |negative: &Binding, positive: &Binding| positive.is_pressed(input, chords) as i32 as f32 - negative.is_pressed(input, chords) as i32 as f32;
+                Vec2::new(axis(left, right), axis(down, up)).normalize_or_zero()
+            }
+            Axis2DBinding::Mouse => {
+                let delta = input.get_mouse_delta();
+                Vec2::new(delta.x as f32, -delta.y as f32)
+            }
+            Axis2DBinding::Wheel => input.get_wheel_delta().into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Axis2D {
+    pub bindings: Vec<Axis2DBinding>,
+    // Dead zone on the length, so it is the same in every direction.
+    pub dead_zone: f32,
+    pub sensitivity: f32,
+}
+
+impl Axis2D {
+    pub fn new(bindings: Vec<Axis2DBinding>) -> Self {
+        Self { bindings, dead_zone: 0.0, sensitivity: 1.0 }
+    }
+
+    fn get_value(&self, input: &Input, chords: &[&[KeyCode]]) -> Vec2 {
+        let value = self.bindings.iter().fold(Vec2::ZERO, |sum, binding| sum + binding.get_value(input, chords)) * self.sensitivity;
+        let length = value.length();
+        if length == 0.0 {
+            return Vec2::ZERO;
+        }
+        value * (apply_dead_zone(length, self.dead_zone) / length)
+    }
+}
+
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() < dead_zone {
+        0.0
+    } else if value.abs() <= 1.0 && dead_zone < 1.0 {
+        value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+    } else {
+        value
+    }
+}
+
+// Named actions the game asks about instead of concrete keys, so players can rebind
+// them. Unknown names read as not pressed and 0.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ActionMap {
+    #[serde(default)]
+    pub buttons: BTreeMap<String, Vec<Binding>>,
+    #[serde(default)]
+    pub axes: BTreeMap<String, Axis>,
+    #[serde(default)]
+    pub axes_2d: BTreeMap<String, Axis2D>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_button(mut self, action: &str, bindings: Vec<Binding>) -> Self {
+        self.buttons.insert(action.to_string(), bindings);
+        self
+    }
+
+    pub fn with_axis(mut self, action: &str, axis: Axis) -> Self {
+        self.axes.insert(action.to_string(), axis);
+        self
+    }
+
+    pub fn with_axis_2d(mut self, action: &str, axis: Axis2D) -> Self {
+        self.axes_2d.insert(action.to_string(), axis);
+        self
+    }
+
+    // Adds a binding next to the existing ones.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.buttons.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: &Binding) {
+        if let Some(bindings) = self.buttons.get_mut(action) {
+            bindings.retain(|bound| bound != binding);
+        }
+    }
+
+    // Swaps out every binding of the action, what a "press a key" menu does.
+    pub fn rebind(&mut self, action: &str, binding: Binding) {
+        self.buttons.insert(action.to_string(), vec![binding]);
+    }
+
+    pub fn get_bindings(&self, action: &str) -> &[Binding] {
+        self.buttons.get(action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    // Actions already using the binding, to warn about conflicts when rebinding.
+    pub fn get_actions_bound_to(&self, binding: &Binding) -> Vec<&str> {
+        self.buttons.iter().filter(|(_, bindings)| bindings.contains(binding)).map(|(action, _)| action.as_str()).collect()
+    }
+
+    pub fn get_axis_mut(&mut self, action: &str) -> Option<&mut Axis> {
+        self.axes.get_mut(action)
+    }
+
+    pub fn get_axis_2d_mut(&mut self, action: &str) -> Option<&mut Axis2D> {
+        self.axes_2d.get_mut(action)
+    }
+
+    pub fn is_pressed(&self, input: &Input, action: &str) -> bool {
+        let chords = self.get_held_chords(input);
+        self.get_bindings(action).iter().any(|binding| binding.is_pressed(input, &chords))
+    }
+
+    pub fn is_just_pressed(&self, input: &Input, action: &str) -> bool {
+        // Only when no other binding already held the action down.
+        let chords = self.get_held_chords(input);
+        let bindings = self.get_bindings(action);
+        bindings.iter().any(|binding| binding.is_just_pressed(input, &chords))
+            && !bindings.iter().any(|binding| binding.is_pressed(input, &chords) && !binding.is_just_pressed(input, &chords))
+    }
+
+    pub fn is_just_released(&self, input: &Input, action: &str) -> bool {
+        let chords = self.get_held_chords(input);
+        let bindings = self.get_bindings(action);
+        bindings.iter().any(|binding| binding.is_just_released(input, &chords)) && !self.is_pressed(input, action)
+    }
+
+    pub fn get_axis(&self, input: &Input, action: &str) -> f32 {
+        self.axes.get(action).map(|axis| axis.get_value(input, &self.get_held_chords(input))).unwrap_or(0.0)
+    }
+
+    pub fn get_axis_2d(&self, input: &Input, action: &str) -> Vec2 {
+        self.axes_2d.get(action).map(|axis| axis.get_value(input, &self.get_held_chords(input))).unwrap_or(Vec2::ZERO)
+    }
+
+    // Keys of every bound chord that is held or was let go of this frame, whichever action
+    // it belongs to. Plain key bindings of their last key are ignored meanwhile.
+    fn get_held_chords(&self, input: &Input) -> Vec<&[KeyCode]> {
+        let buttons = self.buttons.values().flatten();
+        let axes = self.axes.values().flat_map(|axis| &axis.bindings).flat_map(|binding| match binding {
+            AxisBinding::Buttons { negative, positive } => vec![negative, positive],
+            _ => Vec::new(),
+        });
+        let axes_2d = self.axes_2d.values().flat_map(|axis| &axis.bindings).flat_map(|binding| match binding {
+            Axis2DBinding::Buttons { up, down, left, right } => vec![up, down, left, right],
+            _ => Vec::new(),
+        });
+        buttons.chain(axes).chain(axes_2d).filter_map(|binding| match binding {
+            Binding::Chord(keys) if binding.is_pressed(input, &[]) || binding.is_just_released(input, &[]) => Some(keys.as_slice()),
+            _ => None,
+        }).collect()
+    }
+
+    // The first key or mouse button pressed this frame, for capturing a new binding.
+    // Modifiers held with another key come back as a chord. Keys pressed in the same frame
+    // are taken in the order they went down.
+    pub fn capture_binding(input: &Input) -> Option<Binding> {
+        if let Some(key) = input.get_just_pressed_keys().next() {
+            let modifiers: Vec<KeyCode> = input.get_pressed_keys()
+                .filter(|pressed| *pressed != key && is_modifier(**pressed))
+                .copied()
+                .collect();
+            if modifiers.is_empty() || is_modifier(*key) {
+                return Some(Binding::Key(*key));
+            }
+            return Some(Binding::Chord(modifiers.into_iter().chain([*key]).collect()));
+        }
+        input.get_just_pressed_mouse_buttons().next().map(|button| Binding::Mouse(*button))
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, Error> {
+        Ok(ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, Error> {
+        Ok(ron::from_str(ron)?)
+    }
+
+    // Picks JSON or RON from the file extension.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        match extension(path).as_str() {
+            "json" => Self::from_json(&text),
+            "ron" => Self::from_ron(&text),
+            other => bail!("unknown action map extension {:?}", other),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let text = match extension(path).as_str() {
+            "json" => self.to_json()?,
+            "ron" => self.to_ron()?,
+            other => bail!("unknown action map extension {:?}", other),
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+fn is_modifier(key: KeyCode) -> bool {
+    matches!(key,
+        KeyCode::ControlLeft | KeyCode::ControlRight
+        | KeyCode::ShiftLeft | KeyCode::ShiftRight
+        | KeyCode::AltLeft | KeyCode::AltRight
+        | KeyCode::SuperLeft | KeyCode::SuperRight
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(keys: &[KeyCode]) -> Input {
+        let mut input = Input::new();
+        for key in keys {
+            input.handle_key(*key, true);
+        }
+        input
+    }
+
+    #[test]
+    fn dead_zone_rescales_the_rest() {
+        assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(-0.19, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(0.2, 0.2), 0.0);
+        assert!((apply_dead_zone(0.6, 0.2) - 0.5).abs() < 1e-6);
+        assert!((apply_dead_zone(-0.6, 0.2) + 0.5).abs() < 1e-6);
+        assert_eq!(apply_dead_zone(1.0, 0.2), 1.0);
+        assert_eq!(apply_dead_zone(-1.0, 0.2), -1.0);
+        // Unbounded mouse values pass through.
+        assert_eq!(apply_dead_zone(25.0, 0.2), 25.0);
+        assert_eq!(apply_dead_zone(0.5, 0.0), 0.5);
+        assert_eq!(apply_dead_zone(2.0, 1.0), 2.0);
+    }
+
+    #[test]
+    fn axis_2d_dead_zone_works_on_the_length() {
+        let mut axis = Axis2D::new(vec![Axis2DBinding::wasd()]);
+        axis.dead_zone = 0.5;
+        let value = axis.get_value(&input(&[KeyCode::KeyW, KeyCode::KeyD]), &[]);
+        assert!((value.length() - 1.0).abs() < 1e-6);
+        assert!((value.x - value.y).abs() < 1e-6);
+        assert_eq!(axis.get_value(&input(&[]), &[]), Vec2::ZERO);
+    }
+
+    fn action_map() -> ActionMap {
+        let mut axis = Axis::new(vec![AxisBinding::keys(KeyCode::KeyA, KeyCode::KeyD), AxisBinding::WheelY]);
+        axis.dead_zone = 0.25;
+        ActionMap::new()
+            .with_button("jump", vec![Binding::Key(KeyCode::Space), Binding::Mouse(MouseButton::Left)])
+            .with_button("save", vec![Binding::Chord(vec![KeyCode::ControlLeft, KeyCode::KeyS])])
+            .with_axis("turn", axis)
+            .with_axis_2d("move", Axis2D::new(vec![Axis2DBinding::wasd(), Axis2DBinding::Mouse]))
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let map = action_map();
+        assert_eq!(ActionMap::from_json(&map.to_json().unwrap()).unwrap(), map);
+        assert_eq!(ActionMap::from_json("{}").unwrap(), ActionMap::new());
+        assert!(ActionMap::from_json("{\"buttons\": 3}").is_err());
+    }
+
+    #[test]
+    fn ron_round_trips() {
+        let map = action_map();
+        assert_eq!(ActionMap::from_ron(&map.to_ron().unwrap()).unwrap(), map);
+        assert_eq!(ActionMap::from_ron("()").unwrap(), ActionMap::new());
+    }
+
+    #[test]
+    fn unknown_extensions_are_rejected() {
+        let error = ActionMap::new().save("bindings.toml").unwrap_err();
+        assert!(error.to_string().contains("unknown action map extension"), "{}", error);
+    }
+
+    #[test]
+    fn capture_takes_keys_in_press_order() {
+        for _ in 0..16 {
+            let input = input(&[KeyCode::KeyQ, KeyCode::KeyB, KeyCode::KeyZ, KeyCode::KeyA]);
+            assert_eq!(ActionMap::capture_binding(&input), Some(Binding::Key(KeyCode::KeyQ)));
+        }
+
+        let mut input = input(&[KeyCode::ShiftLeft, KeyCode::ControlLeft, KeyCode::AltLeft]);
+        input.end_frame();
+        input.handle_key(KeyCode::KeyS, true);
+        assert_eq!(
+            ActionMap::capture_binding(&input),
+            Some(Binding::Chord(vec![KeyCode::ShiftLeft, KeyCode::ControlLeft, KeyCode::AltLeft, KeyCode::KeyS])),
+        );
+        input.end_frame();
+        assert_eq!(ActionMap::capture_binding(&input), None);
+    }
+
+    #[test]
+    fn chords_hide_their_last_key() {
+        let map = ActionMap::new()
+            .with_button("save", vec![Binding::Chord(vec![KeyCode::ControlLeft, KeyCode::KeyS])])
+            .with_button("back", vec![Binding::Key(KeyCode::KeyS)])
+            .with_button("crouch", vec![Binding::Key(KeyCode::ControlLeft)])
+            .with_axis_2d("move", Axis2D::new(vec![Axis2DBinding::wasd()]));
+
+        let mut input = input(&[KeyCode::ControlLeft, KeyCode::KeyS]);
+        assert!(map.is_just_pressed(&input, "save"));
+        assert!(!map.is_pressed(&input, "back") && !map.is_just_pressed(&input, "back"));
+        assert_eq!(map.get_axis_2d(&input, "move"), Vec2::ZERO);
+        assert!(map.is_pressed(&input, "crouch"));
+
+        // Letting go of the chord doesn't release what never got pressed.
+        input.end_frame();
+        input.handle_key(KeyCode::KeyS, false);
+        assert!(map.is_just_released(&input, "save"));
+        assert!(!map.is_just_released(&input, "back"));
+
+        input.end_frame();
+        input.handle_key(KeyCode::ControlLeft, false);
+        input.end_frame();
+        input.handle_key(KeyCode::KeyS, true);
+        assert!(map.is_just_pressed(&input, "back"));
+        assert!(!map.is_pressed(&input, "save"));
+        assert_eq!(map.get_axis_2d(&input, "move"), Vec2::new(0.0, -1.0));
+    }
+}
@@ -0,0 +1,476 @@
+use std::{collections::{BTreeMap, HashSet}, path::Path, sync::Arc};
+use anyhow::{Error, anyhow, bail};
+use image::{DynamicImage, GenericImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+use crate::{CatEngine, surface::{Surface, SurfaceAttributes}, util::extension};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UvRect {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl UvRect {
+    // The whole texture.
+    pub const FULL: UvRect = UvRect { min_x: 0.0, min_y: 0.0, max_x: 1.0, max_y: 1.0 };
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasEntry {
+    pub page: usize,
+    // Pixel rectangle of the image on its page, without padding or extrusion.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv: UvRect,
+}
+
+// Everything needed to rebuild an atlas without packing it again. Page file names
+// are relative to the manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasManifest {
+    pub page_width: u32,
+    pub page_height: u32,
+    pub padding: u32,
+    pub extrude: u32,
+    pub pages: Vec<String>,
+    pub entries: BTreeMap<String, AtlasEntry>,
+}
+
+impl AtlasManifest {
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, Error> {
+        Ok(ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, Error> {
+        Ok(ron::from_str(ron)?)
+    }
+
+    // Picks JSON or RON from the file extension.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        match extension(path).as_str() {
+            "json" => Self::from_json(&text),
+            "ron" => Self::from_ron(&text),
+            other => bail!("unknown atlas manifest extension {:?}", other),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let text = match extension(path).as_str() {
+            "json" => self.to_json()?,
+            "ron" => self.to_ron()?,
+            other => bail!("unknown atlas manifest extension {:?}", other),
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+// Collects images and packs them into pages with a skyline packer. Each image gets
+// its border pixels repeated `extrude` times around it and `padding` empty pixels
+// between neighbours so linear filtering and mipmaps don't bleed between sprites.
+pub struct TextureAtlasBuilder {
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+    extrude: u32,
+    images: Vec<(String, DynamicImage)>,
+}
+
+impl TextureAtlasBuilder {
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            page_width,
+            page_height,
+            padding: 2,
+            extrude: 1,
+            images: vec![],
+        }
+    }
+
+    pub fn set_padding(&mut self, padding: u32) { self.padding = padding; }
+    pub fn set_extrude(&mut self, extrude: u32) { self.extrude = extrude; }
+
+    pub fn add_image(&mut self, name: &str, image: DynamicImage) {
+        self.images.push((name.to_owned(), image));
+    }
+
+    pub fn add_file(&mut self, name: &str, file: &str) -> Result<(), Error> {
+        self.add_image(name, image::open(file)?);
+        Ok(())
+    }
+
+    pub fn add_memory(&mut self, name: &str, bytes: &[u8]) -> Result<(), Error> {
+        self.add_image(name, image::load_from_memory(bytes)?);
+        Ok(())
+    }
+
+    // Packs on the CPU only, the result can be saved at build time and loaded with
+    // TextureAtlas::open later.
+    pub fn pack(self) -> Result<PackedAtlas, Error> {
+        let mut names = HashSet::new();
+        if let Some((name, _)) = self.images.iter().find(|(name, _)| !names.insert(name.as_str())) {
+            bail!("the atlas already has an image named {:?}", name);
+        }
+
+        let border = self.extrude * 2 + self.padding;
+        let mut images = self.images;
+        // Tallest first keeps the skyline flat.
+        images.sort_by(|a, b| b.1.height().cmp(&a.1.height()).then(b.1.width().cmp(&a.1.width())));
+
+        let mut skylines: Vec<Skyline> = vec![];
+        let mut pages: Vec<RgbaImage> = vec![];
+        let mut entries = BTreeMap::new();
+
+        for (name, image) in images {
+            let (width, height) = (image.width(), image.height());
+            if width == 0 || height == 0 {
+                bail!("{:?} is an empty image", name);
+            }
+            let (cell_width, cell_height) = (width + border, height + border);
+            if cell_width > self.page_width || cell_height > self.page_height {
+                bail!("{:?} ({}x{}) does not fit on a {}x{} atlas page", name, width, height, self.page_width, self.page_height);
+            }
+
+            let placed = skylines.iter_mut().enumerate().find_map(|(page, skyline)| {
+                skyline.insert(cell_width, cell_height).map(|position| (page, position))
+            });
+            let (page, (cell_x, cell_y)) = match placed {
+                Some(placed) => placed,
+                None => {
+                    let mut skyline = Skyline::new(self.page_width, self.page_height);
+                    let position = skyline.insert(cell_width, cell_height).unwrap();
+                    skylines.push(skyline);
+                    pages.push(RgbaImage::new(self.page_width, self.page_height));
+                    (pages.len() - 1, position)
+                }
+            };
+
+            let (x, y) = (cell_x + self.extrude, cell_y + self.extrude);
+            blit_extruded(&mut pages[page], &image, x, y, self.extrude);
+
+            let uv = UvRect {
+                min_x: x as f32 / self.page_width as f32,
+                min_y: y as f32 / self.page_height as f32,
+                max_x: (x + width) as f32 / self.page_width as f32,
+                max_y: (y + height) as f32 / self.page_height as f32,
+            };
+            entries.insert(name, AtlasEntry { page, x, y, width, height, uv });
+        }
+
+        Ok(PackedAtlas {
+            manifest: AtlasManifest {
+                page_width: self.page_width,
+                page_height: self.page_height,
+                padding: self.padding,
+                extrude: self.extrude,
+                pages: vec![],
+                entries,
+            },
+            pages,
+        })
+    }
+
+    pub fn build(self, catengine: &CatEngine, args: impl Fn() -> SurfaceAttributes) -> Result<TextureAtlas, Error> {
+        Ok(self.pack()?.upload(catengine, args))
+    }
+}
+
+// Packed pages still on the CPU.
+pub struct PackedAtlas {
+    pub manifest: AtlasManifest,
+    pub pages: Vec<RgbaImage>,
+}
+
+impl PackedAtlas {
+    // Writes the pages as `<name>_<page>.png` next to the manifest, the manifest
+    // format is picked from its extension.
+    pub fn save(&mut self, manifest_path: &str) -> Result<(), Error> {
+        let manifest_path = Path::new(manifest_path);
+        let directory = manifest_path.parent().unwrap_or(Path::new(""));
+        let stem = manifest_path.file_stem().and_then(|stem| stem.to_str()).ok_or(anyhow!("bad manifest path {:?}", manifest_path))?;
+
+        self.manifest.pages.clear();
+        for (index, page) in self.pages.iter().enumerate() {
+            let file_name = format!("{}_{}.png", stem, index);
+            page.save(directory.join(&file_name))?;
+            self.manifest.pages.push(file_name);
+        }
+        self.manifest.save(&manifest_path.to_string_lossy())
+    }
+
+    // Every page gets its own attributes, so args is called once per page.
+    pub fn upload(self, catengine: &CatEngine, args: impl Fn() -> SurfaceAttributes) -> TextureAtlas {
+        let pages = self.pages.into_iter()
+            .map(|page| Arc::new(Surface::from_image(DynamicImage::ImageRgba8(page), catengine, args())))
+            .collect();
+        TextureAtlas { manifest: self.manifest, pages }
+    }
+}
+
+pub struct TextureAtlas {
+    manifest: AtlasManifest,
+    pages: Vec<Arc<Surface>>,
+}
+
+impl TextureAtlas {
+    // Loads an atlas saved with PackedAtlas::save.
+    pub fn open(manifest_path: &str, catengine: &CatEngine, args: impl Fn() -> SurfaceAttributes) -> Result<Self, Error> {
+        let manifest = AtlasManifest::open(manifest_path)?;
+        let directory = Path::new(manifest_path).parent().unwrap_or(Path::new(""));
+        let mut pages = vec![];
+        for page in &manifest.pages {
+            let image = image::open(directory.join(page))?;
+            pages.push(Arc::new(Surface::from_image(image, catengine, args())));
+        }
+        Ok(Self { manifest, pages })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AtlasEntry> {
+        self.manifest.entries.get(name)
+    }
+
+    pub fn get_uv(&self, name: &str) -> Option<UvRect> {
+        self.get(name).map(|entry| entry.uv)
+    }
+
+    // The page surface and uv rectangle of an image, ready for a sprite.
+    pub fn get_sprite(&self, name: &str) -> Option<(Arc<Surface>, UvRect)> {
+        self.get(name).map(|entry| (self.pages[entry.page].clone(), entry.uv))
+    }
+
+    pub fn get_page(&self, page: usize) -> &Arc<Surface> {
+        &self.pages[page]
+    }
+
+    pub fn get_page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn get_manifest(&self) -> &AtlasManifest {
+        &self.manifest
+    }
+}
+
+// Skyline bottom-left packer, each node is a horizontal segment (x, y, width) of the
+// current top edge of the packed rectangles.
+struct Skyline {
+    width: u32,
+    height: u32,
+    nodes: Vec<(u32, u32, u32)>,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Self {
+        Self { width, height, nodes: vec![(0, 0, width)] }
+    }
+
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        for index in 0..self.nodes.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                let x = self.nodes[index].0;
+                if best.is_none_or(|(_, best_x, best_y)| (y, x) < (best_y, best_x)) {
+                    best = Some((index, x, y));
+                }
+            }
+        }
+
+        let (index, x, y) = best?;
+        self.nodes.insert(index, (x, y + height, width));
+
+        // Shrink or drop the nodes now covered by the new one.
+        let right = x + width;
+        let next = index + 1;
+        while next < self.nodes.len() && self.nodes[next].0 < right {
+            let (node_x, node_y, node_width) = self.nodes[next];
+            let node_right = node_x + node_width;
+            if node_right <= right {
+                self.nodes.remove(next);
+            } else {
+                self.nodes[next] = (right, node_y, node_right - right);
+                break;
+            }
+        }
+
+        self.nodes.dedup_by(|b, a| {
+            if a.1 == b.1 {
+                a.2 += b.2;
+                true
+            } else {
+                false
+            }
+        });
+        Some((x, y))
+    }
+
+    // Lowest y a rectangle starting at the node can sit at, if it fits.
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.nodes[index].0;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut covered = 0;
+        for &(_, node_y, node_width) in &self.nodes[index..] {
+            y = y.max(node_y);
+            if y + height > self.height {
+                return None;
+            }
+            covered += node_width;
+            if covered >= width {
+                return Some(y);
+            }
+        }
+        None
+    }
+}
+
+fn blit_extruded(page: &mut RgbaImage, image: &DynamicImage, x: u32, y: u32, extrude: u32) {
+    let (width, height) = image.dimensions();
+    let rgba = image.to_rgba8();
+    page.copy_from(&rgba, x, y).unwrap();
+
+    let extrude = extrude as i64;
+    for dy in -extrude..height as i64 + extrude {
+        for dx in -extrude..width as i64 + extrude {
+            if dx >= 0 && dy >= 0 && dx < width as i64 && dy < height as i64 {
+                continue;
+            }
+            let source = rgba.get_pixel(dx.clamp(0, width as i64 - 1) as u32, dy.clamp(0, height as i64 - 1) as u32);
+            page.put_pixel((x as i64 + dx) as u32, (y as i64 + dy) as u32, *source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn overlaps(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    }
+
+    #[test]
+    fn skyline_places_bottom_left() {
+        let mut skyline = Skyline::new(10, 10);
+        assert_eq!(skyline.insert(4, 4), Some((0, 0)));
+        assert_eq!(skyline.insert(4, 4), Some((4, 0)));
+        assert_eq!(skyline.insert(4, 4), Some((0, 4)));
+        // The lowest spot wins over the leftmost one.
+        assert_eq!(skyline.insert(2, 2), Some((8, 0)));
+        assert_eq!(skyline.insert(11, 1), None);
+        assert_eq!(skyline.insert(4, 7), None);
+    }
+
+    #[test]
+    fn skyline_rectangles_never_overlap() {
+        let mut skyline = Skyline::new(64, 64);
+        let mut placed = vec![];
+        for i in 0..40 {
+            let (width, height) = (3 + i * 7 % 9, 2 + i * 5 % 11);
+            if let Some((x, y)) = skyline.insert(width, height) {
+                assert!(x + width <= 64 && y + height <= 64);
+                let rect = (x, y, width, height);
+                assert!(placed.iter().all(|other| !overlaps(rect, *other)), "{:?} overlaps", rect);
+                placed.push(rect);
+            }
+        }
+        assert!(placed.len() > 20);
+    }
+
+    fn checker() -> DynamicImage {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+        image.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        image.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn pack_extrudes_and_pads() {
+        let mut builder = TextureAtlasBuilder::new(16, 16);
+        builder.set_padding(2);
+        builder.set_extrude(1);
+        builder.add_image("a", checker());
+        builder.add_image("b", checker());
+        let atlas = builder.pack().unwrap();
+
+        let a = &atlas.manifest.entries["a"];
+        let b = &atlas.manifest.entries["b"];
+        assert_eq!((a.page, a.x, a.y, a.width, a.height), (0, 1, 1, 2, 2));
+        // Each cell is the image plus 1 extruded pixel on both sides and 2 of padding.
+        assert_eq!((b.x, b.y), (7, 1));
+        assert_eq!(a.uv, UvRect { min_x: 1.0 / 16.0, min_y: 1.0 / 16.0, max_x: 3.0 / 16.0, max_y: 3.0 / 16.0 });
+
+        let page = &atlas.pages[0];
+        assert_eq!(*page.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(*page.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*page.get_pixel(3, 0), Rgba([0, 255, 0, 255]));
+        assert_eq!(*page.get_pixel(0, 3), Rgba([0, 0, 255, 255]));
+        assert_eq!(*page.get_pixel(3, 3), Rgba([255, 255, 255, 255]));
+        assert_eq!(*page.get_pixel(4, 1), Rgba([0, 0, 0, 0]));
+        assert_eq!(*page.get_pixel(6, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn pack_starts_new_pages() {
+        let mut builder = TextureAtlasBuilder::new(8, 8);
+        builder.set_padding(0);
+        builder.set_extrude(0);
+        for name in ["a", "b", "c"] {
+            builder.add_image(name, DynamicImage::new_rgba8(6, 6));
+        }
+        let atlas = builder.pack().unwrap();
+        assert_eq!(atlas.pages.len(), 3);
+    }
+
+    #[test]
+    fn pack_rejects_bad_images() {
+        let mut builder = TextureAtlasBuilder::new(8, 8);
+        builder.add_image("big", DynamicImage::new_rgba8(8, 8));
+        assert!(builder.pack().is_err());
+
+        let mut builder = TextureAtlasBuilder::new(8, 8);
+        builder.add_image("empty", DynamicImage::new_rgba8(0, 4));
+        assert!(builder.pack().is_err());
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected_before_packing() {
+        let mut builder = TextureAtlasBuilder::new(8, 8);
+        // Would fail to fit, but the duplicate name is reported first.
+        builder.add_image("a", DynamicImage::new_rgba8(16, 16));
+        builder.add_image("a", checker());
+        let error = builder.pack().err().unwrap();
+        assert!(error.to_string().contains("already has an image"), "{}", error);
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let mut builder = TextureAtlasBuilder::new(16, 16);
+        builder.add_image("a", checker());
+        builder.add_image("b", checker());
+        let mut manifest = builder.pack().unwrap().manifest;
+        manifest.pages = vec!["atlas_0.png".to_owned()];
+
+        assert_eq!(AtlasManifest::from_json(&manifest.to_json().unwrap()).unwrap(), manifest);
+        assert_eq!(AtlasManifest::from_ron(&manifest.to_ron().unwrap()).unwrap(), manifest);
+        assert!(AtlasManifest::from_json("{").is_err());
+    }
+}
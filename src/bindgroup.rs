@@ -0,0 +1,166 @@
+use std::sync::Arc;
+use crate::{CatEngine, buffer::Buffer, sampler::Sampler, surface::Surface};
+
+pub use wgpu::{BindGroupLayoutEntry, ShaderStages, BindingType, BufferBindingType, BindGroupEntry, BindingResource, BindGroupLayoutDescriptor, BindGroupLayout, BindGroupDescriptor, BindGroup, BufferBinding};
+pub use wgpu::{SamplerBindingType, TextureSampleType, TextureViewDimension};
+
+// Builds a bind group out of engine resources, binding them in the order they are
+// added. The layout is inferred from the resources and shared between every bind
+// group with the same signature, so it can also be handed to Shader::new.
+pub struct BindGroupBuilder<'a> {
+    label: Option<&'a str>,
+    visibility: ShaderStages,
+    entries: Vec<(BindGroupLayoutEntry, BindingResource<'a>)>,
+}
+
+impl Default for BindGroupBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> BindGroupBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            label: None,
+            visibility: ShaderStages::VERTEX_FRAGMENT,
+            entries: vec![],
+        }
+    }
+
+    pub fn with_label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    // Applies to the entries added after this call.
+    pub fn with_visibility(mut self, visibility: ShaderStages) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    // Binds the texture view and then its sampler, taking two binding slots. The binding
+    // types follow the surface, e.g. depth surfaces with a comparison sampler bind for
+    // textureSampleCompare.
+    pub fn surface(self, surface: &'a Surface) -> Self {
+        check_sampler(surface.get_format(), surface.get_sample_type(), &surface.get_shared_sampler());
+        self.texture(surface).sampler_of(surface)
+    }
+
+    pub fn texture(mut self, surface: &'a Surface) -> Self {
+        self.push(
+            BindingType::Texture {
+                sample_type: surface.get_sample_type(),
+                view_dimension: surface.get_view_dimension(),
+                multisampled: surface.get_sample_count() > 1,
+            },
+            BindingResource::TextureView(surface.get_view()),
+        );
+        self
+    }
+
+    pub fn sampler(mut self, sampler: &'a Sampler) -> Self {
+        self.push(sampler_binding_type(sampler), BindingResource::Sampler(sampler.get_sampler()));
+        self
+    }
+
+    fn sampler_of(mut self, surface: &'a Surface) -> Self {
+        self.push(sampler_binding_type(&surface.get_shared_sampler()), BindingResource::Sampler(surface.get_sampler()));
+        self
+    }
+
+    // Uniform buffers bind as uniforms, anything else with STORAGE as a read only storage buffer.
+    pub fn buffer(self, buffer: &'a Buffer) -> Self {
+        self.buffer_with_access(buffer, true)
+    }
+
+    // Writable storage buffers are left out of the vertex stage, which can't write to them.
+    pub fn buffer_with_access(mut self, buffer: &'a Buffer, read_only: bool) -> Self {
+        let ty = if buffer.get_buffer().usage().contains(wgpu::BufferUsages::UNIFORM) {
+            BufferBindingType::Uniform
+        } else {
+            BufferBindingType::Storage { read_only }
+        };
+        let visibility = match ty {
+            BufferBindingType::Storage { read_only: false } => self.visibility - ShaderStages::VERTEX,
+            _ => self.visibility,
+        };
+        assert!(!visibility.is_empty(), "a writable storage buffer can't be bound to the vertex stage only");
+
+        self.push_with_visibility(
+            visibility,
+            BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            buffer.as_entire_binding(),
+        );
+        self
+    }
+
+    fn push(&mut self, ty: BindingType, resource: BindingResource<'a>) {
+        self.push_with_visibility(self.visibility, ty, resource);
+    }
+
+    fn push_with_visibility(&mut self, visibility: ShaderStages, ty: BindingType, resource: BindingResource<'a>) {
+        let layout_entry = BindGroupLayoutEntry {
+            binding: self.entries.len() as u32,
+            visibility,
+            ty,
+            count: None,
+        };
+        self.entries.push((layout_entry, resource));
+    }
+
+    pub fn layout(&self, catengine: &CatEngine) -> Arc<BindGroupLayout> {
+        let layout_entries: Vec<BindGroupLayoutEntry> = self.entries.iter().map(|(entry, _)| *entry).collect();
+        catengine.get_bind_group_layout(&layout_entries)
+    }
+
+    pub fn build(self, catengine: &CatEngine) -> Arc<BindGroup> {
+        self.build_with_layout(catengine).0
+    }
+
+    pub fn build_with_layout(self, catengine: &CatEngine) -> (Arc<BindGroup>, Arc<BindGroupLayout>) {
+        let layout = self.layout(catengine);
+        let entries: Vec<BindGroupEntry> = self.entries.into_iter().map(|(layout_entry, resource)| BindGroupEntry {
+            binding: layout_entry.binding,
+            resource,
+        }).collect();
+
+        let bind_group = catengine.create_bind_group(BindGroupDescriptor {
+            label: self.label,
+            layout: &layout,
+            entries: &entries,
+        });
+        (bind_group, layout)
+    }
+}
+
+fn sampler_binding_type(sampler: &Sampler) -> BindingType {
+    BindingType::Sampler(if sampler.is_comparison() {
+        SamplerBindingType::Comparison
+    } else if sampler.is_filtering() {
+        SamplerBindingType::Filtering
+    } else {
+        SamplerBindingType::NonFiltering
+    })
+}
+
+// wgpu only reports a mismatch between a texture and its sampler when the bind group is
+// made, this says what to change instead.
+pub(crate) fn check_sampler(format: wgpu::TextureFormat, sample_type: TextureSampleType, sampler: &Sampler) {
+    match sample_type {
+        TextureSampleType::Float { filterable } => {
+            assert!(!sampler.is_comparison(), "{:?} surfaces can't use a comparison sampler", format);
+            assert!(filterable || !sampler.is_filtering(), "{:?} can't be filtered on this device, give the surface a non filtering sampler like SamplerPreset::PixelArt", format);
+        }
+        TextureSampleType::Depth => {
+            assert!(sampler.is_comparison() || !sampler.is_filtering(), "depth surfaces need a comparison or non filtering sampler");
+        }
+        TextureSampleType::Sint | TextureSampleType::Uint => {
+            panic!("{:?} surfaces can't be sampled, bind them with BindGroupBuilder::texture and use textureLoad", format);
+        }
+    }
+}
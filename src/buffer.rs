@@ -20,6 +20,20 @@ impl Buffer {
         Self { buffer }
     }
 
+    // Uninitialised buffer of the given size, meant to be filled with CatEngine::write_buffer.
+    pub fn new_empty(catengine: &CatEngine, size: u64, label: Option<&str>, usage: BufferUsages) -> Self {
+        let buffer = catengine.device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label,
+                size,
+                usage,
+                mapped_at_creation: false,
+            }
+        );
+
+        Self { buffer }
+    }
+
     pub fn get_buffer(&self) -> &wgpu::Buffer {
         &self.buffer
     }
@@ -0,0 +1,749 @@
+use std::sync::Arc;
+use wgpu::{BindGroup, BindGroupLayout};
+use winit::{event::{MouseButton, MouseScrollDelta, WindowEvent}, keyboard::{KeyCode, PhysicalKey}};
+use crate::{CatEngine, bindgroup::BindGroupBuilder, buffer::{Buffer, BufferUsages}, math::{Coordinate2D, Coordinate3D, Mat4, Ray, Vec2, Vec3, Vec4}};
+
+// The math of a Camera2D, without the uniform buffer. Screen positions are pixels of the
+// render size (CatEngine::width and height) from the top left. Those only match winit
+// cursor positions without an internal resolution, see CatEngine::window_to_render.
+#[derive(Debug, Clone)]
+pub struct View2D {
+    pub position: Coordinate2D,
+    pub zoom: f64,
+    pub rotation: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl View2D {
+    pub fn get_view_projection(&self) -> Mat4 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let scale_x = 2.0 * self.zoom / self.width;
+        let scale_y = -2.0 * self.zoom / self.height;
+
+        Mat4::from_cols(
+            Vec4::new((scale_x * cos) as f32, (-scale_y * sin) as f32, 0.0, 0.0),
+            Vec4::new((scale_x * sin) as f32, (scale_y * cos) as f32, 0.0, 0.0),
+            Vec4::Z,
+            Vec4::new(
+                (-scale_x * (cos * self.position.x + sin * self.position.y)) as f32,
+                (-scale_y * (cos * self.position.y - sin * self.position.x)) as f32,
+                0.0,
+                1.0,
+            ),
+        )
+    }
+
+    pub fn screen_to_world(&self, screen: Coordinate2D) -> Coordinate2D {
+        let (sin, cos) = self.rotation.sin_cos();
+        let dx = (screen.x - self.width / 2.0) / self.zoom;
+        let dy = (screen.y - self.height / 2.0) / self.zoom;
+        Coordinate2D {
+            x: self.position.x + dx * cos - dy * sin,
+            y: self.position.y + dx * sin + dy * cos,
+        }
+    }
+
+    pub fn world_to_screen(&self, world: Coordinate2D) -> Coordinate2D {
+        let (sin, cos) = self.rotation.sin_cos();
+        let dx = world.x - self.position.x;
+        let dy = world.y - self.position.y;
+        Coordinate2D {
+            x: (dx * cos + dy * sin) * self.zoom + self.width / 2.0,
+            y: (dy * cos - dx * sin) * self.zoom + self.height / 2.0,
+        }
+    }
+}
+
+// 2D camera in pixel sized world units with y going down, the same space the sprite
+// batcher uses. The position is the world point shown at the center of the render
+// target, screen positions are render pixels like in View2D.
+pub struct Camera2D {
+    position: Coordinate2D,
+    zoom: f64,
+    rotation: f64,
+    pixel_snapping: bool,
+    bounds: Option<(Coordinate2D, Coordinate2D)>,
+    width: f64,
+    height: f64,
+    buffer: Arc<Buffer>,
+    bind_group: Arc<BindGroup>,
+    bind_group_layout: Arc<BindGroupLayout>,
+}
+
+impl Camera2D {
+    pub fn new(catengine: &CatEngine) -> Self {
+        let buffer = Arc::new(Buffer::new_empty(catengine, 64, Some("camera 2d uniform"), BufferUsages::UNIFORM | BufferUsages::COPY_DST));
+        let (bind_group, bind_group_layout) = BindGroupBuilder::new()
+            .with_label("camera 2d bind group")
+            .buffer(&buffer)
+            .build_with_layout(catengine);
+
+        let mut camera = Self {
+            position: Coordinate2D { x: catengine.width as f64 / 2.0, y: catengine.height as f64 / 2.0 },
+            zoom: 1.0,
+            rotation: 0.0,
+            pixel_snapping: false,
+            bounds: None,
+            width: catengine.width.max(1) as f64,
+            height: catengine.height.max(1) as f64,
+            buffer,
+            bind_group,
+            bind_group_layout,
+        };
+        camera.update(catengine);
+        camera
+    }
+
+    pub fn set_position(&mut self, position: Coordinate2D) { self.position = position; }
+    pub fn set_zoom(&mut self, zoom: f64) { self.zoom = zoom.max(f64::EPSILON); }
+    // Radians, clockwise like sprites.
+    pub fn set_rotation(&mut self, rotation: f64) { self.rotation = rotation; }
+    // Rounds the position to whole screen pixels so pixel art doesn't shimmer while moving.
+    pub fn set_pixel_snapping(&mut self, pixel_snapping: bool) { self.pixel_snapping = pixel_snapping; }
+    // Keeps the visible area inside the min and max corners, rotation is not taken into account.
+    pub fn set_bounds(&mut self, bounds: Option<(Coordinate2D, Coordinate2D)>) { self.bounds = bounds; }
+
+    pub fn get_position(&self) -> Coordinate2D {
+        self.position.clone()
+    }
+
+    pub fn get_zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    pub fn get_rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    // Moves the camera by a distance in render pixels, e.g. a mouse drag.
+    pub fn pan(&mut self, screen_dx: f64, screen_dy: f64) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (dx, dy) = (screen_dx / self.zoom, screen_dy / self.zoom);
+        self.position.x -= dx * cos - dy * sin;
+        self.position.y -= dx * sin + dy * cos;
+    }
+
+    // Zooms while keeping the world point under the screen position in place.
+    pub fn zoom_at(&mut self, factor: f64, screen: Coordinate2D) {
+        let before = self.screen_to_world(screen.clone());
+        self.set_zoom(self.zoom * factor);
+        let after = self.screen_to_world(screen);
+        self.position.x += before.x - after.x;
+        self.position.y += before.y - after.y;
+    }
+
+    // Takes the current window size, clamps to the bounds and writes the uniform. Call it
+    // once per frame after moving the camera.
+    pub fn update(&mut self, catengine: &CatEngine) {
+        self.width = catengine.width.max(1) as f64;
+        self.height = catengine.height.max(1) as f64;
+
+        if let Some((min, max)) = &self.bounds {
+            let half_width = self.width / (2.0 * self.zoom);
+            let half_height = self.height / (2.0 * self.zoom);
+            self.position.x = clamp_axis(self.position.x, min.x, max.x, half_width);
+            self.position.y = clamp_axis(self.position.y, min.y, max.y, half_height);
+        }
+
+        catengine.queue.write_buffer(self.buffer.get_buffer(), 0, self.get_view_projection().as_bytes());
+    }
+
+    fn snapped_position(&self) -> Coordinate2D {
+        if self.pixel_snapping {
+            Coordinate2D {
+                x: (self.position.x * self.zoom).round() / self.zoom,
+                y: (self.position.y * self.zoom).round() / self.zoom,
+            }
+        } else {
+            self.position.clone()
+        }
+    }
+
+    // The view as of the last update, with the snapped position.
+    pub fn get_view_2d(&self) -> View2D {
+        View2D {
+            position: self.snapped_position(),
+            zoom: self.zoom,
+            rotation: self.rotation,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    // Can go straight into a uniform or SpriteBatcher::set_projection.
+    pub fn get_view_projection(&self) -> Mat4 {
+        self.get_view_2d().get_view_projection()
+    }
+
+    pub fn screen_to_world(&self, screen: Coordinate2D) -> Coordinate2D {
+        self.get_view_2d().screen_to_world(screen)
+    }
+
+    pub fn world_to_screen(&self, world: Coordinate2D) -> Coordinate2D {
+        self.get_view_2d().world_to_screen(world)
+    }
+
+    // For winit cursor positions, None outside the letterboxed area.
+    pub fn window_to_world(&self, catengine: &CatEngine, window: Coordinate2D) -> Option<Coordinate2D> {
+        catengine.window_to_render(window).map(|screen| self.screen_to_world(screen))
+    }
+
+    pub fn get_buffer(&self) -> &Arc<Buffer> {
+        &self.buffer
+    }
+
+    pub fn get_bind_group(&self) -> &Arc<BindGroup> {
+        &self.bind_group
+    }
+
+    pub fn get_bind_group_layout(&self) -> &Arc<BindGroupLayout> {
+        &self.bind_group_layout
+    }
+}
+
+// Centers the view when it is wider than the bounds.
+fn clamp_axis(value: f64, min: f64, max: f64, half_extent: f64) -> f64 {
+    if max - min <= half_extent * 2.0 {
+        (min + max) / 2.0
+    } else {
+        value.clamp(min + half_extent, max - half_extent)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // Vertical field of view in radians.
+    Perspective { fov_y: f64, near: f64, far: f64 },
+    // Height of the view volume in world units, the width follows the aspect ratio.
+    Orthographic { height: f64, near: f64, far: f64 },
+}
+
+// The math of a Camera3D, without the uniform buffer. Right handed, y up. A yaw and pitch
+// of zero looks down -z, positive yaw turns right and positive pitch looks up.
+#[derive(Debug, Clone)]
+pub struct View3D {
+    position: Coordinate3D,
+    yaw: f64,
+    pitch: f64,
+    projection: Projection,
+    aspect: f64,
+}
+
+impl View3D {
+    pub fn new(projection: Projection, aspect: f64) -> Self {
+        Self {
+            position: Coordinate3D { x: 0.0, y: 0.0, z: 0.0 },
+            yaw: 0.0,
+            pitch: 0.0,
+            projection,
+            aspect,
+        }
+    }
+
+    pub fn set_position(&mut self, position: Coordinate3D) { self.position = position; }
+    pub fn set_yaw(&mut self, yaw: f64) { self.yaw = yaw; }
+    // Clamped just short of straight up or down so the view never flips.
+    pub fn set_pitch(&mut self, pitch: f64) { self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH); }
+    pub fn set_projection(&mut self, projection: Projection) { self.projection = projection; }
+    // Width over height of the render size.
+    pub fn set_aspect(&mut self, aspect: f64) { self.aspect = aspect; }
+
+    pub fn get_position(&self) -> Coordinate3D {
+        self.position.clone()
+    }
+
+    pub fn get_yaw(&self) -> f64 {
+        self.yaw
+    }
+
+    pub fn get_pitch(&self) -> f64 {
+        self.pitch
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn get_aspect(&self) -> f64 {
+        self.aspect
+    }
+
+    pub fn look_at(&mut self, target: Coordinate3D) {
+        let direction = Vec3::from(target) - Vec3::from(self.position.clone());
+        let horizontal = (direction.x * direction.x + direction.z * direction.z).sqrt();
+        if horizontal == 0.0 && direction.y == 0.0 {
+            return;
+        }
+        self.yaw = direction.x.atan2(-direction.z) as f64;
+        self.set_pitch(direction.y.atan2(horizontal) as f64);
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = (self.yaw as f32).sin_cos();
+        let (sin_pitch, cos_pitch) = (self.pitch as f32).sin_cos();
+        Vec3::new(cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw)
+    }
+
+    pub fn get_right(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = (self.yaw as f32).sin_cos();
+        Vec3::new(cos_yaw, 0.0, sin_yaw)
+    }
+
+    pub fn get_view(&self) -> Mat4 {
+        Mat4::look_to_rh(Vec3::from(self.position.clone()), self.get_forward(), Vec3::Y)
+    }
+
+    pub fn get_projection_matrix(&self) -> Mat4 {
+        let aspect = self.aspect as f32;
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => Mat4::perspective_rh(fov_y as f32, aspect, near as f32, far as f32),
+            Projection::Orthographic { height, near, far } => {
+                let (half_width, half_height) = (height as f32 * aspect / 2.0, height as f32 / 2.0);
+                Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, near as f32, far as f32)
+            }
+        }
+    }
+
+    pub fn get_view_projection(&self) -> Mat4 {
+        self.get_projection_matrix() * self.get_view()
+    }
+}
+
+// View3D with its uniform, the aspect ratio follows the render size. Screen positions
+// are render pixels, see CatEngine::window_to_render and window_ray.
+pub struct Camera3D {
+    view: View3D,
+    buffer: Arc<Buffer>,
+    bind_group: Arc<BindGroup>,
+    bind_group_layout: Arc<BindGroupLayout>,
+}
+
+impl Camera3D {
+    pub fn new(catengine: &CatEngine, projection: Projection) -> Self {
+        // view projection matrix followed by the camera position as a vec4
+        let buffer = Arc::new(Buffer::new_empty(catengine, 80, Some("camera 3d uniform"), BufferUsages::UNIFORM | BufferUsages::COPY_DST));
+        let (bind_group, bind_group_layout) = BindGroupBuilder::new()
+            .with_label("camera 3d bind group")
+            .buffer(&buffer)
+            .build_with_layout(catengine);
+
+        let mut camera = Self {
+            view: View3D::new(projection, 1.0),
+            buffer,
+            bind_group,
+            bind_group_layout,
+        };
+        camera.update(catengine);
+        camera
+    }
+
+    pub fn set_position(&mut self, position: Coordinate3D) { self.view.set_position(position); }
+    pub fn set_yaw(&mut self, yaw: f64) { self.view.set_yaw(yaw); }
+    pub fn set_pitch(&mut self, pitch: f64) { self.view.set_pitch(pitch); }
+    pub fn set_projection(&mut self, projection: Projection) { self.view.set_projection(projection); }
+
+    pub fn get_view_3d(&self) -> &View3D {
+        &self.view
+    }
+
+    pub fn get_view_3d_mut(&mut self) -> &mut View3D {
+        &mut self.view
+    }
+
+    pub fn get_position(&self) -> Coordinate3D {
+        self.view.get_position()
+    }
+
+    pub fn get_yaw(&self) -> f64 {
+        self.view.get_yaw()
+    }
+
+    pub fn get_pitch(&self) -> f64 {
+        self.view.get_pitch()
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.view.get_projection()
+    }
+
+    pub fn get_aspect(&self) -> f64 {
+        self.view.get_aspect()
+    }
+
+    pub fn look_at(&mut self, target: Coordinate3D) {
+        self.view.look_at(target);
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        self.view.get_forward()
+    }
+
+    pub fn get_right(&self) -> Vec3 {
+        self.view.get_right()
+    }
+
+    // Picks up the aspect ratio of the render size and writes the uniform. Call it once
+    // per frame after moving the camera.
+    pub fn update(&mut self, catengine: &CatEngine) {
+        self.view.set_aspect(catengine.width.max(1) as f64 / catengine.height.max(1) as f64);
+
+        let position = Vec3::from(self.view.get_position()).extend(1.0);
+        let contents = [self.get_view_projection().as_bytes(), position.as_bytes()].concat();
+        catengine.queue.write_buffer(self.buffer.get_buffer(), 0, &contents);
+    }
+
+    pub fn get_view(&self) -> Mat4 {
+        self.view.get_view()
+    }
+
+    pub fn get_projection_matrix(&self) -> Mat4 {
+        self.view.get_projection_matrix()
+    }
+
+    pub fn get_view_projection(&self) -> Mat4 {
+        self.view.get_view_projection()
+    }
+
+    // Picking ray under a winit cursor position, None outside the letterboxed area.
+    pub fn window_ray(&self, catengine: &CatEngine, window: Coordinate2D) -> Option<Ray> {
+        let screen = catengine.window_to_render(window)?;
+        let size = Vec2::new(catengine.width.max(1) as f32, catengine.height.max(1) as f32);
+        Some(Ray::from_screen(&self.get_view_projection(), screen.into(), size))
+    }
+
+    pub fn get_buffer(&self) -> &Arc<Buffer> {
+        &self.buffer
+    }
+
+    pub fn get_bind_group(&self) -> &Arc<BindGroup> {
+        &self.bind_group
+    }
+
+    pub fn get_bind_group_layout(&self) -> &Arc<BindGroupLayout> {
+        &self.bind_group_layout
+    }
+}
+
+const MAX_PITCH: f64 = std::f64::consts::FRAC_PI_2 - 0.001;
+
+// Editor style fly camera: hold the right mouse button to look around, WASD to move,
+// E and Q to go up and down, shift to go faster. Feed it every window event from
+// Program::handle_event and call update once per frame.
+pub struct FlyController {
+    pub speed: f64,
+    pub fast_multiplier: f64,
+    // Radians per pixel of cursor movement.
+    pub sensitivity: f64,
+    looking: bool,
+    cursor: Option<(f64, f64)>,
+    look_delta: (f64, f64),
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    fast: bool,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlyController {
+    pub fn new() -> Self {
+        Self {
+            speed: 5.0,
+            fast_multiplier: 4.0,
+            sensitivity: 0.003,
+            looking: false,
+            cursor: None,
+            look_delta: (0.0, 0.0),
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+            fast: false,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::MouseInput { state, button: MouseButton::Right, .. } => {
+                self.looking = state.is_pressed();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some((x, y)) = self.cursor && self.looking {
+                    self.look_delta.0 += position.x - x;
+                    self.look_delta.1 += position.y - y;
+                }
+                self.cursor = Some((position.x, position.y));
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state.is_pressed();
+                match event.physical_key {
+                    PhysicalKey::Code(KeyCode::KeyW) => self.forward = pressed,
+                    PhysicalKey::Code(KeyCode::KeyS) => self.backward = pressed,
+                    PhysicalKey::Code(KeyCode::KeyA) => self.left = pressed,
+                    PhysicalKey::Code(KeyCode::KeyD) => self.right = pressed,
+                    PhysicalKey::Code(KeyCode::KeyE) => self.up = pressed,
+                    PhysicalKey::Code(KeyCode::KeyQ) => self.down = pressed,
+                    PhysicalKey::Code(KeyCode::ShiftLeft | KeyCode::ShiftRight) => self.fast = pressed,
+                    _ => {}
+                }
+            }
+            WindowEvent::Focused(false) => {
+                self.looking = false;
+                self.forward = false;
+                self.backward = false;
+                self.left = false;
+                self.right = false;
+                self.up = false;
+                self.down = false;
+                self.fast = false;
+            }
+            _ => {}
+        }
+    }
+
+    // delta_time is in seconds.
+    pub fn update(&mut self, camera: &mut Camera3D, delta_time: f64) {
+        self.update_view(camera.get_view_3d_mut(), delta_time);
+    }
+
+    pub fn update_view(&mut self, camera: &mut View3D, delta_time: f64) {
+        camera.yaw += self.look_delta.0 * self.sensitivity;
+        camera.set_pitch(camera.pitch - self.look_delta.1 * self.sensitivity);
+        self.look_delta = (0.0, 0.0);
+
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let direction = camera.get_forward() * axis(self.forward, self.backward)
+            + camera.get_right() * axis(self.right, self.left)
+            + Vec3::Y * axis(self.up, self.down);
+        if direction == Vec3::ZERO {
+            return;
+        }
+
+        let speed = if self.fast { self.speed * self.fast_multiplier } else { self.speed };
+        let position = Vec3::from(camera.position.clone()) + direction.normalize() * (speed * delta_time) as f32;
+        camera.position = position.into();
+    }
+}
+
+// Editor style orbit camera: drag with the left mouse button to orbit around the target,
+// with the middle button to pan and scroll to zoom. Feed it every window event from
+// Program::handle_event and call update once per frame.
+pub struct OrbitController {
+    pub target: Coordinate3D,
+    pub distance: f64,
+    pub min_distance: f64,
+    pub max_distance: f64,
+    // Radians per pixel of cursor movement.
+    pub sensitivity: f64,
+    // Fraction of the distance zoomed per scroll line.
+    pub zoom_speed: f64,
+    orbiting: bool,
+    panning: bool,
+    cursor: Option<(f64, f64)>,
+    orbit_delta: (f64, f64),
+    pan_delta: (f64, f64),
+    scroll: f64,
+}
+
+impl OrbitController {
+    pub fn new(target: Coordinate3D, distance: f64) -> Self {
+        Self {
+            target,
+            distance,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+            orbiting: false,
+            panning: false,
+            cursor: None,
+            orbit_delta: (0.0, 0.0),
+            pan_delta: (0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.orbiting = state.is_pressed();
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Middle, .. } => {
+                self.panning = state.is_pressed();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some((x, y)) = self.cursor {
+                    let delta = (position.x - x, position.y - y);
+                    if self.orbiting {
+                        self.orbit_delta.0 += delta.0;
+                        self.orbit_delta.1 += delta.1;
+                    } else if self.panning {
+                        self.pan_delta.0 += delta.0;
+                        self.pan_delta.1 += delta.1;
+                    }
+                }
+                self.cursor = Some((position.x, position.y));
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    // Roughly one line per 20 pixels.
+                    MouseScrollDelta::PixelDelta(position) => position.y / 20.0,
+                };
+            }
+            WindowEvent::Focused(false) => {
+                self.orbiting = false;
+                self.panning = false;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn update(&mut self, camera: &mut Camera3D) {
+        self.update_view(camera.get_view_3d_mut());
+    }
+
+    pub fn update_view(&mut self, camera: &mut View3D) {
+        camera.yaw += self.orbit_delta.0 * self.sensitivity;
+        camera.set_pitch(camera.pitch - self.orbit_delta.1 * self.sensitivity);
+        self.orbit_delta = (0.0, 0.0);
+
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(self.scroll)).clamp(self.min_distance, self.max_distance);
+        self.scroll = 0.0;
+
+        // Pan speed scales with the distance so it feels the same at any zoom.
+        let forward = camera.get_forward();
+        let right = camera.get_right();
+        let up = right.cross(forward);
+        let pan_scale = (self.distance * 0.002) as f32;
+        let pan = right * (-self.pan_delta.0 as f32 * pan_scale) + up * (self.pan_delta.1 as f32 * pan_scale);
+        self.target = (Vec3::from(self.target.clone()) + pan).into();
+        self.pan_delta = (0.0, 0.0);
+
+        camera.position = (Vec3::from(self.target.clone()) - forward * self.distance as f32).into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &Coordinate2D, b: &Coordinate2D) {
+        assert!((a.x - b.x).abs() < 1e-6 && (a.y - b.y).abs() < 1e-6, "{:?} != {:?}", a, b);
+    }
+
+    fn view_2d() -> View2D {
+        View2D { position: Coordinate2D { x: 100.0, y: -40.0 }, zoom: 2.5, rotation: 0.7, width: 800.0, height: 600.0 }
+    }
+
+    #[test]
+    fn view_2d_round_trips() {
+        let view = view_2d();
+        for point in [Coordinate2D { x: 0.0, y: 0.0 }, Coordinate2D { x: 123.5, y: 456.25 }, Coordinate2D { x: -300.0, y: 20.0 }] {
+            assert_close(&view.world_to_screen(view.screen_to_world(point.clone())), &point);
+            assert_close(&view.screen_to_world(view.world_to_screen(point.clone())), &point);
+        }
+        // The position sits in the middle of the screen.
+        assert_close(&view.world_to_screen(view.position.clone()), &Coordinate2D { x: 400.0, y: 300.0 });
+    }
+
+    #[test]
+    fn view_2d_zoom_and_rotation() {
+        let mut view = View2D { rotation: 0.0, ..view_2d() };
+        // Zoomed in 2.5 times, 10 world units are 25 pixels.
+        let screen = view.world_to_screen(Coordinate2D { x: 110.0, y: -40.0 });
+        assert_close(&screen, &Coordinate2D { x: 425.0, y: 300.0 });
+
+        // A quarter turn clockwise shows world +x pointing up the screen.
+        view.rotation = std::f64::consts::FRAC_PI_2;
+        let screen = view.world_to_screen(Coordinate2D { x: 110.0, y: -40.0 });
+        assert_close(&screen, &Coordinate2D { x: 400.0, y: 275.0 });
+    }
+
+    #[test]
+    fn view_2d_projection_matches_world_to_screen() {
+        let view = view_2d();
+        let world = Coordinate2D { x: 150.0, y: 10.0 };
+        let ndc = view.get_view_projection() * Vec4::new(world.x as f32, world.y as f32, 0.0, 1.0);
+        let from_matrix = Coordinate2D {
+            x: (ndc.x as f64 + 1.0) / 2.0 * view.width,
+            y: (1.0 - ndc.y as f64) / 2.0 * view.height,
+        };
+        let expected = view.world_to_screen(world);
+        assert!((from_matrix.x - expected.x).abs() < 1e-3 && (from_matrix.y - expected.y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bounds_center_small_areas() {
+        assert_eq!(clamp_axis(50.0, 0.0, 100.0, 80.0), 50.0);
+        assert_eq!(clamp_axis(-20.0, 0.0, 1000.0, 100.0), 100.0);
+        assert_eq!(clamp_axis(990.0, 0.0, 1000.0, 100.0), 900.0);
+    }
+
+    fn distance(a: &Coordinate3D, b: &Coordinate3D) -> f64 {
+        (Vec3::from(a.clone()) - Vec3::from(b.clone())).length() as f64
+    }
+
+    #[test]
+    fn orbit_keeps_its_distance_to_the_target() {
+        let mut view = View3D::new(Projection::Perspective { fov_y: 1.0, near: 0.1, far: 100.0 }, 1.5);
+        let target = Coordinate3D { x: 1.0, y: 2.0, z: -3.0 };
+        let mut orbit = OrbitController::new(target.clone(), 10.0);
+        for delta in [(0.0, 0.0), (120.0, -40.0), (-500.0, 250.0)] {
+            orbit.orbit_delta = delta;
+            orbit.update_view(&mut view);
+            assert!((distance(&view.get_position(), &target) - 10.0).abs() < 1e-4);
+            // The camera keeps looking at the target.
+            let to_target = (Vec3::from(target.clone()) - Vec3::from(view.get_position())).normalize();
+            assert!(to_target.dot(view.get_forward()) > 0.9999);
+        }
+
+        // Zooming moves along the same line, within the distance limits.
+        orbit.scroll = 100.0;
+        orbit.update_view(&mut view);
+        assert_eq!(orbit.distance, orbit.min_distance);
+        assert!((distance(&view.get_position(), &target) - orbit.min_distance).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pitch_is_clamped() {
+        let mut view = View3D::new(Projection::Perspective { fov_y: 1.0, near: 0.1, far: 100.0 }, 1.0);
+        view.set_pitch(10.0);
+        assert_eq!(view.get_pitch(), MAX_PITCH);
+
+        let mut orbit = OrbitController::new(Coordinate3D { x: 0.0, y: 0.0, z: 0.0 }, 5.0);
+        orbit.orbit_delta = (0.0, 1.0e6);
+        orbit.update_view(&mut view);
+        assert_eq!(view.get_pitch(), -MAX_PITCH);
+
+        let mut fly = FlyController::new();
+        fly.look_delta = (0.0, -1.0e6);
+        fly.update_view(&mut view, 0.016);
+        assert_eq!(view.get_pitch(), MAX_PITCH);
+        // Even looking straight up the view stays well defined.
+        assert!(view.get_view().to_cols_array_2d().iter().flatten().all(|value| value.is_finite()));
+    }
+
+    #[test]
+    fn look_at_faces_the_target() {
+        let mut view = View3D::new(Projection::Perspective { fov_y: 1.0, near: 0.1, far: 100.0 }, 1.0);
+        view.set_position(Coordinate3D { x: 0.0, y: 1.0, z: 5.0 });
+        view.look_at(Coordinate3D { x: 3.0, y: 0.0, z: 1.0 });
+        let expected = Vec3::new(3.0, -1.0, -4.0).normalize();
+        assert!(view.get_forward().dot(expected) > 0.9999);
+
+        // A ray through the center of the screen goes the same way.
+        let ray = Ray::from_screen(&view.get_view_projection(), Vec2::new(400.0, 300.0), Vec2::new(800.0, 600.0));
+        assert!(ray.direction.normalize().dot(expected) > 0.9999);
+    }
+}
@@ -0,0 +1,366 @@
+use anyhow::{Error, bail};
+use crate::math::Vec4;
+
+// RGBA color with components from 0 to 1. Values are sRGB encoded, the way colors are
+// written in hex codes and picked in image editors. Use to_linear for math and shaders
+// that work in linear space.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const GRAY: Color = Color::rgb(0.5, 0.5, 0.5);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Color = Color::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::rgb(1.0, 0.0, 1.0);
+    pub const ORANGE: Color = Color::rgb(1.0, 0.647, 0.0);
+    pub const PURPLE: Color = Color::rgb(0.502, 0.0, 0.502);
+    pub const CORNFLOWER_BLUE: Color = Color::rgb(0.392, 0.584, 0.929);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::rgba(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    pub fn to_rgba8(self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a].map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    // Accepts "rgb", "rgba", "rrggbb" and "rrggbbaa", with or without a leading '#'.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            bail!("{:?} is not a hex color", hex);
+        }
+
+        let channel = |index: usize, width: usize| {
+            let value = u8::from_str_radix(&digits[index * width..(index + 1) * width], 16).unwrap();
+            // Short forms repeat the digit, so "f" is 0xff.
+            if width == 1 { value * 17 } else { value }
+        };
+        Ok(match digits.len() {
+            3 => Self::rgba8(channel(0, 1), channel(1, 1), channel(2, 1), 255),
+            4 => Self::rgba8(channel(0, 1), channel(1, 1), channel(2, 1), channel(3, 1)),
+            6 => Self::rgba8(channel(0, 2), channel(1, 2), channel(2, 2), 255),
+            8 => Self::rgba8(channel(0, 2), channel(1, 2), channel(2, 2), channel(3, 2)),
+            _ => bail!("{:?} is not a hex color", hex),
+        })
+    }
+
+    // "#rrggbb", or "#rrggbbaa" when the color is not opaque.
+    pub fn to_hex(self) -> String {
+        let [r, g, b, a] = self.to_rgba8();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    // Hue in degrees, saturation and value from 0 to 1.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let chroma = value * saturation;
+        Self::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (hue, saturation, max)
+    }
+
+    // Hue in degrees, saturation and lightness from 0 to 1.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Self::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min { 0.0 } else { (max - min) / (1.0 - (2.0 * lightness - 1.0).abs()) };
+        (hue, saturation, lightness)
+    }
+
+    fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, second, 0.0),
+            1 => (second, chroma, 0.0),
+            2 => (0.0, chroma, second),
+            3 => (0.0, second, chroma),
+            4 => (second, 0.0, chroma),
+            _ => (chroma, 0.0, second),
+        };
+        Self::rgb(r + offset, g + offset, b + offset)
+    }
+
+    fn hue_max_min(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / delta + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / delta + 4.0)
+        };
+        (hue, max, min)
+    }
+
+    // Alpha is never encoded, so it stays the same.
+    pub fn to_linear(self) -> Self {
+        Self::rgba(srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a)
+    }
+
+    pub fn from_linear(linear: Color) -> Self {
+        Self::rgba(linear_to_srgb(linear.r), linear_to_srgb(linear.g), linear_to_srgb(linear.b), linear.a)
+    }
+
+    // Values a render target of the format expects. sRGB formats encode on write, so they
+    // are given linear values, anything else gets the color as it is.
+    pub fn for_format(self, format: wgpu::TextureFormat) -> Self {
+        if format.is_srgb() { self.to_linear() } else { self }
+    }
+
+    pub fn to_wgpu(self, format: wgpu::TextureFormat) -> wgpu::Color {
+        let color = self.for_format(format);
+        wgpu::Color { r: color.r as f64, g: color.g as f64, b: color.b as f64, a: color.a as f64 }
+    }
+
+    // Component wise in sRGB space, like most image editors blend.
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        Self::from(Vec4::from(self).lerp(Vec4::from(other), t))
+    }
+
+    // Blends in linear space, which keeps gradients from going dark in the middle.
+    pub fn lerp_linear(self, other: Color, t: f32) -> Self {
+        Self::from_linear(self.to_linear().lerp(other.to_linear(), t))
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safe because the struct is repr(C) and only made of f32s.
+        unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>()) }
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+impl From<Vec4> for Color {
+    fn from(vector: Vec4) -> Self {
+        Self::rgba(vector.x, vector.y, vector.z, vector.w)
+    }
+}
+
+impl From<Color> for Vec4 {
+    fn from(color: Color) -> Self {
+        Vec4::new(color.r, color.g, color.b, color.a)
+    }
+}
+
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+// Ordered list of colors, indexed directly or sampled as a gradient.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Palette {
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Color>) -> Self {
+        Self { colors }
+    }
+
+    pub fn from_hex(hex_codes: &[&str]) -> Result<Self, Error> {
+        Ok(Self::new(hex_codes.iter().map(|hex| Color::from_hex(hex)).collect::<Result<_, _>>()?))
+    }
+
+    // The 16 color PICO-8 palette.
+    pub fn pico8() -> Self {
+        Self::from_hex(&[
+            "000000", "1d2b53", "7e2553", "008751", "ab5236", "5f574f", "c2c3c7", "fff1e8",
+            "ff004d", "ffa300", "ffec27", "00e436", "29adff", "83769c", "ff77a8", "ffccaa",
+        ]).unwrap()
+    }
+
+    // Evenly spaced hues at the given saturation and value, handy for debug colors.
+    pub fn rainbow(count: usize, saturation: f32, value: f32) -> Self {
+        Self::new((0..count).map(|index| Color::from_hsv(index as f32 * 360.0 / count as f32, saturation, value)).collect())
+    }
+
+    pub fn get(&self, index: usize) -> Option<Color> {
+        self.colors.get(index).copied()
+    }
+
+    pub fn get_colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn push(&mut self, color: Color) {
+        self.colors.push(color);
+    }
+
+    // t from 0 to 1 runs through every color in order, blending in linear space.
+    pub fn sample(&self, t: f32) -> Color {
+        match self.colors.len() {
+            0 => Color::TRANSPARENT,
+            1 => self.colors[0],
+            len => {
+                let position = t.clamp(0.0, 1.0) * (len - 1) as f32;
+                let index = (position as usize).min(len - 2);
+                self.colors[index].lerp_linear(self.colors[index + 1], position - index as f32)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_near(a: Color, b: Color) {
+        assert!((Vec4::from(a) - Vec4::from(b)).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        assert_eq!(Color::from_hex("#ff8000").unwrap().to_rgba8(), [255, 128, 0, 255]);
+        assert_eq!(Color::from_hex("f80").unwrap().to_rgba8(), [255, 136, 0, 255]);
+        assert_eq!(Color::from_hex("#f808").unwrap().to_rgba8(), [255, 136, 0, 136]);
+        assert_eq!(Color::from_hex("11223344").unwrap().to_rgba8(), [0x11, 0x22, 0x33, 0x44]);
+
+        for hex in ["#000000", "#ffffff", "#1d2b53", "#ff004d80", "#12345678"] {
+            assert_eq!(Color::from_hex(hex).unwrap().to_hex(), hex);
+        }
+        assert_eq!(Color::from_hex("ABCDEF").unwrap().to_hex(), "#abcdef");
+        assert_eq!(Color::rgba(2.0, -1.0, 0.0, 1.0).to_hex(), "#ff0000");
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        for hex in ["", "#", "#12", "#12345", "#1234567", "#123456789", "#gg0000", "#12 345", "##123456", "#ffé"] {
+            let error = Color::from_hex(hex).unwrap_err();
+            assert!(error.to_string().contains("is not a hex color"), "{:?}: {}", hex, error);
+        }
+        assert!(Palette::from_hex(&["000000", "nope"]).is_err());
+    }
+
+    #[test]
+    fn hsv_round_trips() {
+        assert_color_near(Color::from_hsv(0.0, 1.0, 1.0), Color::RED);
+        assert_color_near(Color::from_hsv(120.0, 1.0, 1.0), Color::GREEN);
+        assert_color_near(Color::from_hsv(240.0, 1.0, 1.0), Color::BLUE);
+        assert_color_near(Color::from_hsv(-60.0, 1.0, 1.0), Color::MAGENTA);
+        assert_color_near(Color::from_hsv(420.0, 1.0, 1.0), Color::YELLOW);
+        assert_eq!(Color::WHITE.to_hsv(), (0.0, 0.0, 1.0));
+        assert_eq!(Color::BLACK.to_hsv(), (0.0, 0.0, 0.0));
+
+        for hue in [0.0, 30.0, 90.0, 150.0, 210.0, 270.0, 330.0] {
+            let color = Color::from_hsv(hue, 0.6, 0.8);
+            let (h, s, v) = color.to_hsv();
+            assert!((h - hue).abs() < 1e-3 && (s - 0.6).abs() < 1e-4 && (v - 0.8).abs() < 1e-4, "{} gave {:?}", hue, (h, s, v));
+            assert_color_near(Color::from_hsv(h, s, v), color);
+        }
+    }
+
+    #[test]
+    fn hsl_round_trips() {
+        assert_color_near(Color::from_hsl(0.0, 1.0, 0.5), Color::RED);
+        assert_color_near(Color::from_hsl(180.0, 1.0, 0.5), Color::CYAN);
+        assert_color_near(Color::from_hsl(0.0, 0.0, 0.5), Color::GRAY);
+        assert_color_near(Color::from_hsl(200.0, 1.0, 1.0), Color::WHITE);
+        assert_eq!(Color::GRAY.to_hsl(), (0.0, 0.0, 0.5));
+
+        for hue in [15.0, 75.0, 135.0, 195.0, 255.0, 315.0] {
+            for lightness in [0.25, 0.5, 0.75] {
+                let color = Color::from_hsl(hue, 0.4, lightness);
+                let (h, s, l) = color.to_hsl();
+                assert!((h - hue).abs() < 1e-3 && (s - 0.4).abs() < 1e-4 && (l - lightness).abs() < 1e-4, "{} gave {:?}", hue, (h, s, l));
+            }
+        }
+    }
+
+    #[test]
+    fn linear_conversion_round_trips() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.21404).abs() < 1e-4);
+        assert!((linear_to_srgb(0.21404) - 0.5).abs() < 1e-4);
+        // Both sides of the linear toe.
+        assert!((srgb_to_linear(0.04) - 0.04 / 12.92).abs() < 1e-7);
+        assert!((linear_to_srgb(0.003) - 0.003 * 12.92).abs() < 1e-7);
+
+        let color = Color::rgba(0.1, 0.5, 0.9, 0.3);
+        let linear = color.to_linear();
+        assert_eq!(linear.a, 0.3);
+        assert!(linear.r < color.r && linear.g < color.g && linear.b < color.b);
+        assert_color_near(Color::from_linear(linear), color);
+
+        assert_eq!(color.for_format(wgpu::TextureFormat::Rgba8Unorm), color);
+        assert_eq!(color.for_format(wgpu::TextureFormat::Bgra8UnormSrgb), linear);
+    }
+
+    #[test]
+    fn palette_samples_through_every_color() {
+        let palette = Palette::new(vec![Color::BLACK, Color::WHITE, Color::RED]);
+        assert_eq!(palette.sample(-1.0), Color::BLACK);
+        assert_color_near(palette.sample(0.5), Color::WHITE);
+        assert_color_near(palette.sample(2.0), Color::RED);
+        assert_eq!(Palette::default().sample(0.5), Color::TRANSPARENT);
+        assert_eq!(Palette::pico8().len(), 16);
+    }
+}
@@ -0,0 +1,1862 @@
+use anyhow::{Context, Error, anyhow, bail};
+use wgpu::{AstcBlock, AstcChannel, Features, TextureDimension, TextureFormat, util::TextureDataOrder};
+
+const KTX2_MAGIC: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+type BlockDecoder = fn(&[u8]) -> [[u8; 4]; 16];
+// Decodes a block of any footprint into the bytes of its texels, row by row.
+type TexelDecoder = Box<dyn Fn(&[u8]) -> Vec<u8>>;
+
+// Block compressed pixel data read straight out of a KTX2 or DDS container, with
+// every stored mip level. The data is laid out the way wgpu expects it in `order`.
+pub struct CompressedImage {
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub depth_or_array_layers: u32,
+    pub mip_level_count: u32,
+    pub dimension: TextureDimension,
+    pub is_cube: bool,
+    pub order: TextureDataOrder,
+    pub data: Vec<u8>,
+}
+
+impl CompressedImage {
+    pub fn open(file: &str) -> Result<Self, Error> {
+        let bytes = std::fs::read(file).with_context(|| format!("could not read {}", file))?;
+
+        if bytes.starts_with(&KTX2_MAGIC) {
+            Self::from_ktx2(&bytes)
+        } else if bytes.starts_with(&DDS_MAGIC) {
+            Self::from_dds(&bytes)
+        } else {
+            bail!("{} is neither a KTX2 nor a DDS file", file)
+        }
+    }
+
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, Error> {
+        let reader = ktx2::Reader::new(bytes).map_err(|e| anyhow!("invalid KTX2 file: {:?}", e))?;
+        let header = reader.header();
+
+        if let Some(scheme) = header.supercompression_scheme {
+            bail!("KTX2 supercompression ({:?}) is not supported", scheme);
+        }
+        let format = header.format
+            .and_then(ktx2_format)
+            .ok_or_else(|| anyhow!("unsupported KTX2 format {:?}", header.format))?;
+
+        let dimension = if header.pixel_depth > 1 { TextureDimension::D3 } else { TextureDimension::D2 };
+        let depth_or_array_layers = match dimension {
+            TextureDimension::D3 => header.pixel_depth,
+            _ => header.layer_count.max(1) * header.face_count.max(1),
+        };
+
+        // KTX2 stores every layer of a level next to each other, level 0 first.
+        let data = reader.levels().flat_map(|level| level.data.iter().copied()).collect();
+
+        Ok(Self {
+            format,
+            width: header.pixel_width,
+            height: header.pixel_height.max(1),
+            depth_or_array_layers,
+            mip_level_count: header.level_count.max(1),
+            dimension,
+            is_cube: header.face_count == 6,
+            order: TextureDataOrder::MipMajor,
+            data,
+        })
+    }
+
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, Error> {
+        let dds = ddsfile::Dds::read(bytes).map_err(|e| anyhow!("invalid DDS file: {:?}", e))?;
+
+        let format = match dds.get_dxgi_format() {
+            Some(format) => dxgi_format(format),
+            None => dds.get_d3d_format().and_then(d3d_format),
+        }.ok_or_else(|| anyhow!("unsupported DDS format {:?}", dds.get_dxgi_format()))?;
+
+        let is_cube = dds.header.caps2.contains(ddsfile::Caps2::CUBEMAP)
+            || dds.header10.as_ref().is_some_and(|h| h.misc_flag.contains(ddsfile::MiscFlag::TEXTURECUBE));
+        let is_volume = dds.header.caps2.contains(ddsfile::Caps2::VOLUME)
+            || dds.header10.as_ref().is_some_and(|h| h.resource_dimension == ddsfile::D3D10ResourceDimension::Texture3D);
+
+        let (dimension, depth_or_array_layers) = if is_volume {
+            (TextureDimension::D3, dds.get_depth())
+        } else {
+            let layers = match &dds.header10 {
+                Some(header10) => header10.array_size.max(1) * if is_cube { 6 } else { 1 },
+                None => dds.get_num_array_layers(),
+            };
+            (TextureDimension::D2, layers)
+        };
+
+        Ok(Self {
+            format,
+            width: dds.get_width(),
+            height: dds.get_height().max(1),
+            depth_or_array_layers,
+            mip_level_count: dds.get_num_mipmap_levels().max(1),
+            dimension,
+            is_cube,
+            // DDS keeps the whole mip chain of a layer together.
+            order: TextureDataOrder::LayerMajor,
+            data: dds.data,
+        })
+    }
+
+    pub fn is_supported_by(&self, features: Features) -> bool {
+        features.contains(self.format.required_features())
+    }
+
+    // Decodes every level into RGBA8 (half floats for BC6H) for adapters that can't sample
+    // the stored format. HDR ASTC has no CPU decoder, those files need an adapter that has it.
+    pub fn decompress(&self) -> Result<Self, Error> {
+        let rgba8 = |decode: BlockDecoder| -> TexelDecoder { Box::new(move |block| decode(block).concat()) };
+        let (block_size, decode_block): (usize, TexelDecoder) = match self.format {
+            TextureFormat::Bc1RgbaUnorm | TextureFormat::Bc1RgbaUnormSrgb => (8, rgba8(decode_bc1_block)),
+            TextureFormat::Bc2RgbaUnorm | TextureFormat::Bc2RgbaUnormSrgb => (16, rgba8(decode_bc2_block)),
+            TextureFormat::Bc3RgbaUnorm | TextureFormat::Bc3RgbaUnormSrgb => (16, rgba8(decode_bc3_block)),
+            TextureFormat::Bc4RUnorm => (8, rgba8(decode_bc4_block)),
+            TextureFormat::Bc4RSnorm => (8, rgba8(decode_bc4_snorm_block)),
+            TextureFormat::Bc5RgUnorm => (16, rgba8(decode_bc5_block)),
+            TextureFormat::Bc5RgSnorm => (16, rgba8(decode_bc5_snorm_block)),
+            TextureFormat::Bc6hRgbUfloat => (16, Box::new(|block: &[u8]| decode_bc6h_block(block, false).concat())),
+            TextureFormat::Bc6hRgbFloat => (16, Box::new(|block: &[u8]| decode_bc6h_block(block, true).concat())),
+            TextureFormat::Bc7RgbaUnorm | TextureFormat::Bc7RgbaUnormSrgb => (16, rgba8(decode_bc7_block)),
+            TextureFormat::Etc2Rgb8Unorm | TextureFormat::Etc2Rgb8UnormSrgb => (8, rgba8(decode_etc2_rgb8_block)),
+            TextureFormat::Etc2Rgb8A1Unorm | TextureFormat::Etc2Rgb8A1UnormSrgb => (8, rgba8(decode_etc2_rgb8a1_block)),
+            TextureFormat::Etc2Rgba8Unorm | TextureFormat::Etc2Rgba8UnormSrgb => (16, rgba8(decode_etc2_rgba8_block)),
+            TextureFormat::EacR11Unorm => (8, rgba8(decode_eac_r11_block)),
+            TextureFormat::EacR11Snorm => (8, rgba8(decode_eac_r11_snorm_block)),
+            TextureFormat::EacRg11Unorm => (16, rgba8(decode_eac_rg11_block)),
+            TextureFormat::EacRg11Snorm => (16, rgba8(decode_eac_rg11_snorm_block)),
+            TextureFormat::Astc { channel: AstcChannel::Unorm | AstcChannel::UnormSrgb, .. } => {
+                let (width, height) = self.format.block_dimensions();
+                let srgb = self.format.is_srgb();
+                (16, Box::new(move |block: &[u8]| decode_astc_block(block, width as usize, height as usize, srgb).concat()))
+            }
+            other => bail!("{:?} is not supported by the adapter and can't be decompressed on the CPU", other),
+        };
+        let half_float = matches!(self.format, TextureFormat::Bc6hRgbUfloat | TextureFormat::Bc6hRgbFloat);
+        let texel_size = if half_float { 8 } else { 4 };
+        let (block_width, block_height) = self.format.block_dimensions();
+        let (block_width, block_height) = (block_width as usize, block_height as usize);
+
+        let mut data = Vec::new();
+        let mut offset = 0;
+        for (width, height) in self.sub_image_sizes() {
+            let (width, height) = (width as usize, height as usize);
+            let blocks_x = width.div_ceil(block_width);
+            let blocks_y = height.div_ceil(block_height);
+            let size = blocks_x * blocks_y * block_size;
+            let blocks = self.data.get(offset..offset + size).context("compressed image data is truncated")?;
+            offset += size;
+
+            let mut pixels = vec![0; width * height * texel_size];
+            for (index, block) in blocks.chunks_exact(block_size).enumerate() {
+                let texels = decode_block(block);
+                let (block_x, block_y) = ((index % blocks_x) * block_width, (index / blocks_x) * block_height);
+                for (texel, bytes) in texels.chunks_exact(texel_size).enumerate() {
+                    let (x, y) = (block_x + texel % block_width, block_y + texel / block_width);
+                    if x < width && y < height {
+                        let start = (y * width + x) * texel_size;
+                        pixels[start..start + texel_size].copy_from_slice(bytes);
+                    }
+                }
+            }
+            data.extend_from_slice(&pixels);
+        }
+
+        // Signed formats keep their sign, the decoders write the bytes of i8 values for them.
+        let signed = matches!(self.format, TextureFormat::Bc4RSnorm | TextureFormat::Bc5RgSnorm | TextureFormat::EacR11Snorm | TextureFormat::EacRg11Snorm);
+        Ok(Self {
+            format: if half_float {
+                TextureFormat::Rgba16Float
+            } else if self.format.is_srgb() {
+                TextureFormat::Rgba8UnormSrgb
+            } else if signed {
+                TextureFormat::Rgba8Snorm
+            } else {
+                TextureFormat::Rgba8Unorm
+            },
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: self.depth_or_array_layers,
+            mip_level_count: self.mip_level_count,
+            dimension: self.dimension,
+            is_cube: self.is_cube,
+            order: self.order,
+            data,
+        })
+    }
+
+    // Size of every 2D image in the data, in storage order.
+    fn sub_image_sizes(&self) -> Vec<(u32, u32)> {
+        let layers = if self.dimension == TextureDimension::D3 { 1 } else { self.depth_or_array_layers };
+        let level_size = |level: u32| {
+            let slices = if self.dimension == TextureDimension::D3 { (self.depth_or_array_layers >> level).max(1) } else { 1 };
+            ((self.width >> level).max(1), (self.height >> level).max(1), slices)
+        };
+
+        let mut sizes = Vec::new();
+        let mut push = |level: u32| {
+            let (width, height, slices) = level_size(level);
+            sizes.extend(std::iter::repeat_n((width, height), slices as usize));
+        };
+        match self.order {
+            TextureDataOrder::MipMajor => {
+                for level in 0..self.mip_level_count {
+                    for _ in 0..layers {
+                        push(level);
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..layers {
+                    for level in 0..self.mip_level_count {
+                        push(level);
+                    }
+                }
+            }
+        }
+        sizes
+    }
+}
+
+fn ktx2_format(format: ktx2::Format) -> Option<TextureFormat> {
+    use ktx2::Format as F;
+
+    let astc = |block, channel| Some(TextureFormat::Astc { block, channel });
+    match format {
+        F::R8G8B8A8_UNORM => Some(TextureFormat::Rgba8Unorm),
+        F::R8G8B8A8_SRGB => Some(TextureFormat::Rgba8UnormSrgb),
+        F::R16G16B16A16_SFLOAT => Some(TextureFormat::Rgba16Float),
+        F::R32G32B32A32_SFLOAT => Some(TextureFormat::Rgba32Float),
+        F::BC1_RGB_UNORM_BLOCK | F::BC1_RGBA_UNORM_BLOCK => Some(TextureFormat::Bc1RgbaUnorm),
+        F::BC1_RGB_SRGB_BLOCK | F::BC1_RGBA_SRGB_BLOCK => Some(TextureFormat::Bc1RgbaUnormSrgb),
+        F::BC2_UNORM_BLOCK => Some(TextureFormat::Bc2RgbaUnorm),
+        F::BC2_SRGB_BLOCK => Some(TextureFormat::Bc2RgbaUnormSrgb),
+        F::BC3_UNORM_BLOCK => Some(TextureFormat::Bc3RgbaUnorm),
+        F::BC3_SRGB_BLOCK => Some(TextureFormat::Bc3RgbaUnormSrgb),
+        F::BC4_UNORM_BLOCK => Some(TextureFormat::Bc4RUnorm),
+        F::BC4_SNORM_BLOCK => Some(TextureFormat::Bc4RSnorm),
+        F::BC5_UNORM_BLOCK => Some(TextureFormat::Bc5RgUnorm),
+        F::BC5_SNORM_BLOCK => Some(TextureFormat::Bc5RgSnorm),
+        F::BC6H_UFLOAT_BLOCK => Some(TextureFormat::Bc6hRgbUfloat),
+        F::BC6H_SFLOAT_BLOCK => Some(TextureFormat::Bc6hRgbFloat),
+        F::BC7_UNORM_BLOCK => Some(TextureFormat::Bc7RgbaUnorm),
+        F::BC7_SRGB_BLOCK => Some(TextureFormat::Bc7RgbaUnormSrgb),
+        F::ETC2_R8G8B8_UNORM_BLOCK => Some(TextureFormat::Etc2Rgb8Unorm),
+        F::ETC2_R8G8B8_SRGB_BLOCK => Some(TextureFormat::Etc2Rgb8UnormSrgb),
+        F::ETC2_R8G8B8A1_UNORM_BLOCK => Some(TextureFormat::Etc2Rgb8A1Unorm),
+        F::ETC2_R8G8B8A1_SRGB_BLOCK => Some(TextureFormat::Etc2Rgb8A1UnormSrgb),
+        F::ETC2_R8G8B8A8_UNORM_BLOCK => Some(TextureFormat::Etc2Rgba8Unorm),
+        F::ETC2_R8G8B8A8_SRGB_BLOCK => Some(TextureFormat::Etc2Rgba8UnormSrgb),
+        F::EAC_R11_UNORM_BLOCK => Some(TextureFormat::EacR11Unorm),
+        F::EAC_R11_SNORM_BLOCK => Some(TextureFormat::EacR11Snorm),
+        F::EAC_R11G11_UNORM_BLOCK => Some(TextureFormat::EacRg11Unorm),
+        F::EAC_R11G11_SNORM_BLOCK => Some(TextureFormat::EacRg11Snorm),
+        F::ASTC_4x4_UNORM_BLOCK => astc(AstcBlock::B4x4, AstcChannel::Unorm),
+        F::ASTC_4x4_SRGB_BLOCK => astc(AstcBlock::B4x4, AstcChannel::UnormSrgb),
+        F::ASTC_5x4_UNORM_BLOCK => astc(AstcBlock::B5x4, AstcChannel::Unorm),
+        F::ASTC_5x4_SRGB_BLOCK => astc(AstcBlock::B5x4, AstcChannel::UnormSrgb),
+        F::ASTC_5x5_UNORM_BLOCK => astc(AstcBlock::B5x5, AstcChannel::Unorm),
+        F::ASTC_5x5_SRGB_BLOCK => astc(AstcBlock::B5x5, AstcChannel::UnormSrgb),
+        F::ASTC_6x5_UNORM_BLOCK => astc(AstcBlock::B6x5, AstcChannel::Unorm),
+        F::ASTC_6x5_SRGB_BLOCK => astc(AstcBlock::B6x5, AstcChannel::UnormSrgb),
+        F::ASTC_6x6_UNORM_BLOCK => astc(AstcBlock::B6x6, AstcChannel::Unorm),
+        F::ASTC_6x6_SRGB_BLOCK => astc(AstcBlock::B6x6, AstcChannel::UnormSrgb),
+        F::ASTC_8x5_UNORM_BLOCK => astc(AstcBlock::B8x5, AstcChannel::Unorm),
+        F::ASTC_8x5_SRGB_BLOCK => astc(AstcBlock::B8x5, AstcChannel::UnormSrgb),
+        F::ASTC_8x6_UNORM_BLOCK => astc(AstcBlock::B8x6, AstcChannel::Unorm),
+        F::ASTC_8x6_SRGB_BLOCK => astc(AstcBlock::B8x6, AstcChannel::UnormSrgb),
+        F::ASTC_8x8_UNORM_BLOCK => astc(AstcBlock::B8x8, AstcChannel::Unorm),
+        F::ASTC_8x8_SRGB_BLOCK => astc(AstcBlock::B8x8, AstcChannel::UnormSrgb),
+        F::ASTC_10x5_UNORM_BLOCK => astc(AstcBlock::B10x5, AstcChannel::Unorm),
+        F::ASTC_10x5_SRGB_BLOCK => astc(AstcBlock::B10x5, AstcChannel::UnormSrgb),
+        F::ASTC_10x6_UNORM_BLOCK => astc(AstcBlock::B10x6, AstcChannel::Unorm),
+        F::ASTC_10x6_SRGB_BLOCK => astc(AstcBlock::B10x6, AstcChannel::UnormSrgb),
+        F::ASTC_10x8_UNORM_BLOCK => astc(AstcBlock::B10x8, AstcChannel::Unorm),
+        F::ASTC_10x8_SRGB_BLOCK => astc(AstcBlock::B10x8, AstcChannel::UnormSrgb),
+        F::ASTC_10x10_UNORM_BLOCK => astc(AstcBlock::B10x10, AstcChannel::Unorm),
+        F::ASTC_10x10_SRGB_BLOCK => astc(AstcBlock::B10x10, AstcChannel::UnormSrgb),
+        F::ASTC_12x10_UNORM_BLOCK => astc(AstcBlock::B12x10, AstcChannel::Unorm),
+        F::ASTC_12x10_SRGB_BLOCK => astc(AstcBlock::B12x10, AstcChannel::UnormSrgb),
+        F::ASTC_12x12_UNORM_BLOCK => astc(AstcBlock::B12x12, AstcChannel::Unorm),
+        F::ASTC_12x12_SRGB_BLOCK => astc(AstcBlock::B12x12, AstcChannel::UnormSrgb),
+        _ => None,
+    }
+}
+
+fn dxgi_format(format: ddsfile::DxgiFormat) -> Option<TextureFormat> {
+    use ddsfile::DxgiFormat as F;
+
+    match format {
+        F::R8G8B8A8_UNorm => Some(TextureFormat::Rgba8Unorm),
+        F::R8G8B8A8_UNorm_sRGB => Some(TextureFormat::Rgba8UnormSrgb),
+        F::R16G16B16A16_Float => Some(TextureFormat::Rgba16Float),
+        F::R32G32B32A32_Float => Some(TextureFormat::Rgba32Float),
+        F::BC1_UNorm => Some(TextureFormat::Bc1RgbaUnorm),
+        F::BC1_UNorm_sRGB => Some(TextureFormat::Bc1RgbaUnormSrgb),
+        F::BC2_UNorm => Some(TextureFormat::Bc2RgbaUnorm),
+        F::BC2_UNorm_sRGB => Some(TextureFormat::Bc2RgbaUnormSrgb),
+        F::BC3_UNorm => Some(TextureFormat::Bc3RgbaUnorm),
+        F::BC3_UNorm_sRGB => Some(TextureFormat::Bc3RgbaUnormSrgb),
+        F::BC4_UNorm => Some(TextureFormat::Bc4RUnorm),
+        F::BC4_SNorm => Some(TextureFormat::Bc4RSnorm),
+        F::BC5_UNorm => Some(TextureFormat::Bc5RgUnorm),
+        F::BC5_SNorm => Some(TextureFormat::Bc5RgSnorm),
+        F::BC6H_UF16 => Some(TextureFormat::Bc6hRgbUfloat),
+        F::BC6H_SF16 => Some(TextureFormat::Bc6hRgbFloat),
+        F::BC7_UNorm => Some(TextureFormat::Bc7RgbaUnorm),
+        F::BC7_UNorm_sRGB => Some(TextureFormat::Bc7RgbaUnormSrgb),
+        _ => None,
+    }
+}
+
+fn d3d_format(format: ddsfile::D3DFormat) -> Option<TextureFormat> {
+    match format {
+        ddsfile::D3DFormat::DXT1 => Some(TextureFormat::Bc1RgbaUnorm),
+        ddsfile::D3DFormat::DXT2 | ddsfile::D3DFormat::DXT3 => Some(TextureFormat::Bc2RgbaUnorm),
+        ddsfile::D3DFormat::DXT4 | ddsfile::D3DFormat::DXT5 => Some(TextureFormat::Bc3RgbaUnorm),
+        _ => None,
+    }
+}
+
+// BC1-BC5. Texels of every block are returned row by row.
+
+fn unpack_565(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 31) as u8;
+    let g = ((color >> 5) & 63) as u8;
+    let b = (color & 31) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+fn decode_bc1_colors(block: &[u8], three_color_mode_allowed: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (unpack_565(c0), unpack_565(c1));
+
+    let mix = |a: u8, b: u8, wa: u32, wb: u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+    let mut palette = [e0, e1, [0; 4], [0; 4]];
+    if c0 > c1 || !three_color_mode_allowed {
+        for channel in 0..3 {
+            palette[2][channel] = mix(e0[channel], e1[channel], 2, 1);
+            palette[3][channel] = mix(e0[channel], e1[channel], 1, 2);
+        }
+        palette[2][3] = 255;
+        palette[3][3] = 255;
+    } else {
+        for channel in 0..3 {
+            palette[2][channel] = mix(e0[channel], e1[channel], 1, 1);
+        }
+        palette[2][3] = 255;
+        // palette[3] stays transparent black
+    }
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|texel| palette[((indices >> (2 * texel)) & 3) as usize])
+}
+
+fn decode_bc4_channel(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as i32, block[1] as i32);
+    bc4_lookup(block, bc4_palette(a0, a1, 0, 255)).map(|value| value as u8)
+}
+
+// Endpoints are stored as i8 with -128 standing in for -127.
+fn decode_bc4_snorm_channel(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = ((block[0] as i8).max(-127) as i32, (block[1] as i8).max(-127) as i32);
+    bc4_lookup(block, bc4_palette(a0, a1, -127, 127)).map(|value| value as i8 as u8)
+}
+
+fn bc4_palette(a0: i32, a1: i32, min: i32, max: i32) -> [i32; 8] {
+    let mut palette = [a0, a1, 0, 0, 0, 0, min, max];
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i as i32) * a0 + i as i32 * a1) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i as i32) * a0 + i as i32 * a1) / 5;
+        }
+    }
+    palette
+}
+
+fn bc4_lookup(block: &[u8], palette: [i32; 8]) -> [i32; 16] {
+    let mut bits = 0u64;
+    for (i, byte) in block[2..8].iter().enumerate() {
+        bits |= (*byte as u64) << (8 * i);
+    }
+    std::array::from_fn(|texel| palette[((bits >> (3 * texel)) & 7) as usize])
+}
+
+fn decode_bc1_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_bc1_colors(block, true)
+}
+
+fn decode_bc2_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_bc1_colors(&block[8..16], false);
+    let alpha = u64::from_le_bytes(block[0..8].try_into().unwrap());
+    for (texel, rgba) in texels.iter_mut().enumerate() {
+        rgba[3] = ((alpha >> (4 * texel)) & 15) as u8 * 17;
+    }
+    texels
+}
+
+fn decode_bc3_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_bc1_colors(&block[8..16], false);
+    let alpha = decode_bc4_channel(&block[0..8]);
+    for (rgba, a) in texels.iter_mut().zip(alpha) {
+        rgba[3] = a;
+    }
+    texels
+}
+
+fn decode_bc4_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_bc4_channel(block).map(|r| [r, 0, 0, 255])
+}
+
+fn decode_bc4_snorm_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_bc4_snorm_channel(block).map(|r| [r, 0, 0, 127])
+}
+
+fn decode_bc5_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_bc4_channel(&block[0..8]);
+    let green = decode_bc4_channel(&block[8..16]);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 255])
+}
+
+fn decode_bc5_snorm_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_bc4_snorm_channel(&block[0..8]);
+    let green = decode_bc4_snorm_channel(&block[8..16]);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 127])
+}
+
+// BC7
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true, shared_pbits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+];
+
+// Bit n is the subset of texel n.
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE, 0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2], [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2], [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2], [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2], [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2], [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0], [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0], [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2], [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1], [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2], [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0], [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0], [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1], [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1], [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1], [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1], [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2], [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2], [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2], [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1], [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2], [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const BC7_ANCHORS_3_SECOND: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+];
+
+const BC7_ANCHORS_3_THIRD: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+struct BitReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        // Bits past the end of the block read as zero.
+        let value = self.bits.checked_shr(self.position).unwrap_or(0) & ((1u128 << count) - 1);
+        self.position += count;
+        value as u32
+    }
+}
+
+fn bc7_weight(index_bits: u32, index: u32) -> u32 {
+    match index_bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize],
+    }
+}
+
+fn bc7_interpolate(e0: u32, e1: u32, weight: u32) -> u8 {
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+fn expand_bits(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    value | (value >> bits)
+}
+
+fn decode_bc7_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = BitReader { bits: u128::from_le_bytes(block.try_into().unwrap()), position: 0 };
+
+    // Reserved mode 8 decodes to transparent black.
+    let Some(mode) = (0..8).find(|_| bits.read(1) == 1) else {
+        return [[0; 4]; 16];
+    };
+    let info = &BC7_MODES[mode];
+
+    let partition = bits.read(info.partition_bits) as usize;
+    let rotation = bits.read(info.rotation_bits);
+    let index_selection = bits.read(info.index_selection_bits);
+
+    let endpoint_count = info.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = bits.read(info.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        endpoint[3] = bits.read(info.alpha_bits);
+    }
+
+    let (mut color_bits, mut alpha_bits) = (info.color_bits, info.alpha_bits);
+    if info.endpoint_pbits || info.shared_pbits {
+        let mut pbits = [0u32; 6];
+        if info.endpoint_pbits {
+            for pbit in pbits.iter_mut().take(endpoint_count) {
+                *pbit = bits.read(1);
+            }
+        } else {
+            for subset in 0..info.subsets {
+                let pbit = bits.read(1);
+                pbits[subset * 2] = pbit;
+                pbits[subset * 2 + 1] = pbit;
+            }
+        }
+        for (endpoint, pbit) in endpoints.iter_mut().zip(pbits).take(endpoint_count) {
+            for value in endpoint.iter_mut().take(3) {
+                *value = (*value << 1) | pbit;
+            }
+            if alpha_bits > 0 {
+                endpoint[3] = (endpoint[3] << 1) | pbit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for value in endpoint.iter_mut().take(3) {
+            *value = expand_bits(*value, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 { expand_bits(endpoint[3], alpha_bits) } else { 255 };
+    }
+
+    let subset_of = |texel: usize| match info.subsets {
+        1 => 0,
+        2 => ((BC7_PARTITIONS_2[partition] >> texel) & 1) as usize,
+        _ => BC7_PARTITIONS_3[partition][texel] as usize,
+    };
+    let is_anchor = |texel: usize| texel == 0 || match info.subsets {
+        2 => texel == BC7_ANCHORS_2[partition] as usize,
+        3 => texel == BC7_ANCHORS_3_SECOND[partition] as usize || texel == BC7_ANCHORS_3_THIRD[partition] as usize,
+        _ => false,
+    };
+
+    // Anchor texels store their index with the top bit left out.
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        *index = bits.read(if is_anchor(texel) { info.index_bits - 1 } else { info.index_bits });
+    }
+    let mut secondary_indices = [0u32; 16];
+    if info.secondary_index_bits > 0 {
+        for (texel, index) in secondary_indices.iter_mut().enumerate() {
+            *index = bits.read(if texel == 0 { info.secondary_index_bits - 1 } else { info.secondary_index_bits });
+        }
+    }
+
+    std::array::from_fn(|texel| {
+        let subset = subset_of(texel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+
+        let (color_weight, alpha_weight) = if info.secondary_index_bits == 0 {
+            let weight = bc7_weight(info.index_bits, indices[texel]);
+            (weight, weight)
+        } else if index_selection == 0 {
+            (bc7_weight(info.index_bits, indices[texel]), bc7_weight(info.secondary_index_bits, secondary_indices[texel]))
+        } else {
+            (bc7_weight(info.secondary_index_bits, secondary_indices[texel]), bc7_weight(info.index_bits, indices[texel]))
+        };
+
+        let mut rgba = [
+            bc7_interpolate(e0[0], e1[0], color_weight),
+            bc7_interpolate(e0[1], e1[1], color_weight),
+            bc7_interpolate(e0[2], e1[2], color_weight),
+            bc7_interpolate(e0[3], e1[3], alpha_weight),
+        ];
+        if rotation > 0 {
+            rgba.swap(3, rotation as usize - 1);
+        }
+        rgba
+    })
+}
+
+// BC6H. Texels are returned as the bytes of Rgba16Float values with alpha set to one.
+
+// Header fields of a BC6H block: endpoints w, x, y and z of red, green and blue, then the partition.
+mod bc6h_field {
+    pub const RW: u8 = 0;
+    pub const GW: u8 = 1;
+    pub const BW: u8 = 2;
+    pub const RX: u8 = 3;
+    pub const GX: u8 = 4;
+    pub const BX: u8 = 5;
+    pub const RY: u8 = 6;
+    pub const GY: u8 = 7;
+    pub const BY: u8 = 8;
+    pub const RZ: u8 = 9;
+    pub const GZ: u8 = 10;
+    pub const BZ: u8 = 11;
+    pub const D: u8 = 12;
+}
+use bc6h_field::*;
+
+struct Bc6hMode {
+    transformed: bool,
+    endpoint_bits: u32,
+    delta_bits: [u32; 3],
+    // (field, first bit, last bit) in stream order, ranges stored top bit first run backwards.
+    fields: &'static [(u8, u8, u8)],
+}
+
+// Modes 1 to 10 have two regions, 11 to 14 have one.
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode { transformed: true, endpoint_bits: 10, delta_bits: [5, 5, 5], fields: &[
+        (GY, 4, 4), (BY, 4, 4), (BZ, 4, 4), (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4),
+        (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 7, delta_bits: [6, 6, 6], fields: &[
+        (GY, 5, 5), (GZ, 4, 4), (GZ, 5, 5), (RW, 0, 6), (BZ, 0, 0), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 6), (BY, 5, 5), (BZ, 2, 2),
+        (GY, 4, 4), (BW, 0, 6), (BZ, 3, 3), (BZ, 5, 5), (BZ, 4, 4), (RX, 0, 5), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 5),
+        (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 11, delta_bits: [5, 4, 4], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 4), (RW, 10, 10), (GY, 0, 3), (GX, 0, 3), (GW, 10, 10), (BZ, 0, 0), (GZ, 0, 3),
+        (BX, 0, 3), (BW, 10, 10), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 11, delta_bits: [4, 5, 4], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 10, 10), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (GW, 10, 10), (GZ, 0, 3),
+        (BX, 0, 3), (BW, 10, 10), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 3), (BZ, 0, 0), (BZ, 2, 2), (RZ, 0, 3), (GY, 4, 4), (BZ, 3, 3),
+        (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 11, delta_bits: [4, 4, 5], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 10, 10), (BY, 4, 4), (GY, 0, 3), (GX, 0, 3), (GW, 10, 10), (BZ, 0, 0),
+        (GZ, 0, 3), (BX, 0, 4), (BW, 10, 10), (BY, 0, 3), (RY, 0, 3), (BZ, 1, 1), (BZ, 2, 2), (RZ, 0, 3), (BZ, 4, 4), (BZ, 3, 3),
+        (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 9, delta_bits: [5, 5, 5], fields: &[
+        (RW, 0, 8), (BY, 4, 4), (GW, 0, 8), (GY, 4, 4), (BW, 0, 8), (BZ, 4, 4), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4),
+        (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 8, delta_bits: [6, 5, 5], fields: &[
+        (RW, 0, 7), (GZ, 4, 4), (BY, 4, 4), (GW, 0, 7), (BZ, 2, 2), (GY, 4, 4), (BW, 0, 7), (BZ, 3, 3), (BZ, 4, 4), (RX, 0, 5),
+        (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 8, delta_bits: [5, 6, 5], fields: &[
+        (RW, 0, 7), (BZ, 0, 0), (BY, 4, 4), (GW, 0, 7), (GY, 5, 5), (GY, 4, 4), (BW, 0, 7), (GZ, 5, 5), (BZ, 4, 4), (RX, 0, 4),
+        (GZ, 4, 4), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4),
+        (BZ, 3, 3), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 8, delta_bits: [5, 5, 6], fields: &[
+        (RW, 0, 7), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 7), (BY, 5, 5), (GY, 4, 4), (BW, 0, 7), (BZ, 5, 5), (BZ, 4, 4), (RX, 0, 4),
+        (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 5), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4),
+        (BZ, 3, 3), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: false, endpoint_bits: 6, delta_bits: [6, 6, 6], fields: &[
+        (RW, 0, 5), (GZ, 4, 4), (BZ, 0, 0), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 5), (GY, 5, 5), (BY, 5, 5), (BZ, 2, 2), (GY, 4, 4),
+        (BW, 0, 5), (GZ, 5, 5), (BZ, 3, 3), (BZ, 5, 5), (BZ, 4, 4), (RX, 0, 5), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 5),
+        (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5), (D, 0, 4),
+    ] },
+    Bc6hMode { transformed: false, endpoint_bits: 10, delta_bits: [10, 10, 10], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 9), (GX, 0, 9), (BX, 0, 9),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 11, delta_bits: [9, 9, 9], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 8), (RW, 10, 10), (GX, 0, 8), (GW, 10, 10), (BX, 0, 8), (BW, 10, 10),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 12, delta_bits: [8, 8, 8], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 7), (RW, 11, 10), (GX, 0, 7), (GW, 11, 10), (BX, 0, 7), (BW, 11, 10),
+    ] },
+    Bc6hMode { transformed: true, endpoint_bits: 16, delta_bits: [4, 4, 4], fields: &[
+        (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 15, 10), (GX, 0, 3), (GW, 15, 10), (BX, 0, 3), (BW, 15, 10),
+    ] },
+];
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+// Spreads an endpoint over the full 16 bit (15 bit and sign for signed blocks) range.
+fn bc6h_unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        if bits >= 15 || value == 0 {
+            value
+        } else if value == (1 << bits) - 1 {
+            0xFFFF
+        } else {
+            ((value << 16) + 0x8000) >> bits
+        }
+    } else if bits >= 16 {
+        value
+    } else {
+        let magnitude = value.abs();
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+        if value < 0 { -unquantized } else { unquantized }
+    }
+}
+
+// Scales an interpolated value down to the bits of a half float.
+fn bc6h_finish(value: i32, signed: bool) -> u16 {
+    if !signed {
+        ((value * 31) >> 6) as u16
+    } else if value < 0 {
+        0x8000 | ((-value * 31) >> 5) as u16
+    } else {
+        ((value * 31) >> 5) as u16
+    }
+}
+
+fn decode_bc6h_block(block: &[u8], signed: bool) -> [[u8; 8]; 16] {
+    let mut bits = BitReader { bits: u128::from_le_bytes(block.try_into().unwrap()), position: 0 };
+
+    // Modes 1 and 2 use two mode bits, the others five. Reserved modes decode to black.
+    let mode = match bits.read(2) {
+        mode @ (0 | 1) => mode,
+        low => low | (bits.read(3) << 2),
+    };
+    let mode = match mode {
+        0x00 => 0,
+        0x01 => 1,
+        0x02 => 2,
+        0x06 => 3,
+        0x0A => 4,
+        0x0E => 5,
+        0x12 => 6,
+        0x16 => 7,
+        0x1A => 8,
+        0x1E => 9,
+        0x03 => 10,
+        0x07 => 11,
+        0x0B => 12,
+        0x0F => 13,
+        _ => return [[0; 8]; 16],
+    };
+    let info = &BC6H_MODES[mode];
+    let regions = if mode < 10 { 2 } else { 1 };
+
+    let mut header = [0i32; 13];
+    for &(field, first, last) in info.fields {
+        let mut bit = first;
+        loop {
+            header[field as usize] |= (bits.read(1) as i32) << bit;
+            if bit == last {
+                break;
+            }
+            bit = if last > first { bit + 1 } else { bit - 1 };
+        }
+    }
+
+    let mut endpoints = [[0i32; 3]; 4];
+    for (index, endpoint) in endpoints.iter_mut().enumerate().take(regions * 2) {
+        *endpoint = std::array::from_fn(|channel| header[index * 3 + channel]);
+    }
+    if signed {
+        endpoints[0] = endpoints[0].map(|value| sign_extend(value, info.endpoint_bits));
+    }
+    // Transformed modes store the other endpoints as deltas from the first one.
+    let base = endpoints[0];
+    for endpoint in endpoints.iter_mut().take(regions * 2).skip(1) {
+        for channel in 0..3 {
+            let value = endpoint[channel];
+            endpoint[channel] = if info.transformed {
+                let value = (base[channel] + sign_extend(value, info.delta_bits[channel])) & ((1 << info.endpoint_bits) - 1);
+                if signed { sign_extend(value, info.endpoint_bits) } else { value }
+            } else if signed {
+                sign_extend(value, info.endpoint_bits)
+            } else {
+                value
+            };
+        }
+    }
+    let endpoints = endpoints.map(|endpoint| endpoint.map(|value| bc6h_unquantize(value, info.endpoint_bits, signed)));
+
+    let partition = header[D as usize] as usize;
+    let index_bits = if regions == 2 { 3 } else { 4 };
+    let is_anchor = |texel: usize| texel == 0 || (regions == 2 && texel == BC7_ANCHORS_2[partition] as usize);
+
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        *index = bits.read(if is_anchor(texel) { index_bits - 1 } else { index_bits });
+    }
+
+    std::array::from_fn(|texel| {
+        let weight = bc7_weight(index_bits, indices[texel]) as i32;
+        let region = if regions == 2 { ((BC7_PARTITIONS_2[partition] >> texel) & 1) as usize } else { 0 };
+        let (e0, e1) = (endpoints[region * 2], endpoints[region * 2 + 1]);
+
+        let mut rgba = [0; 8];
+        for channel in 0..3 {
+            let value = ((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6;
+            rgba[channel * 2..channel * 2 + 2].copy_from_slice(&bc6h_finish(value, signed).to_le_bytes());
+        }
+        rgba[6..8].copy_from_slice(&half::f16::ONE.to_bits().to_le_bytes());
+        rgba
+    })
+}
+
+// ETC2. The texel indices of a block run column by column.
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [[2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183]];
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14], [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12], [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11], [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10], [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9], [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9], [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9], [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8], [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn bits_at(bits: u64, shift: u32, count: u32) -> i32 {
+    ((bits >> shift) & ((1 << count) - 1)) as i32
+}
+
+fn clamp_u8(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+fn offset_color(color: [i32; 3], offset: i32) -> [u8; 4] {
+    [clamp_u8(color[0] + offset), clamp_u8(color[1] + offset), clamp_u8(color[2] + offset), 255]
+}
+
+fn decode_etc2_color(block: &[u8], punchthrough: bool) -> [[u8; 4]; 16] {
+    let bits = u64::from_be_bytes(block[0..8].try_into().unwrap());
+    let flip = (bits >> 32) & 1 == 1;
+    // With punchthrough alpha the differential bit says whether the block is opaque.
+    let differential = punchthrough || (bits >> 33) & 1 == 1;
+    let opaque = !punchthrough || (bits >> 33) & 1 == 1;
+
+    let texel_index = |x: usize, y: usize| {
+        let i = x * 4 + y;
+        (((bits >> (16 + i)) & 1) << 1 | ((bits >> i) & 1)) as usize
+    };
+    let extend_4 = |value: i32| value * 17;
+    let extend_5 = |value: i32| (value << 3) | (value >> 2);
+
+    if !differential {
+        let base = [
+            [extend_4(bits_at(bits, 60, 4)), extend_4(bits_at(bits, 52, 4)), extend_4(bits_at(bits, 44, 4))],
+            [extend_4(bits_at(bits, 56, 4)), extend_4(bits_at(bits, 48, 4)), extend_4(bits_at(bits, 40, 4))],
+        ];
+        let tables = [bits_at(bits, 37, 3) as usize, bits_at(bits, 34, 3) as usize];
+        return etc_subblocks(base, tables, flip, true, texel_index);
+    }
+
+    let r = bits_at(bits, 59, 5);
+    let g = bits_at(bits, 51, 5);
+    let b = bits_at(bits, 43, 5);
+    let signed_3 = |value: i32| if value >= 4 { value - 8 } else { value };
+    let r2 = r + signed_3(bits_at(bits, 56, 3));
+    let g2 = g + signed_3(bits_at(bits, 48, 3));
+    let b2 = b + signed_3(bits_at(bits, 40, 3));
+
+    let transparent_index = if opaque { None } else { Some(2) };
+    let paint = |palette: [[u8; 4]; 4]| -> [[u8; 4]; 16] {
+        std::array::from_fn(|texel| {
+            let index = texel_index(texel % 4, texel / 4);
+            if transparent_index == Some(index) { [0; 4] } else { palette[index] }
+        })
+    };
+
+    if !(0..32).contains(&r2) {
+        // T mode
+        let c1 = [
+            extend_4((bits_at(bits, 59, 2) << 2) | bits_at(bits, 56, 2)),
+            extend_4(bits_at(bits, 52, 4)),
+            extend_4(bits_at(bits, 48, 4)),
+        ];
+        let c2 = [extend_4(bits_at(bits, 44, 4)), extend_4(bits_at(bits, 40, 4)), extend_4(bits_at(bits, 36, 4))];
+        let distance = ETC_DISTANCES[((bits_at(bits, 34, 2) << 1) | bits_at(bits, 32, 1)) as usize];
+        paint([offset_color(c1, 0), offset_color(c2, distance), offset_color(c2, 0), offset_color(c2, -distance)])
+    } else if !(0..32).contains(&g2) {
+        // H mode
+        let r1 = bits_at(bits, 59, 4);
+        let g1 = (bits_at(bits, 56, 3) << 1) | bits_at(bits, 52, 1);
+        let b1 = (bits_at(bits, 51, 1) << 3) | bits_at(bits, 47, 3);
+        let (r2, g2, b2) = (bits_at(bits, 43, 4), bits_at(bits, 39, 4), bits_at(bits, 35, 4));
+        let order_bit = (((r1 << 8) | (g1 << 4) | b1) >= ((r2 << 8) | (g2 << 4) | b2)) as i32;
+        let distance = ETC_DISTANCES[((bits_at(bits, 34, 1) << 2) | (bits_at(bits, 32, 1) << 1) | order_bit) as usize];
+        let c1 = [extend_4(r1), extend_4(g1), extend_4(b1)];
+        let c2 = [extend_4(r2), extend_4(g2), extend_4(b2)];
+        paint([offset_color(c1, distance), offset_color(c1, -distance), offset_color(c2, distance), offset_color(c2, -distance)])
+    } else if !(0..32).contains(&b2) {
+        // Planar mode, always opaque
+        let extend_6 = |value: i32| (value << 2) | (value >> 4);
+        let extend_7 = |value: i32| (value << 1) | (value >> 6);
+        let origin = [
+            extend_6(bits_at(bits, 57, 6)),
+            extend_7((bits_at(bits, 56, 1) << 6) | bits_at(bits, 49, 6)),
+            extend_6((bits_at(bits, 48, 1) << 5) | (bits_at(bits, 43, 2) << 3) | bits_at(bits, 39, 3)),
+        ];
+        let horizontal = [
+            extend_6((bits_at(bits, 34, 5) << 1) | bits_at(bits, 32, 1)),
+            extend_7(bits_at(bits, 25, 7)),
+            extend_6(bits_at(bits, 19, 6)),
+        ];
+        let vertical = [extend_6(bits_at(bits, 13, 6)), extend_7(bits_at(bits, 6, 7)), extend_6(bits_at(bits, 0, 6))];
+        std::array::from_fn(|texel| {
+            let (x, y) = ((texel % 4) as i32, (texel / 4) as i32);
+            let channel = |c: usize| clamp_u8((x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]) + 4 * origin[c] + 2) >> 2);
+            [channel(0), channel(1), channel(2), 255]
+        })
+    } else {
+        let base = [[extend_5(r), extend_5(g), extend_5(b)], [extend_5(r2), extend_5(g2), extend_5(b2)]];
+        let tables = [bits_at(bits, 37, 3) as usize, bits_at(bits, 34, 3) as usize];
+        etc_subblocks(base, tables, flip, opaque, texel_index)
+    }
+}
+
+fn etc_subblocks(base: [[i32; 3]; 2], tables: [usize; 2], flip: bool, opaque: bool, texel_index: impl Fn(usize, usize) -> usize) -> [[u8; 4]; 16] {
+    std::array::from_fn(|texel| {
+        let (x, y) = (texel % 4, texel / 4);
+        let subblock = if flip { (y >= 2) as usize } else { (x >= 2) as usize };
+        let [small, large] = ETC_MODIFIERS[tables[subblock]];
+        let offset = match (texel_index(x, y), opaque) {
+            (0, true) => small,
+            (0, false) => 0,
+            (1, _) => large,
+            (2, true) => -small,
+            (2, false) => return [0; 4],
+            _ => -large,
+        };
+        offset_color(base[subblock], offset)
+    })
+}
+
+fn eac_index(bits: u64, texel: usize) -> usize {
+    let i = (texel % 4) * 4 + texel / 4;
+    bits_at(bits, 45 - 3 * i as u32, 3) as usize
+}
+
+fn decode_eac_alpha(block: &[u8]) -> [u8; 16] {
+    let bits = u64::from_be_bytes(block[0..8].try_into().unwrap());
+    let base = bits_at(bits, 56, 8);
+    let multiplier = bits_at(bits, 52, 4);
+    let table = EAC_MODIFIERS[bits_at(bits, 48, 4) as usize];
+    std::array::from_fn(|texel| clamp_u8(base + table[eac_index(bits, texel)] * multiplier))
+}
+
+// R11 channels hold 11 bits, only the top 8 are kept.
+fn decode_eac_r11(block: &[u8], signed: bool) -> [u8; 16] {
+    let bits = u64::from_be_bytes(block[0..8].try_into().unwrap());
+    let multiplier = bits_at(bits, 52, 4);
+    let table = EAC_MODIFIERS[bits_at(bits, 48, 4) as usize];
+    let offset = |texel: usize| {
+        let modifier = table[eac_index(bits, texel)];
+        if multiplier == 0 { modifier } else { modifier * multiplier * 8 }
+    };
+
+    if signed {
+        let base = (bits_at(bits, 56, 8) as u8 as i8).max(-127) as i32 * 8;
+        std::array::from_fn(|texel| ((base + offset(texel)).clamp(-1023, 1023) * 127 / 1023) as i8 as u8)
+    } else {
+        let base = bits_at(bits, 56, 8) * 8 + 4;
+        std::array::from_fn(|texel| ((base + offset(texel)).clamp(0, 2047) * 255 / 2047) as u8)
+    }
+}
+
+fn decode_etc2_rgb8_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_etc2_color(block, false)
+}
+
+fn decode_etc2_rgb8a1_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_etc2_color(block, true)
+}
+
+fn decode_etc2_rgba8_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = decode_etc2_color(&block[8..16], false);
+    let alpha = decode_eac_alpha(&block[0..8]);
+    for (rgba, a) in texels.iter_mut().zip(alpha) {
+        rgba[3] = a;
+    }
+    texels
+}
+
+fn decode_eac_r11_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_eac_r11(block, false).map(|r| [r, 0, 0, 255])
+}
+
+fn decode_eac_r11_snorm_block(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_eac_r11(block, true).map(|r| [r, 0, 0, 127])
+}
+
+fn decode_eac_rg11_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_eac_r11(&block[0..8], false);
+    let green = decode_eac_r11(&block[8..16], false);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 255])
+}
+
+fn decode_eac_rg11_snorm_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_eac_r11(&block[0..8], true);
+    let green = decode_eac_r11(&block[8..16], true);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 127])
+}
+
+// ASTC, LDR profile only. Every footprint packs into 128 bits and texels are returned row by
+// row. Blocks that are malformed or need the HDR profile decode to magenta.
+
+const ASTC_ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+// (trits, quints, bits) of every integer sequence range, from 2 up to 256 levels.
+const ASTC_RANGES: [(u32, u32, u32); 21] = [
+    (0, 0, 1), (1, 0, 0), (0, 0, 2), (0, 1, 0), (1, 0, 1), (0, 0, 3), (0, 1, 1), (1, 0, 2), (0, 0, 4), (0, 1, 2), (1, 0, 3),
+    (0, 0, 5), (0, 1, 3), (1, 0, 4), (0, 0, 6), (0, 1, 4), (1, 0, 5), (0, 0, 7), (0, 1, 5), (1, 0, 6), (0, 0, 8),
+];
+
+struct AstcWeightGrid {
+    width: usize,
+    height: usize,
+    dual_plane: bool,
+    range: usize,
+}
+
+fn astc_weight_grid(mode: u32) -> Option<AstcWeightGrid> {
+    let a = ((mode >> 5) & 3) as usize;
+    let mut high_precision = (mode >> 9) & 1 == 1;
+    let mut dual_plane = (mode >> 10) & 1 == 1;
+
+    let (width, height, precision) = if mode & 3 != 0 {
+        let b = ((mode >> 7) & 3) as usize;
+        let (width, height) = match (mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if mode & 0x100 != 0 => ((b & 1) + 2, a + 2),
+            _ => (a + 2, (b & 1) + 6),
+        };
+        (width, height, ((mode >> 4) & 1) | ((mode & 3) << 1))
+    } else {
+        if (mode >> 2) & 3 == 0 {
+            return None;
+        }
+        let b = ((mode >> 9) & 3) as usize;
+        let (width, height) = match (mode >> 7) & 3 {
+            0 => (12, a + 2),
+            1 => (a + 2, 12),
+            2 => {
+                high_precision = false;
+                dual_plane = false;
+                (a + 6, b + 6)
+            }
+            _ => match a {
+                0 => (6, 10),
+                1 => (10, 6),
+                _ => return None,
+            },
+        };
+        (width, height, ((mode >> 4) & 1) | (((mode >> 2) & 3) << 1))
+    };
+
+    Some(AstcWeightGrid {
+        width,
+        height,
+        dual_plane,
+        range: precision as usize - 2 + if high_precision { 6 } else { 0 },
+    })
+}
+
+fn astc_sequence_bits(count: usize, range: usize) -> u32 {
+    let (trits, quints, bits) = ASTC_RANGES[range];
+    let count = count as u32;
+    count * bits + trits * (8 * count).div_ceil(5) + quints * (7 * count).div_ceil(3)
+}
+
+fn astc_trits(packed: u32) -> [u32; 5] {
+    let bit = |value: u32, n: u32| (value >> n) & 1;
+    let (c, t3, t4) = if (packed >> 2) & 7 == 7 {
+        ((((packed >> 5) & 7) << 2) | (packed & 3), 2, 2)
+    } else if (packed >> 5) & 3 == 3 {
+        (packed & 0x1F, bit(packed, 7), 2)
+    } else {
+        (packed & 0x1F, (packed >> 5) & 3, bit(packed, 7))
+    };
+    let (t0, t1, t2) = if c & 3 == 3 {
+        ((bit(c, 3) << 1) | (bit(c, 2) & !bit(c, 3) & 1), bit(c, 4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        ((bit(c, 1) << 1) | (bit(c, 0) & !bit(c, 1) & 1), (c >> 2) & 3, bit(c, 4))
+    };
+    [t0, t1, t2, t3, t4]
+}
+
+fn astc_quints(packed: u32) -> [u32; 3] {
+    let bit = |value: u32, n: u32| (value >> n) & 1;
+    if (packed >> 1) & 3 == 3 && (packed >> 5) & 3 == 0 {
+        let low = bit(packed, 0);
+        let q2 = (low << 2) | ((bit(packed, 4) & !low & 1) << 1) | (bit(packed, 3) & !low & 1);
+        return [4, 4, q2];
+    }
+    let (c, q2) = if (packed >> 1) & 3 == 3 {
+        ((((packed >> 3) & 3) << 3) | ((!(packed >> 5) & 3) << 1) | (packed & 1), 4)
+    } else {
+        (packed & 0x1F, (packed >> 5) & 3)
+    };
+    let (q0, q1) = if c & 7 == 5 { ((c >> 3) & 3, 4) } else { (c & 7, (c >> 3) & 3) };
+    [q0, q1, q2]
+}
+
+// Reads `count` values of an integer sequence, trits and quints are packed in blocks of
+// five and three values interleaved with the plain bits.
+fn astc_decode_sequence(data: u128, count: usize, range: usize) -> Vec<u32> {
+    let (trits, quints, bits) = ASTC_RANGES[range];
+    // Whatever follows the sequence reads as zero.
+    let length = astc_sequence_bits(count, range);
+    let mut data = BitReader { bits: data & u128::MAX.checked_shr(128 - length).unwrap_or(0), position: 0 };
+
+    let mut values = Vec::with_capacity(count + 4);
+    while values.len() < count {
+        if trits == 1 {
+            let mut low = [0; 5];
+            let mut packed = 0;
+            for (index, (value, packed_bits)) in low.iter_mut().zip([2, 2, 1, 2, 1]).enumerate() {
+                *value = data.read(bits);
+                packed |= data.read(packed_bits) << [0, 2, 4, 5, 7][index];
+            }
+            values.extend(astc_trits(packed).iter().zip(low).map(|(trit, low)| (trit << bits) | low));
+        } else if quints == 1 {
+            let mut low = [0; 3];
+            let mut packed = 0;
+            for (index, (value, packed_bits)) in low.iter_mut().zip([3, 2, 2]).enumerate() {
+                *value = data.read(bits);
+                packed |= data.read(packed_bits) << [0, 3, 5][index];
+            }
+            values.extend(astc_quints(packed).iter().zip(low).map(|(quint, low)| (quint << bits) | low));
+        } else {
+            values.push(data.read(bits));
+        }
+    }
+    values.truncate(count);
+    values
+}
+
+fn replicate_bits(value: u32, bits: u32, to: u32) -> u32 {
+    let (mut result, mut filled) = (0, 0);
+    while filled < to {
+        result = (result << bits) | value;
+        filled += bits;
+    }
+    result >> (filled - to)
+}
+
+// Builds a bit pattern of the unquantization tables, letters a to f stand for the low bits of `value`.
+fn astc_bit_pattern(pattern: &str, value: u32) -> u32 {
+    pattern.bytes().fold(0, |bits, letter| (bits << 1) | match letter {
+        b'0' => 0,
+        letter => (value >> (letter - b'a')) & 1,
+    })
+}
+
+fn astc_unquantize_color(value: u32, range: usize) -> i32 {
+    let (trits, quints, bits) = ASTC_RANGES[range];
+    if trits == 0 && quints == 0 {
+        return replicate_bits(value, bits, 8) as i32;
+    }
+    let (pattern, scale) = match (trits == 1, bits) {
+        (true, 1) => ("000000000", 204),
+        (false, 1) => ("000000000", 113),
+        (true, 2) => ("b000b0bb0", 93),
+        (false, 2) => ("b0000bb00", 54),
+        (true, 3) => ("cb000cbcb", 44),
+        (false, 3) => ("cb0000cbc", 26),
+        (true, 4) => ("dcb000dcb", 22),
+        (false, 4) => ("dcb0000dc", 13),
+        (true, 5) => ("edcb000ed", 11),
+        (false, 5) => ("edcb0000e", 6),
+        _ => ("fedcb000f", 5),
+    };
+    let a = if value & 1 == 1 { 0x1FF } else { 0 };
+    let t = ((value >> bits) * scale + astc_bit_pattern(pattern, value)) ^ a;
+    ((a & 0x80) | (t >> 2)) as i32
+}
+
+// Weights end up in 0..=64.
+fn astc_unquantize_weight(value: u32, range: usize) -> u32 {
+    let (trits, quints, bits) = ASTC_RANGES[range];
+    let weight = if trits == 0 && quints == 0 {
+        replicate_bits(value, bits, 6)
+    } else if bits == 0 {
+        return value * if trits == 1 { 32 } else { 16 };
+    } else {
+        let (pattern, scale) = match (trits == 1, bits) {
+            (true, 1) => ("0000000", 50),
+            (false, 1) => ("0000000", 28),
+            (true, 2) => ("b000b0b", 23),
+            (false, 2) => ("b0000b0", 13),
+            _ => ("cb000cb", 11),
+        };
+        let a = if value & 1 == 1 { 0x7F } else { 0 };
+        let t = ((value >> bits) * scale + astc_bit_pattern(pattern, value)) ^ a;
+        (a & 0x20) | (t >> 2)
+    };
+    if weight > 32 { weight + 1 } else { weight }
+}
+
+fn astc_hash(seed: u32) -> u32 {
+    let mut value = seed;
+    value ^= value >> 15;
+    value = value.wrapping_mul(0xEEDE0891);
+    value ^= value >> 5;
+    value = value.wrapping_add(value << 16);
+    value ^= value >> 7;
+    value ^= value >> 3;
+    value ^= value << 6;
+    value ^= value >> 17;
+    value
+}
+
+fn astc_partition(seed: u32, x: u32, y: u32, partitions: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block { (x << 1, y << 1) } else { (x, y) };
+    let seed = seed + (partitions - 1) * 1024;
+    let random = astc_hash(seed);
+
+    let (shift1, shift2) = if seed & 1 == 1 {
+        (if seed & 2 != 0 { 4 } else { 5 }, if partitions == 3 { 6 } else { 5 })
+    } else {
+        (if partitions == 3 { 6 } else { 5 }, if seed & 2 != 0 { 4 } else { 5 })
+    };
+    // Blocks are 2D, the seeds of the z terms aren't needed.
+    let seeds: [u32; 8] = std::array::from_fn(|index| {
+        let value = (random >> (index * 4)) & 0xF;
+        (value * value) >> if index % 2 == 0 { shift1 } else { shift2 }
+    });
+
+    let a = (seeds[0] * x + seeds[1] * y + (random >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (random >> 10)) & 0x3F;
+    let c = if partitions >= 3 { (seeds[4] * x + seeds[5] * y + (random >> 6)) & 0x3F } else { 0 };
+    let d = if partitions >= 4 { (seeds[6] * x + seeds[7] * y + (random >> 2)) & 0x3F } else { 0 };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+// Turns the values of an LDR color endpoint mode into two RGBA endpoints.
+fn astc_endpoints(mode: u32, v: &[i32]) -> [[i32; 4]; 2] {
+    let blue_contract = |[r, g, b, a]: [i32; 4]| [(r + b) >> 1, (g + b) >> 1, b, a];
+    // Returns the signed offset and the base that share the bits of `a` and `b`.
+    let transfer = |a: i32, b: i32| {
+        let base = (b >> 1) | (a & 0x80);
+        let offset = (a >> 1) & 0x3F;
+        (if offset & 0x20 != 0 { offset - 0x40 } else { offset }, base)
+    };
+
+    let endpoints = match mode {
+        0 => [[v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]],
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            [[l0, l0, l0, 255], [l1, l1, l1, 255]]
+        }
+        4 => [[v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]],
+        5 => {
+            let (dl, l) = transfer(v[1], v[0]);
+            let (da, a) = transfer(v[3], v[2]);
+            [[l, l, l, a], [l + dl, l + dl, l + dl, a + da]]
+        }
+        6 | 10 => {
+            let alpha = if mode == 10 { [v[4], v[5]] } else { [255, 255] };
+            [[(v[0] * v[3]) >> 8, (v[1] * v[3]) >> 8, (v[2] * v[3]) >> 8, alpha[0]], [v[0], v[1], v[2], alpha[1]]]
+        }
+        8 | 12 => {
+            let alpha = if mode == 12 { [v[6], v[7]] } else { [255, 255] };
+            let (e0, e1) = ([v[0], v[2], v[4], alpha[0]], [v[1], v[3], v[5], alpha[1]]);
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] { [e0, e1] } else { [blue_contract(e1), blue_contract(e0)] }
+        }
+        _ => {
+            let (dr, r) = transfer(v[1], v[0]);
+            let (dg, g) = transfer(v[3], v[2]);
+            let (db, b) = transfer(v[5], v[4]);
+            let (da, a) = if mode == 13 { transfer(v[7], v[6]) } else { (0, 255) };
+            let (base, offset) = ([r, g, b, a], [r + dr, g + dg, b + db, a + da]);
+            if dr + dg + db >= 0 { [base, offset] } else { [blue_contract(offset), blue_contract(base)] }
+        }
+    };
+    endpoints.map(|endpoint| endpoint.map(|value| value.clamp(0, 255)))
+}
+
+fn decode_astc_block(block: &[u8], block_width: usize, block_height: usize, srgb: bool) -> Vec<[u8; 4]> {
+    decode_astc(block, block_width, block_height, srgb).unwrap_or_else(|| vec![ASTC_ERROR_COLOR; block_width * block_height])
+}
+
+fn decode_astc(block: &[u8], block_width: usize, block_height: usize, srgb: bool) -> Option<Vec<[u8; 4]>> {
+    let bits = u128::from_le_bytes(block.try_into().unwrap());
+    let read = |start: u32, count: u32| ((bits >> start) & ((1u128 << count) - 1)) as u32;
+    let texel_count = block_width * block_height;
+
+    let mode = read(0, 11);
+    if mode & 0x1FF == 0x1FC {
+        // Void extent blocks hold a single color, as UNORM16 unless they are HDR.
+        if mode & 0x200 != 0 {
+            return None;
+        }
+        let color = [read(64, 16), read(80, 16), read(96, 16), read(112, 16)].map(|channel| (channel >> 8) as u8);
+        return Some(vec![color; texel_count]);
+    }
+
+    let grid = astc_weight_grid(mode)?;
+    let planes = if grid.dual_plane { 2 } else { 1 };
+    let weight_count = grid.width * grid.height * planes;
+    let weight_bits = astc_sequence_bits(weight_count, grid.range);
+    if grid.width > block_width || grid.height > block_height || weight_count > 64 || !(24..=96).contains(&weight_bits) {
+        return None;
+    }
+
+    let partitions = read(11, 2) + 1;
+    if partitions == 4 && grid.dual_plane {
+        return None;
+    }
+
+    // Weights fill the block from the top, the extra mode bits and the dual plane channel sit below them.
+    let mut below_weights = 128 - weight_bits;
+    let (partition_seed, endpoint_modes, color_start) = if partitions == 1 {
+        (0, vec![read(13, 4)], 17)
+    } else {
+        let selector = read(23, 2);
+        let modes = if selector == 0 {
+            vec![read(25, 4); partitions as usize]
+        } else {
+            let extra_bits = 3 * partitions - 4;
+            below_weights -= extra_bits;
+            let encoded = (read(23, 6) | (read(below_weights, extra_bits) << 6)) >> 2;
+            (0..partitions).map(|partition| {
+                let class = selector - 1 + ((encoded >> partition) & 1);
+                (class << 2) | ((encoded >> (partitions + 2 * partition)) & 3)
+            }).collect()
+        };
+        (read(13, 10), modes, 29)
+    };
+    let plane2_channel = if grid.dual_plane {
+        below_weights -= 2;
+        Some(read(below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    // Modes 2, 3, 7, 11, 14 and 15 are HDR.
+    if endpoint_modes.iter().any(|mode| matches!(mode, 2 | 3 | 7 | 11 | 14 | 15)) {
+        return None;
+    }
+    let color_count: usize = endpoint_modes.iter().map(|mode| ((mode >> 2) as usize + 1) * 2).sum();
+    let color_bits = below_weights.checked_sub(color_start)?;
+    if color_count > 18 {
+        return None;
+    }
+    let color_range = (4..ASTC_RANGES.len()).rev().find(|&range| astc_sequence_bits(color_count, range) <= color_bits)?;
+    let colors: Vec<i32> = astc_decode_sequence(bits >> color_start, color_count, color_range)
+        .into_iter()
+        .map(|value| astc_unquantize_color(value, color_range))
+        .collect();
+
+    let mut offset = 0;
+    let endpoints: Vec<[[i32; 4]; 2]> = endpoint_modes.iter().map(|&mode| {
+        let count = ((mode >> 2) as usize + 1) * 2;
+        offset += count;
+        astc_endpoints(mode, &colors[offset - count..offset])
+    }).collect();
+
+    // The weight sequence is stored bit reversed.
+    let weights: Vec<u32> = astc_decode_sequence(bits.reverse_bits(), weight_count, grid.range)
+        .into_iter()
+        .map(|value| astc_unquantize_weight(value, grid.range))
+        .collect();
+
+    // Weight grids smaller than the block get bilinearly filled in.
+    let scale_x = (1024 + block_width / 2) / (block_width - 1);
+    let scale_y = (1024 + block_height / 2) / (block_height - 1);
+    let infill = |plane: usize, x: usize, y: usize| {
+        let gs = (scale_x * x * (grid.width - 1) + 32) >> 6;
+        let gt = (scale_y * y * (grid.height - 1) + 32) >> 6;
+        let (js, fs) = (gs >> 4, (gs & 0xF) as u32);
+        let (jt, ft) = (gt >> 4, (gt & 0xF) as u32);
+        let weight = |s: usize, t: usize| if s < grid.width && t < grid.height { weights[(t * grid.width + s) * planes + plane] } else { 0 };
+
+        let w11 = (fs * ft + 8) >> 4;
+        let (w10, w01) = (ft - w11, fs - w11);
+        let w00 = 16 + w11 - fs - ft;
+        (weight(js, jt) * w00 + weight(js + 1, jt) * w01 + weight(js, jt + 1) * w10 + weight(js + 1, jt + 1) * w11 + 8) >> 4
+    };
+
+    // sRGB endpoints are expanded to 16 bits without replicating the byte.
+    let expand = |value: i32| if srgb { (value << 8) | 0x80 } else { value * 257 };
+    let mut texels = Vec::with_capacity(texel_count);
+    for y in 0..block_height {
+        for x in 0..block_width {
+            let partition = match partitions {
+                1 => 0,
+                _ => astc_partition(partition_seed, x as u32, y as u32, partitions, texel_count < 31),
+            };
+            let [e0, e1] = endpoints[partition];
+            let weights = [infill(0, x, y) as i32, if grid.dual_plane { infill(1, x, y) as i32 } else { 0 }];
+            texels.push(std::array::from_fn(|channel| {
+                let weight = if plane2_channel == Some(channel) { weights[1] } else { weights[0] };
+                let value = (expand(e0[channel]) * (64 - weight) + expand(e1[channel]) * weight + 32) >> 6;
+                (value >> 8) as u8
+            }));
+        }
+    }
+    Some(texels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Packs (value, bit count) fields into a block, least significant bit first like BC7.
+    fn pack_bits(fields: &[(u32, u32)]) -> [u8; 16] {
+        let mut bits = 0u128;
+        let mut position = 0;
+        for &(value, count) in fields {
+            bits |= (value as u128) << position;
+            position += count;
+        }
+        assert!(position <= 128);
+        bits.to_le_bytes()
+    }
+
+    // A constant orange block, HDR void extents hold half floats instead.
+    fn astc_void_extent(hdr: bool) -> [u8; 16] {
+        pack_bits(&[(0xDFC | ((hdr as u32) << 9), 12), (0x3FFFFFF, 26), (0x3FFFFFF, 26), (0xFF00, 16), (0x8000, 16), (0, 16), (0xFFFF, 16)])
+    }
+
+    // One partition of direct RGB endpoints, red0 red1 green0 green1 blue0 blue1, and a 4x4
+    // grid of 2 bit weights. Weights are stored bit reversed from the top of the block.
+    fn astc_rgb_block(colors: [u32; 6], weights: [u32; 16]) -> [u8; 16] {
+        let mut fields = vec![(66, 11), (0, 2), (8, 4)];
+        fields.extend(colors.map(|color| (color, 8)));
+        let mut bits = u128::from_le_bytes(pack_bits(&fields));
+        for (texel, weight) in weights.iter().enumerate() {
+            for bit in 0..2 {
+                bits |= (((weight >> bit) & 1) as u128) << (127 - (texel * 2 + bit));
+            }
+        }
+        bits.to_le_bytes()
+    }
+
+    const BC1_FOUR_COLOR: [u8; 8] = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0x00, 0x00, 0x00];
+    const ETC2_INDIVIDUAL: [u8; 8] = [0xF0, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x10];
+    const EAC_BLOCK: [u8; 8] = [0x80, 0x10, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn bc1_four_color_block() {
+        let texels = decode_bc1_block(&BC1_FOUR_COLOR);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 255]);
+        assert_eq!(texels[2], [170, 0, 85, 255]);
+        assert_eq!(texels[3], [85, 0, 170, 255]);
+        assert_eq!(texels[15], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bc1_three_color_block_has_transparent_black() {
+        let texels = decode_bc1_block(&[0x1F, 0x00, 0x00, 0xF8, 0xE4, 0x00, 0x00, 0x00]);
+        assert_eq!(texels[0], [0, 0, 255, 255]);
+        assert_eq!(texels[1], [255, 0, 0, 255]);
+        assert_eq!(texels[2], [127, 0, 127, 255]);
+        assert_eq!(texels[3], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bc2_block() {
+        let mut block = [0; 16];
+        block[0] = 0x0F;
+        block[1] = 0x08;
+        block[8..16].copy_from_slice(&BC1_FOUR_COLOR);
+        let texels = decode_bc2_block(&block);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 0]);
+        assert_eq!(texels[2], [170, 0, 85, 136]);
+        assert_eq!(texels[3], [85, 0, 170, 0]);
+    }
+
+    #[test]
+    fn bc3_block() {
+        let mut block = [0; 16];
+        block[0..8].copy_from_slice(&[255, 0, 0x88, 0, 0, 0, 0, 0]);
+        block[8..16].copy_from_slice(&BC1_FOUR_COLOR);
+        let texels = decode_bc3_block(&block);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 0]);
+        assert_eq!(texels[2], [170, 0, 85, 218]);
+        assert_eq!(texels[3], [85, 0, 170, 255]);
+    }
+
+    #[test]
+    fn bc4_blocks() {
+        let texels = decode_bc4_block(&[0, 255, 0xBE, 0, 0, 0, 0, 0]);
+        assert_eq!(texels[0], [0, 0, 0, 255]);
+        assert_eq!(texels[1], [255, 0, 0, 255]);
+        assert_eq!(texels[2], [51, 0, 0, 255]);
+
+        // -128 is read as -127, the signed result is stored as i8 bytes.
+        let texels = decode_bc4_snorm_block(&[0x80, 0x7F, 0xBE, 0, 0, 0, 0, 0]);
+        assert_eq!(texels[0], [-127i8 as u8, 0, 0, 127]);
+        assert_eq!(texels[1], [127, 0, 0, 127]);
+        assert_eq!(texels[2], [-76i8 as u8, 0, 0, 127]);
+        assert_eq!(texels[3], [-127i8 as u8, 0, 0, 127]);
+    }
+
+    #[test]
+    fn bc5_blocks() {
+        let mut block = [0; 16];
+        block[0..8].copy_from_slice(&[0, 255, 0xBE, 0, 0, 0, 0, 0]);
+        block[8..16].copy_from_slice(&[255, 0, 0, 0, 0, 0, 0, 0]);
+        let texels = decode_bc5_block(&block);
+        assert_eq!(texels[0], [0, 255, 0, 255]);
+        assert_eq!(texels[1], [255, 255, 0, 255]);
+        assert_eq!(texels[2], [51, 255, 0, 255]);
+
+        block[0..8].copy_from_slice(&[0x80, 0x7F, 0xBE, 0, 0, 0, 0, 0]);
+        block[8..16].copy_from_slice(&[0x7F, 0x81, 0, 0, 0, 0, 0, 0]);
+        let texels = decode_bc5_snorm_block(&block);
+        assert_eq!(texels[0], [-127i8 as u8, 127, 0, 127]);
+        assert_eq!(texels[1], [127, 127, 0, 127]);
+    }
+
+    #[test]
+    fn bc7_mode_6_block() {
+        let block = pack_bits(&[
+            (0, 6), (1, 1),
+            (0, 7), (127, 7), (127, 7), (0, 7), (0, 7), (0, 7), (127, 7), (127, 7),
+            (0, 1), (1, 1),
+            (0, 3), (15, 4), (8, 4),
+        ]);
+        let texels = decode_bc7_block(&block);
+        assert_eq!(texels[0], [0, 254, 0, 254]);
+        assert_eq!(texels[1], [255, 1, 1, 255]);
+        assert_eq!(texels[2], [135, 120, 1, 255]);
+        assert_eq!(texels[15], [0, 254, 0, 254]);
+    }
+
+    #[test]
+    fn bc7_reserved_mode_is_transparent_black() {
+        assert_eq!(decode_bc7_block(&[0; 16]), [[0; 4]; 16]);
+    }
+
+    #[test]
+    fn etc2_rgb8_individual_block() {
+        let texels = decode_etc2_rgb8_block(&ETC2_INDIVIDUAL);
+        assert_eq!(texels[0], [255, 2, 2, 255]);
+        assert_eq!(texels[1], [247, 0, 0, 255]);
+        assert_eq!(texels[2], [2, 2, 2, 255]);
+        assert_eq!(texels[12], [255, 2, 2, 255]);
+    }
+
+    #[test]
+    fn etc2_rgb8a1_punchthrough_block() {
+        let mut block = [0xF8, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+        let texels = decode_etc2_rgb8a1_block(&block);
+        assert_eq!(texels[0], [0, 0, 0, 0]);
+        assert_eq!(texels[1], [255, 0, 0, 255]);
+
+        // The opaque bit turns it back into a plain differential block.
+        block[3] = 0x02;
+        let texels = decode_etc2_rgb8a1_block(&block);
+        assert_eq!(texels[0], [253, 0, 0, 255]);
+        assert_eq!(texels[1], [255, 2, 2, 255]);
+    }
+
+    #[test]
+    fn etc2_rgba8_block() {
+        let mut block = [0; 16];
+        block[0..8].copy_from_slice(&EAC_BLOCK);
+        block[8..16].copy_from_slice(&ETC2_INDIVIDUAL);
+        let texels = decode_etc2_rgba8_block(&block);
+        assert_eq!(texels[0], [255, 2, 2, 125]);
+        assert_eq!(texels[1], [247, 0, 0, 130]);
+    }
+
+    #[test]
+    fn eac_r11_blocks() {
+        let texels = decode_eac_r11_block(&EAC_BLOCK);
+        assert_eq!(texels[0], [125, 0, 0, 255]);
+        assert_eq!(texels[1], [130, 0, 0, 255]);
+
+        // A zero multiplier adds the modifier without scaling it.
+        let texels = decode_eac_r11_block(&[0x80, 0x00, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(texels[0], [127, 0, 0, 255]);
+
+        let texels = decode_eac_r11_snorm_block(&[0x40, 0x10, 0x00, 0x08, 0, 0, 0, 0]);
+        assert_eq!(texels[0], [60, 0, 0, 127]);
+        assert_eq!(texels[1], [65, 0, 0, 127]);
+        let texels = decode_eac_r11_snorm_block(&[0x80, 0x10, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(texels[0], [-127i8 as u8, 0, 0, 127]);
+    }
+
+    #[test]
+    fn eac_rg11_blocks() {
+        let mut block = [0; 16];
+        block[0..8].copy_from_slice(&EAC_BLOCK);
+        block[8..16].copy_from_slice(&[0xFF, 0x10, 0, 0, 0, 0, 0, 0]);
+        let texels = decode_eac_rg11_block(&block);
+        assert_eq!(texels[0], [125, 251, 0, 255]);
+
+        block[0..8].copy_from_slice(&[0x40, 0x10, 0x00, 0x08, 0, 0, 0, 0]);
+        let texels = decode_eac_rg11_snorm_block(&block);
+        assert_eq!(texels[1], [65, -3i8 as u8, 0, 127]);
+    }
+
+    fn image(format: TextureFormat, width: u32, height: u32, data: Vec<u8>) -> CompressedImage {
+        CompressedImage {
+            format,
+            width,
+            height,
+            depth_or_array_layers: 1,
+            mip_level_count: 1,
+            dimension: TextureDimension::D2,
+            is_cube: false,
+            order: TextureDataOrder::MipMajor,
+            data,
+        }
+    }
+
+    #[test]
+    fn decompress_crops_partial_blocks() {
+        let decompressed = image(TextureFormat::Bc1RgbaUnormSrgb, 2, 2, BC1_FOUR_COLOR.to_vec()).decompress().unwrap();
+        assert_eq!(decompressed.format, TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(decompressed.data, [255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, 255, 0, 0, 255]);
+
+        let decompressed = image(TextureFormat::EacR11Snorm, 4, 4, EAC_BLOCK.to_vec()).decompress().unwrap();
+        assert_eq!(decompressed.format, TextureFormat::Rgba8Snorm);
+        assert_eq!(decompressed.data.len(), 64);
+    }
+
+    #[test]
+    fn decompress_rejects_truncated_and_unsupported_data() {
+        assert!(image(TextureFormat::Bc1RgbaUnorm, 8, 4, BC1_FOUR_COLOR.to_vec()).decompress().is_err());
+        let astc = TextureFormat::Astc { block: AstcBlock::B4x4, channel: AstcChannel::Hdr };
+        assert!(image(astc, 4, 4, vec![0; 16]).decompress().is_err());
+    }
+
+    #[test]
+    fn decompress_astc_footprints_and_bc6h() {
+        let astc = TextureFormat::Astc { block: AstcBlock::B6x6, channel: AstcChannel::UnormSrgb };
+        let decompressed = image(astc, 8, 7, astc_void_extent(false).repeat(4)).decompress().unwrap();
+        assert_eq!(decompressed.format, TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(decompressed.data, [255, 128, 0, 255].repeat(56));
+
+        let decompressed = image(TextureFormat::Bc6hRgbUfloat, 2, 2, vec![0; 16]).decompress().unwrap();
+        assert_eq!(decompressed.format, TextureFormat::Rgba16Float);
+        assert_eq!(decompressed.data.len(), 32);
+    }
+
+    // Checks every mode against the bits it should take and the precision of its fields.
+    #[test]
+    fn bc6h_mode_layouts_fill_the_header() {
+        for (mode, info) in BC6H_MODES.iter().enumerate() {
+            let mut field_bits = [0u32; 13];
+            for &(field, first, last) in info.fields {
+                field_bits[field as usize] += first.abs_diff(last) as u32 + 1;
+            }
+            let (mode_bits, regions, header_bits) = if mode < 2 { (2, 2, 82) } else if mode < 10 { (5, 2, 82) } else { (5, 1, 65) };
+            assert_eq!(field_bits.iter().sum::<u32>() + mode_bits, header_bits, "mode {}", mode + 1);
+            assert_eq!(field_bits[D as usize], if regions == 2 { 5 } else { 0 }, "mode {}", mode + 1);
+
+            for channel in 0..3 {
+                assert_eq!(field_bits[channel], info.endpoint_bits, "mode {}", mode + 1);
+                for endpoint in 1..regions * 2 {
+                    let expected = if info.transformed { info.delta_bits[channel] } else { info.endpoint_bits };
+                    assert_eq!(field_bits[endpoint * 3 + channel], expected, "mode {}", mode + 1);
+                }
+            }
+        }
+    }
+
+    fn bc6h_red(texel: [u8; 8]) -> u16 {
+        u16::from_le_bytes([texel[0], texel[1]])
+    }
+
+    #[test]
+    fn bc6h_one_region_block() {
+        // Mode 11: untransformed 10 bit endpoints, black to white.
+        let mut fields = vec![(0x03, 5), (0, 10), (0, 10), (0, 10), (1023, 10), (1023, 10), (1023, 10), (0, 3), (15, 4)];
+        fields.extend([(0, 4); 14]);
+        let texels = decode_bc6h_block(&pack_bits(&fields), false);
+        let one = half::f16::ONE.to_bits().to_le_bytes();
+        assert_eq!(texels[0], [0, 0, 0, 0, 0, 0, one[0], one[1]]);
+        // The largest unsigned value is the largest finite half.
+        let max = half::f16::MAX.to_bits().to_le_bytes();
+        assert_eq!(texels[1], [max[0], max[1], max[0], max[1], max[0], max[1], one[0], one[1]]);
+    }
+
+    #[test]
+    fn bc6h_two_region_block_applies_deltas_and_signs() {
+        // Mode 1 with every delta zero, all texels take the base endpoint.
+        let block = pack_bits(&[(0, 2), (0, 3), (512, 10), (512, 10), (512, 10)]);
+        assert!(decode_bc6h_block(&block, false).iter().all(|&texel| bc6h_red(texel) == 15887));
+        // Read as a signed block the same bits are the most negative endpoint.
+        assert!(decode_bc6h_block(&block, true).iter().all(|&texel| bc6h_red(texel) == half::f16::MIN.to_bits()));
+
+        // Region 1 of partition 0 (texel 15 is in it, texel 0 isn't) gets red deltas of 5.
+        let block = pack_bits(&[(0, 2), (0, 3), (256, 10), (256, 10), (256, 10), (0, 30), (5, 5), (0, 1), (5, 5), (0, 1), (0, 5)]);
+        let texels = decode_bc6h_block(&block, false);
+        assert_eq!(bc6h_red(texels[0]), 7951);
+        assert_eq!(bc6h_red(texels[15]), 8106);
+    }
+
+    #[test]
+    fn bc6h_reserved_mode_is_black() {
+        assert_eq!(decode_bc6h_block(&pack_bits(&[(0x13, 5)]), false), [[0; 8]; 16]);
+    }
+
+    #[test]
+    fn astc_void_extent_blocks() {
+        assert_eq!(decode_astc_block(&astc_void_extent(false), 6, 6, false), vec![[255, 128, 0, 255]; 36]);
+        assert_eq!(decode_astc_block(&astc_void_extent(true), 4, 4, false), vec![ASTC_ERROR_COLOR; 16]);
+    }
+
+    #[test]
+    fn astc_reserved_block_modes_are_errors() {
+        assert_eq!(decode_astc_block(&[0; 16], 4, 4, false), vec![ASTC_ERROR_COLOR; 16]);
+        // A 12x12 grid doesn't fit a 4x4 footprint.
+        assert_eq!(decode_astc_block(&pack_bits(&[(0x0C, 11)]), 4, 4, false), vec![ASTC_ERROR_COLOR; 16]);
+    }
+
+    #[test]
+    fn astc_unquantization_tables() {
+        assert_eq!((0..2).map(|value| astc_unquantize_weight(value, 0)).collect::<Vec<_>>(), [0, 64]);
+        assert_eq!((0..3).map(|value| astc_unquantize_weight(value, 1)).collect::<Vec<_>>(), [0, 32, 64]);
+        assert_eq!((0..4).map(|value| astc_unquantize_weight(value, 2)).collect::<Vec<_>>(), [0, 21, 43, 64]);
+        assert_eq!((0..6).map(|value| astc_unquantize_weight(value, 4)).collect::<Vec<_>>(), [0, 64, 12, 52, 25, 39]);
+        assert_eq!((0..6).map(|value| astc_unquantize_color(value, 4)).collect::<Vec<_>>(), [0, 255, 51, 204, 102, 153]);
+        assert_eq!((0..8).map(|value| astc_unquantize_color(value, 5)).collect::<Vec<_>>(), [0, 36, 73, 109, 146, 182, 219, 255]);
+    }
+
+    #[test]
+    fn astc_trits_and_quints_cover_every_combination() {
+        let trits: std::collections::HashSet<_> = (0..256).map(astc_trits).collect();
+        assert_eq!(trits.len(), 243);
+        assert!(trits.iter().flatten().all(|&trit| trit < 3));
+        let quints: std::collections::HashSet<_> = (0..128).map(astc_quints).collect();
+        assert_eq!(quints.len(), 125);
+        assert!(quints.iter().flatten().all(|&quint| quint < 5));
+    }
+
+    #[test]
+    fn astc_rgb_endpoints_and_weights() {
+        let block = astc_rgb_block([0, 255, 0, 128, 255, 0], [0, 1, 2, 3, 3, 2, 1, 0, 0, 0, 0, 0, 3, 3, 3, 3]);
+        let texels = decode_astc_block(&block, 4, 4, false);
+        assert_eq!(texels[0], [0, 0, 255, 255]);
+        assert_eq!(texels[1], [84, 42, 171, 255]);
+        assert_eq!(texels[2], [171, 86, 84, 255]);
+        assert_eq!(texels[3], [255, 128, 0, 255]);
+        assert_eq!(texels[4], texels[3]);
+        assert_eq!(texels[12], texels[3]);
+
+        // Stretched over a 6x6 footprint the corners keep the weights of the grid corners.
+        let texels = decode_astc_block(&block, 6, 6, false);
+        assert_eq!(texels[0], [0, 0, 255, 255]);
+        assert_eq!(texels[5], [255, 128, 0, 255]);
+        assert_eq!(texels[30], [255, 128, 0, 255]);
+    }
+
+    #[test]
+    fn astc_blue_contraction_swaps_the_endpoints() {
+        // The second endpoint is darker, so the decoder swaps them and pulls red and green toward blue.
+        let texels = decode_astc_block(&astc_rgb_block([200, 0, 100, 0, 50, 0], [0; 16]), 4, 4, false);
+        assert_eq!(texels[0], [0, 0, 0, 255]);
+        let texels = decode_astc_block(&astc_rgb_block([200, 0, 100, 0, 50, 0], [3; 16]), 4, 4, false);
+        assert_eq!(texels[0], [125, 75, 50, 255]);
+    }
+}
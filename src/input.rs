@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+use winit::{event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent}, keyboard::{Key, KeyCode, ModifiersState, PhysicalKey}};
+use crate::math::Coordinate2D;
+
+#[derive(Debug, Clone)]
+pub struct TouchPoint {
+    pub id: u64,
+    pub position: Coordinate2D,
+    pub start_position: Coordinate2D,
+    // Movement since the previous frame.
+    pub delta: Coordinate2D,
+}
+
+// Keyboard, mouse and touch state the engine keeps up to date from window events.
+// The just_pressed/just_released queries and the deltas cover everything that happened
+// since the previous Program::update.
+#[derive(Default)]
+pub struct Input {
+    // Held and just pressed keys and buttons are kept in press order, so capturing a
+    // binding gets the same result every time.
+    keys: Vec<KeyCode>,
+    keys_just_pressed: Vec<KeyCode>,
+    keys_just_released: HashSet<KeyCode>,
+    // Logical keys are remembered by the physical key that made them, so releasing shift
+    // before 'a' still releases "A".
+    logical_keys: HashMap<PhysicalKey, Key>,
+    logical_just_pressed: HashSet<Key>,
+    logical_just_released: HashSet<Key>,
+    modifiers: ModifiersState,
+    text: String,
+
+    mouse_position: Option<Coordinate2D>,
+    mouse_delta: (f64, f64),
+    raw_mouse_delta: (f64, f64),
+    mouse_buttons: HashSet<MouseButton>,
+    mouse_just_pressed: Vec<MouseButton>,
+    mouse_just_released: HashSet<MouseButton>,
+    wheel_delta: (f64, f64),
+    mouse_inside: bool,
+
+    touches: HashMap<u64, TouchPoint>,
+    // Kept as they were at the start, so a tap that ends in the same frame still shows up.
+    touches_just_started: Vec<TouchPoint>,
+    // Touches that ended this frame stay readable until the frame is over.
+    touches_just_ended: Vec<TouchPoint>,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    self.handle_key(code, pressed);
+                }
+
+                if pressed && let Some(text) = &event.text {
+                    self.text.push_str(text);
+                }
+                self.handle_logical_key(event.physical_key, &event.logical_key, pressed, event.repeat);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(previous) = &self.mouse_position {
+                    self.mouse_delta.0 += position.x - previous.x;
+                    self.mouse_delta.1 += position.y - previous.y;
+                }
+                self.mouse_position = Some((*position).into());
+            }
+            WindowEvent::CursorEntered { .. } => self.mouse_inside = true,
+            WindowEvent::CursorLeft { .. } => self.mouse_inside = false,
+            WindowEvent::MouseInput { state, button, .. } => {
+                if state.is_pressed() && self.mouse_buttons.insert(*button) && !self.mouse_just_pressed.contains(button) {
+                    self.mouse_just_pressed.push(*button);
+                } else if !state.is_pressed() && self.mouse_buttons.remove(button) {
+                    self.mouse_just_released.insert(*button);
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x as f64, *y as f64),
+                    // Roughly one line per 20 pixels.
+                    MouseScrollDelta::PixelDelta(position) => (position.x / 20.0, position.y / 20.0),
+                };
+                self.wheel_delta.0 += x;
+                self.wheel_delta.1 += y;
+            }
+            WindowEvent::Touch(touch) => {
+                let position: Coordinate2D = touch.location.into();
+                match touch.phase {
+                    TouchPhase::Started => {
+                        let point = TouchPoint {
+                            id: touch.id,
+                            start_position: position.clone(),
+                            position,
+                            delta: Coordinate2D { x: 0.0, y: 0.0 },
+                        };
+                        self.touches_just_started.push(point.clone());
+                        self.touches.insert(touch.id, point);
+                    }
+                    TouchPhase::Moved => {
+                        if let Some(point) = self.touches.get_mut(&touch.id) {
+                            point.delta.x += position.x - point.position.x;
+                            point.delta.y += position.y - point.position.y;
+                            point.position = position;
+                        }
+                    }
+                    TouchPhase::Ended | TouchPhase::Cancelled => {
+                        if let Some(mut point) = self.touches.remove(&touch.id) {
+                            point.position = position;
+                            self.touches_just_ended.push(point);
+                        }
+                    }
+                }
+            }
+            // Releases never arrive while another window has focus, so let go of everything.
+            WindowEvent::Focused(false) => self.release_all(),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn handle_key(&mut self, code: KeyCode, pressed: bool) {
+        if pressed && !self.keys.contains(&code) {
+            self.keys.push(code);
+            if !self.keys_just_pressed.contains(&code) {
+                self.keys_just_pressed.push(code);
+            }
+        } else if !pressed && self.keys.contains(&code) {
+            self.keys.retain(|key| *key != code);
+            self.keys_just_released.insert(code);
+        }
+    }
+
+    pub(crate) fn handle_logical_key(&mut self, physical_key: PhysicalKey, logical_key: &Key, pressed: bool, repeat: bool) {
+        if pressed {
+            if !repeat {
+                self.logical_keys.insert(physical_key, logical_key.clone());
+                self.logical_just_pressed.insert(logical_key.clone());
+            }
+        } else if let Some(key) = self.logical_keys.remove(&physical_key) {
+            self.logical_just_released.insert(key);
+        }
+    }
+
+    // Unaccelerated mouse movement, which keeps working when the cursor is grabbed.
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.raw_mouse_delta.0 += delta.0;
+            self.raw_mouse_delta.1 += delta.1;
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.keys_just_released.extend(self.keys.drain(..));
+        self.logical_just_released.extend(self.logical_keys.drain().map(|(_, key)| key));
+        self.mouse_just_released.extend(self.mouse_buttons.drain());
+        self.touches_just_ended.extend(self.touches.drain().map(|(_, point)| point));
+        self.modifiers = ModifiersState::empty();
+    }
+
+    // Called by the engine after every Program::update.
+    pub(crate) fn end_frame(&mut self) {
+        self.keys_just_pressed.clear();
+        self.keys_just_released.clear();
+        self.logical_just_pressed.clear();
+        self.logical_just_released.clear();
+        self.text.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.raw_mouse_delta = (0.0, 0.0);
+        self.mouse_just_pressed.clear();
+        self.mouse_just_released.clear();
+        self.wheel_delta = (0.0, 0.0);
+        self.touches_just_started.clear();
+        self.touches_just_ended.clear();
+        for point in self.touches.values_mut() {
+            point.delta = Coordinate2D { x: 0.0, y: 0.0 };
+        }
+    }
+
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn is_key_just_pressed(&self, key: KeyCode) -> bool {
+        self.keys_just_pressed.contains(&key)
+    }
+
+    pub fn is_key_just_released(&self, key: KeyCode) -> bool {
+        self.keys_just_released.contains(&key)
+    }
+
+    // Oldest first.
+    pub fn get_pressed_keys(&self) -> impl Iterator<Item = &KeyCode> {
+        self.keys.iter()
+    }
+
+    // In the order they went down this frame.
+    pub fn get_just_pressed_keys(&self) -> impl Iterator<Item = &KeyCode> {
+        self.keys_just_pressed.iter()
+    }
+
+    // Logical keys follow the keyboard layout, e.g. Key::Character("z".into()) on both
+    // QWERTY and AZERTY. Character keys include modifiers, shift+a is "A".
+    pub fn is_logical_pressed(&self, key: &Key) -> bool {
+        self.logical_keys.values().any(|pressed| pressed == key)
+    }
+
+    pub fn is_logical_just_pressed(&self, key: &Key) -> bool {
+        self.logical_just_pressed.contains(key)
+    }
+
+    pub fn is_logical_just_released(&self, key: &Key) -> bool {
+        self.logical_just_released.contains(key)
+    }
+
+    pub fn get_modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    // Text typed this frame, including key repeats.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    // None until the cursor moved over the window once.
+    pub fn get_mouse_position(&self) -> Option<Coordinate2D> {
+        self.mouse_position.clone()
+    }
+
+    pub fn get_mouse_delta(&self) -> Coordinate2D {
+        Coordinate2D { x: self.mouse_delta.0, y: self.mouse_delta.1 }
+    }
+
+    pub fn get_raw_mouse_delta(&self) -> Coordinate2D {
+        Coordinate2D { x: self.raw_mouse_delta.0, y: self.raw_mouse_delta.1 }
+    }
+
+    pub fn is_mouse_inside(&self) -> bool {
+        self.mouse_inside
+    }
+
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.contains(&button)
+    }
+
+    pub fn is_mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_just_pressed.contains(&button)
+    }
+
+    pub fn is_mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse_just_released.contains(&button)
+    }
+
+    pub fn get_just_pressed_mouse_buttons(&self) -> impl Iterator<Item = &MouseButton> {
+        self.mouse_just_pressed.iter()
+    }
+
+    // In lines, positive y scrolls up.
+    pub fn get_wheel_delta(&self) -> Coordinate2D {
+        Coordinate2D { x: self.wheel_delta.0, y: self.wheel_delta.1 }
+    }
+
+    pub fn get_touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches.values()
+    }
+
+    pub fn get_touch(&self, id: u64) -> Option<&TouchPoint> {
+        self.touches.get(&id)
+    }
+
+    pub fn get_touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    // As they were when they went down.
+    pub fn get_touches_just_started(&self) -> &[TouchPoint] {
+        &self.touches_just_started
+    }
+
+    pub fn get_touches_just_ended(&self) -> &[TouchPoint] {
+        &self.touches_just_ended
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::{dpi::PhysicalPosition, event::{DeviceId, Touch}};
+
+    fn touch(id: u64, phase: TouchPhase, x: f64, y: f64) -> WindowEvent {
+        WindowEvent::Touch(Touch { device_id: DeviceId::dummy(), phase, location: PhysicalPosition::new(x, y), force: None, id })
+    }
+
+    fn mouse(button: MouseButton, state: ElementState) -> WindowEvent {
+        WindowEvent::MouseInput { device_id: DeviceId::dummy(), state, button }
+    }
+
+    #[test]
+    fn just_pressed_and_released_last_one_frame() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::KeyA, true);
+        assert!(input.is_key_pressed(KeyCode::KeyA) && input.is_key_just_pressed(KeyCode::KeyA));
+        input.end_frame();
+        assert!(input.is_key_pressed(KeyCode::KeyA) && !input.is_key_just_pressed(KeyCode::KeyA));
+
+        input.handle_key(KeyCode::KeyA, false);
+        assert!(!input.is_key_pressed(KeyCode::KeyA) && input.is_key_just_released(KeyCode::KeyA));
+        input.end_frame();
+        assert!(!input.is_key_just_released(KeyCode::KeyA));
+
+        input.handle_event(&mouse(MouseButton::Left, ElementState::Pressed));
+        assert!(input.is_mouse_just_pressed(MouseButton::Left));
+        input.end_frame();
+        assert!(input.is_mouse_pressed(MouseButton::Left) && !input.is_mouse_just_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn press_and_release_in_one_frame() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::Space, true);
+        input.handle_key(KeyCode::Space, false);
+        assert!(input.is_key_just_pressed(KeyCode::Space));
+        assert!(input.is_key_just_released(KeyCode::Space));
+        assert!(!input.is_key_pressed(KeyCode::Space));
+
+        input.handle_event(&mouse(MouseButton::Right, ElementState::Pressed));
+        input.handle_event(&mouse(MouseButton::Right, ElementState::Released));
+        assert!(input.is_mouse_just_pressed(MouseButton::Right) && input.is_mouse_just_released(MouseButton::Right));
+    }
+
+    #[test]
+    fn repeats_do_not_trigger_again() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::KeyW, true);
+        input.end_frame();
+        input.handle_key(KeyCode::KeyW, true);
+        assert!(input.is_key_pressed(KeyCode::KeyW));
+        assert!(!input.is_key_just_pressed(KeyCode::KeyW));
+
+        let key = Key::Character("w".into());
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::KeyW), &key, true, false);
+        input.end_frame();
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::KeyW), &key, true, true);
+        assert!(input.is_logical_pressed(&key) && !input.is_logical_just_pressed(&key));
+    }
+
+    #[test]
+    fn logical_key_released_after_shift() {
+        let mut input = Input::new();
+        let upper = Key::Character("A".into());
+        let shift = Key::Named(winit::keyboard::NamedKey::Shift);
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::ShiftLeft), &shift, true, false);
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::KeyA), &upper, true, false);
+        input.end_frame();
+
+        // Shift goes up first, the 'a' key still releases the "A" it pressed.
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::ShiftLeft), &shift, false, false);
+        assert!(input.is_logical_pressed(&upper));
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::KeyA), &Key::Character("a".into()), false, false);
+        assert!(!input.is_logical_pressed(&upper));
+        assert!(input.is_logical_just_released(&upper));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = Input::new();
+        input.handle_key(KeyCode::KeyD, true);
+        input.handle_logical_key(PhysicalKey::Code(KeyCode::KeyD), &Key::Character("d".into()), true, false);
+        input.handle_event(&mouse(MouseButton::Left, ElementState::Pressed));
+        input.handle_event(&touch(7, TouchPhase::Started, 10.0, 10.0));
+        input.end_frame();
+
+        input.handle_event(&WindowEvent::Focused(false));
+        assert_eq!(input.get_pressed_keys().count(), 0);
+        assert!(input.is_key_just_released(KeyCode::KeyD));
+        assert!(input.is_logical_just_released(&Key::Character("d".into())));
+        assert!(!input.is_mouse_pressed(MouseButton::Left) && input.is_mouse_just_released(MouseButton::Left));
+        assert_eq!(input.get_touch_count(), 0);
+        assert_eq!(input.get_touches_just_ended().len(), 1);
+    }
+
+    #[test]
+    fn touch_deltas() {
+        let mut input = Input::new();
+        input.handle_event(&touch(1, TouchPhase::Started, 10.0, 20.0));
+        input.handle_event(&touch(1, TouchPhase::Moved, 15.0, 18.0));
+        input.handle_event(&touch(1, TouchPhase::Moved, 25.0, 18.0));
+        let point = input.get_touch(1).unwrap();
+        assert_eq!((point.delta.x, point.delta.y), (15.0, -2.0));
+        assert_eq!((point.start_position.x, point.start_position.y), (10.0, 20.0));
+        assert_eq!(input.get_touches_just_started().len(), 1);
+
+        input.end_frame();
+        let point = input.get_touch(1).unwrap();
+        assert_eq!((point.delta.x, point.delta.y), (0.0, 0.0));
+        assert!(input.get_touches_just_started().is_empty());
+
+        input.handle_event(&touch(1, TouchPhase::Ended, 30.0, 30.0));
+        assert_eq!(input.get_touch_count(), 0);
+        let ended = &input.get_touches_just_ended()[0];
+        assert_eq!((ended.position.x, ended.position.y), (30.0, 30.0));
+    }
+
+    #[test]
+    fn taps_within_one_frame_are_kept() {
+        let mut input = Input::new();
+        input.handle_event(&touch(3, TouchPhase::Started, 5.0, 6.0));
+        input.handle_event(&touch(3, TouchPhase::Ended, 5.0, 6.0));
+        assert_eq!(input.get_touch_count(), 0);
+        assert_eq!(input.get_touches_just_started().len(), 1);
+        assert_eq!(input.get_touches_just_started()[0].id, 3);
+        assert_eq!(input.get_touches_just_ended().len(), 1);
+    }
+}
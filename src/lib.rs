@@ -1,15 +1,27 @@
-use std::{ops::Range, sync::Arc};
+use std::{collections::HashMap, ops::Range, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}};
 use anyhow::{Ok, Error};
 use wgpu::{BindGroup, BindGroupDescriptor};
-use winit::{application::ApplicationHandler, dpi::PhysicalSize, event_loop::{EventLoop, ActiveEventLoop}, window::Window, event::{WindowEvent}};
+use winit::{application::ApplicationHandler, event_loop::{EventLoop, ActiveEventLoop, ControlFlow}, window::Window, event::{DeviceEvent, DeviceId, WindowEvent}};
 
 pub mod shader;
 pub mod math;
 pub mod buffer;
 pub mod surface;
-pub mod bindgroup {
-    pub use wgpu::{BindGroupLayoutEntry, ShaderStages, BindingType, BufferBindingType, BindGroupEntry, BindingResource, BindGroupLayoutDescriptor, BindGroupLayout, BindGroupDescriptor, BindGroup, BufferBinding};
-}
+pub mod compressed;
+pub mod sampler;
+pub mod bindgroup;
+pub mod atlas;
+pub mod sprite;
+pub mod camera;
+pub mod transform;
+pub mod color;
+pub mod tween;
+pub mod time;
+pub mod input;
+pub mod action;
+pub mod window;
+pub mod resolution;
+mod util;
 
 pub use winit;
 pub use wgpu;
@@ -19,14 +31,16 @@ use wasm_bigen::prelude::*;
 #[cfg(target_arch = "wasm32")]
 use winit::{event_loop, platform::web::EventLoopExtWebSys};
 
-use crate::surface::Surface;
-
 pub struct CatEngineInit<P: Program> {
     pub app: App<P>, 
 }
 
 impl<P: Program + 'static> CatEngineInit<P> {
     pub fn start(width: u32, height: u32) {
+        Self::start_with_config(window::WindowConfig::new().with_size(width, height));
+    }
+
+    pub fn start_with_config(config: window::WindowConfig) {
         #[cfg(not(target_arch = "wasm32"))]
         {
             env_logger::init();
@@ -37,43 +51,89 @@ impl<P: Program + 'static> CatEngineInit<P> {
         }
 
         let event_loop = EventLoop::with_user_event().build();
-         
+
+        #[cfg(not(target_arch = "wasm32"))]
         {
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                let mut app = App::<P>::new(width, height);
-                let _ = event_loop.expect("event loop").run_app(&mut app);
-                app
-            }
-            #[cfg(target_arch = "wasm32")]
-            {
-                let app = App::new(&event_loop, program, width, height);
-                event_loop.spawn_app(app);
-                app
-            }
-        };
+            let mut app = App::<P>::with_config(config);
+            let _ = event_loop.expect("event loop").run_app(&mut app);
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let app = App::with_config(&event_loop, config);
+            event_loop.spawn_app(app);
+        }
     }
 }
 
 pub enum CatEngineDrawCommand {
     Shader(Arc<shader::Shader>, Arc<buffer::Buffer>, Arc<buffer::Buffer>, u32, math::Range<u64>, Range<u32>, Range<u32>, Vec<(Arc<BindGroup>, u32, Vec<u32>)>),
+    // Commands after this one draw into the given render target, None goes back to the window.
+    // The target is cleared with the color if there is one, otherwise its contents are kept.
+    SetRenderTarget(Option<RenderTarget>, Option<color::Color>),
+    // Same as SetRenderTarget with a depth target from CatEngine::create_depth_target next
+    // to it, cleared to the value if there is one. Shaders drawn here need new_with_depth.
+    SetRenderTargetWithDepth(Option<RenderTarget>, Option<color::Color>, RenderTarget, Option<f32>),
+    // Only the depth target is drawn into, for shadow maps and depth prepasses.
+    SetDepthOnlyTarget(RenderTarget, Option<f32>),
+}
+
+// How often the App redraws. Every redraw runs Program::update. Programs that animate
+// opt in to Continuous or CappedFps with CatEngine::set_run_mode.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RunMode {
+    // Redraws as soon as the previous frame is done, paced by the present mode.
+    Continuous,
+    // Only redraws after input or CatEngine::request_redraw, so idle windows don't burn
+    // power.
+    #[default]
+    OnDemand,
+    // Redraws at most this many times per second and sleeps in between.
+    CappedFps(u32),
+}
+
+// Handle to a render target owned by the engine, see CatEngine::create_render_target.
+// Handles of destroyed targets stop working even if the slot gets reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTarget {
+    index: usize,
+    generation: u32,
 }
 
 pub struct CatEngine {
-    surface: wgpu::Surface<'static>,
+    instance: wgpu::Instance,
+    // None while suspended, Android destroys the native window behind it.
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     pub is_surface_configured: bool,
     window: Arc<Window>,
     pub command_list: Vec<CatEngineDrawCommand>,
+    // Size programs draw at, the internal resolution when there is one and the window
+    // surface size otherwise.
     pub width: u32,
     pub height: u32,
+    scale_factor: f64,
+    internal_resolution: Option<resolution::InternalResolution>,
+    samplers: Mutex<HashMap<sampler::SamplerPreset, Arc<sampler::Sampler>>>,
+    bind_group_layouts: Mutex<HashMap<Vec<wgpu::BindGroupLayoutEntry>, Arc<wgpu::BindGroupLayout>>>,
+    render_targets: Vec<Option<Arc<surface::Surface>>>,
+    render_target_generations: Vec<u32>,
+    free_render_targets: Vec<usize>,
+    time: time::Time,
+    input: input::Input,
+    run_mode: RunMode,
+    present_modes: Vec<wgpu::PresentMode>,
+    // Set from wgpu's device lost callback, the engine recovers before the next frame.
+    device_lost: Arc<AtomicBool>,
+    suspended: bool,
+    exit_requested: bool,
 }
 
 impl CatEngine {
-    async fn new(window: Arc<Window>) -> Result<Self, Error> {
+    async fn new(window: Arc<Window>, transparent: bool) -> Result<Self, Error> {
         let size = window.inner_size();
+        let scale_factor = window.scale_factor();
 
         // The instance is a handle to our GPU
         // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
@@ -88,33 +148,8 @@ impl CatEngine {
             display: None,
         });
 
-        let surface = instance.create_surface(window.clone()).unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-                apply_limit_buckets: true,
-            })
-            .await.unwrap();
-        
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                experimental_features: wgpu::ExperimentalFeatures::disabled(),
-                // WebGL doesn't support all of wgpu's features, so if
-                // we're building for the web we'll have to disable some.
-                required_limits: if cfg!(target_arch = "wasm32") {
-                    wgpu::Limits::downlevel_webgl2_defaults()
-                } else {
-                    wgpu::Limits::default()
-                },
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-            })
-            .await.unwrap();
+        let surface = instance.create_surface(window.clone())?;
+        let (adapter, device, queue, device_lost) = Self::request_device(&instance, &surface).await?;
 
         let surface_caps = surface.get_capabilities(&adapter);
         // Shader code in this tutorial assumes an sRGB surface texture. Using a different
@@ -129,8 +164,12 @@ impl CatEngine {
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            present_mode: wgpu::PresentMode::AutoVsync,
+            // Transparent windows need a surface that keeps the alpha channel.
+            alpha_mode: surface_caps.alpha_modes.iter()
+                .find(|mode| transparent && matches!(mode, wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied))
+                .copied()
+                .unwrap_or(surface_caps.alpha_modes[0]),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
             color_space: wgpu::SurfaceColorSpace::Auto,
@@ -138,7 +177,8 @@ impl CatEngine {
 
 
         Ok(Self {
-            surface,
+            instance,
+            surface: Some(surface),
             device,
             queue,
             command_list: vec![],
@@ -147,16 +187,222 @@ impl CatEngine {
             window,
             width: size.width,
             height: size.height,
+            scale_factor,
+            internal_resolution: None,
+            samplers: Mutex::new(HashMap::new()),
+            bind_group_layouts: Mutex::new(HashMap::new()),
+            render_targets: vec![],
+            render_target_generations: vec![],
+            free_render_targets: vec![],
+            time: time::Time::new(),
+            input: input::Input::new(),
+            run_mode: RunMode::default(),
+            present_modes: surface_caps.present_modes,
+            device_lost,
+            suspended: false,
+            exit_requested: false,
         })
 
     }
 
+    async fn request_device(instance: &wgpu::Instance, surface: &wgpu::Surface<'static>) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue, Arc<AtomicBool>), Error> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(surface),
+                force_fallback_adapter: false,
+                apply_limit_buckets: true,
+            })
+            .await?;
+        
+        // Compressed texture formats, 16 bit normalized formats, filtering of 32 bit float
+        // textures and border clamping get enabled
+        // whenever the adapter has them. Surfaces fall back to decompressing on the CPU (all but
+        // HDR ASTC) and to half floats otherwise.
+        let optional_features = adapter.features() & (
+            wgpu::Features::TEXTURE_COMPRESSION_BC
+            | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+            | wgpu::Features::TEXTURE_COMPRESSION_ASTC
+            | wgpu::Features::TEXTURE_FORMAT_16BIT_NORM
+            | wgpu::Features::FLOAT32_FILTERABLE
+            | wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER
+            | wgpu::Features::ADDRESS_MODE_CLAMP_TO_ZERO
+        );
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features: optional_features,
+                experimental_features: wgpu::ExperimentalFeatures::disabled(),
+                // WebGL doesn't support all of wgpu's features, so if
+                // we're building for the web we'll have to disable some.
+                required_limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::default()
+                },
+                memory_hints: Default::default(),
+                trace: wgpu::Trace::Off,
+            })
+            .await?;
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let flag = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Destroyed is the engine dropping an old device on purpose.
+            if reason == wgpu::DeviceLostReason::Unknown {
+                log::error!("the GPU device was lost: {}", message);
+                flag.store(true, Ordering::Release);
+            }
+        });
+
+        Ok((adapter, device, queue, device_lost))
+    }
+
+    // Makes a new device after the old one was lost, e.g. to a driver reset or the GPU
+    // being unplugged. Render targets, the internal resolution and the sampler and layout
+    // caches are rebuilt here. Everything the program made itself belongs to the old
+    // device and has to be made again, see Program::device_recreated.
+    fn recreate_device(&mut self) -> Result<(), Error> {
+        let surface = self.instance.create_surface(self.window.clone())?;
+        let (adapter, device, queue, device_lost) = pollster::block_on(Self::request_device(&self.instance, &surface))?;
+        let surface_caps = surface.get_capabilities(&adapter);
+        // Shaders are made for the old format, so it is only swapped when it has to be.
+        if !surface_caps.formats.contains(&self.config.format) {
+            self.config.format = surface_caps.formats.iter().find(|f| f.is_srgb()).copied().unwrap_or(surface_caps.formats[0]);
+        }
+        if !surface_caps.alpha_modes.contains(&self.config.alpha_mode) {
+            self.config.alpha_mode = surface_caps.alpha_modes[0];
+        }
+        self.surface = Some(surface);
+        self.device = device;
+        self.queue = queue;
+        self.device_lost = device_lost;
+        self.present_modes = surface_caps.present_modes;
+        self.config.present_mode = self.supported_present_mode(self.config.present_mode);
+        self.configure_surface();
+
+        self.samplers.lock().unwrap().clear();
+        self.bind_group_layouts.lock().unwrap().clear();
+        self.command_list.clear();
+
+        let mut render_targets = std::mem::take(&mut self.render_targets);
+        for render_target in render_targets.iter_mut().flatten() {
+            *render_target = Arc::new(render_target.recreated(self));
+        }
+        self.render_targets = render_targets;
+
+        if let Some(internal_resolution) = self.internal_resolution.take() {
+            let (width, height) = internal_resolution.get_size();
+            self.internal_resolution = Some(resolution::InternalResolution::new(self, width, height, internal_resolution.get_scaling_mode()));
+        }
+        Ok(())
+    }
+
+    fn recreate_surface(&mut self) -> Result<(), Error> {
+        self.surface = Some(self.instance.create_surface(self.window.clone())?);
+        self.configure_surface();
+        Ok(())
+    }
+
+    fn configure_surface(&self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    // Android takes the native window away while the app is in the background, so the
+    // surface has to go with it and is made again when the app comes back.
+    fn suspend(&mut self) {
+        self.surface = None;
+        self.suspended = true;
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        self.recreate_surface()?;
+        self.suspended = false;
+        Ok(())
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    // Closes the application once the current event is handled, after Program::exiting.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
     pub fn request_redraw(&mut self) {
         self.window.request_redraw();
     }
 
+    pub fn get_window(&self) -> &Arc<Window> {
+        &self.window
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: window::FullscreenMode) {
+        self.window.set_fullscreen(fullscreen.to_winit(self.window.current_monitor()));
+    }
+
+    pub fn get_fullscreen(&self) -> window::FullscreenMode {
+        match self.window.fullscreen() {
+            None => window::FullscreenMode::Windowed,
+            Some(winit::window::Fullscreen::Borderless(_)) => window::FullscreenMode::Borderless,
+            Some(winit::window::Fullscreen::Exclusive(_)) => window::FullscreenMode::Exclusive,
+        }
+    }
+
+    // Switches between windowed and borderless, the usual alt+enter behaviour.
+    pub fn toggle_fullscreen(&mut self) {
+        let fullscreen = match self.get_fullscreen() {
+            window::FullscreenMode::Windowed => window::FullscreenMode::Borderless,
+            _ => window::FullscreenMode::Windowed,
+        };
+        self.set_fullscreen(fullscreen);
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.window.set_cursor_visible(visible);
+    }
+
+    // Keeps the cursor in the window, for mouse look use Input::get_raw_mouse_delta while
+    // it is grabbed. Locking the cursor in place is tried first and confining it to the
+    // window second, since platforms only support one of them.
+    pub fn set_cursor_grab(&mut self, grab: bool) -> Result<(), Error> {
+        use winit::window::CursorGrabMode;
+        if !grab {
+            self.window.set_cursor_grab(CursorGrabMode::None)?;
+        } else if self.window.set_cursor_grab(CursorGrabMode::Locked).is_err() {
+            self.window.set_cursor_grab(CursorGrabMode::Confined)?;
+        }
+        Ok(())
+    }
+
+    // Clears the window with raw values, use update_with_color for sRGB aware clearing.
     pub fn update(&mut self, r: f64, g: f64, b: f64) -> Result<(), Error> {
-        let output = match self.surface.get_current_texture() {
+        self.draw_frame(wgpu::Color { r, g, b, a: 1.0 })
+    }
+
+    pub fn update_with_color(&mut self, color: color::Color) -> Result<(), Error> {
+        self.draw_frame(color.to_wgpu(self.config.format))
+    }
+
+    fn draw_frame(&mut self, clear_color: wgpu::Color) -> Result<(), Error> {
+        let Some(surface) = self.surface.as_ref().filter(|_| !self.suspended) else {
+            self.command_list.clear();
+            return Ok(());
+        };
+
+        let output = match surface.get_current_texture() {
                 wgpu::CurrentSurfaceTexture::Success(surface_texture) => surface_texture,
                 wgpu::CurrentSurfaceTexture::Suboptimal(surface_texture) => {
                     surface_texture
@@ -168,52 +414,116 @@ impl CatEngine {
                     return Ok(());
                 }
                 wgpu::CurrentSurfaceTexture::Outdated => {
-                    self.surface.configure(&self.device, &self.config);
+                    self.configure_surface();
                     return Ok(());
                 }
                 wgpu::CurrentSurfaceTexture::Lost => {
-                    // You could recreate the devices and all resources
-                    // created with it here, but we'll just bail
-                    anyhow::bail!("Lost device");
+                    // The device is fine, only the surface has to be made again.
+                    self.recreate_surface()?;
+                    return Ok(());
                 }
             };
 
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let surface_view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // With an internal resolution the window draws go offscreen and get scaled up at the end.
+        let view = match &self.internal_resolution {
+            Some(internal_resolution) => internal_resolution.get_target().get_view(),
+            None => &surface_view,
+        };
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
-        {
+
+        // Every SetRenderTarget command starts a new render pass, the first one always
+        // clears the window. Passes into a destroyed target are skipped with their draws.
+        let stale_target = |render_target: &RenderTarget| {
+            let surface = self.get_render_target(*render_target);
+            if surface.is_none() {
+                log::warn!("skipping the draws into a destroyed render target");
+            }
+            surface
+        };
+        let color_target = |render_target: &Option<RenderTarget>, clear: &Option<color::Color>| {
+            let (target_view, format) = match render_target {
+                Some(render_target) => {
+                    let surface = stale_target(render_target)?;
+                    (surface.get_view(), surface.get_format())
+                }
+                None => (view, self.config.format),
+            };
+            let load = match clear {
+                Some(color) => wgpu::LoadOp::Clear(color.to_wgpu(format)),
+                None => wgpu::LoadOp::Load,
+            };
+            Some((target_view, load))
+        };
+        let depth_target = |render_target: &RenderTarget, clear: &Option<f32>| {
+            let surface = stale_target(render_target)?;
+            let load = match clear {
+                Some(depth) => wgpu::LoadOp::Clear(*depth),
+                None => wgpu::LoadOp::Load,
+            };
+            Some((surface.get_attachment_view(), load, surface.get_format().has_stencil_aspect()))
+        };
+
+        let mut passes = vec![];
+        let mut target = Some((Some((view, wgpu::LoadOp::Clear(clear_color))), None));
+        let mut start = 0;
+        for (i, command) in self.command_list.iter().enumerate() {
+            let next_target = match command {
+                CatEngineDrawCommand::SetRenderTarget(render_target, clear) => color_target(render_target, clear).map(|color| (Some(color), None)),
+                CatEngineDrawCommand::SetRenderTargetWithDepth(render_target, clear, depth, clear_depth) => {
+                    color_target(render_target, clear).zip(depth_target(depth, clear_depth)).map(|(color, depth)| (Some(color), Some(depth)))
+                }
+                CatEngineDrawCommand::SetDepthOnlyTarget(depth, clear_depth) => depth_target(depth, clear_depth).map(|depth| (None, Some(depth))),
+                CatEngineDrawCommand::Shader(..) => continue,
+            };
+            passes.push((target, &self.command_list[start..i]));
+            target = next_target;
+            start = i + 1;
+        }
+        passes.push((target, &self.command_list[start..]));
+
+        for (target, commands) in passes {
+            let Some((color, depth)) = target else {
+                continue;
+            };
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[
                     // This is what @location(0) in the fragment shader targets
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
+                    color.map(|(target_view, load)| wgpu::RenderPassColorAttachment {
+                        view: target_view,
                         resolve_target: None,
                         depth_slice: None,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(
-                                wgpu::Color {
-                                    r,
-                                    g,
-                                    b, //Person in sitcom: breathes
-                                    a: 1.0,
-                                }
-                            ),
+                            load,
                             store: wgpu::StoreOp::Store,
                         }
                     })
                 ],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: depth.map(|(depth_view, load, has_stencil)| wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    // The stencil is cleared together with the depth.
+                    stencil_ops: has_stencil.then_some(wgpu::Operations {
+                        load: match load {
+                            wgpu::LoadOp::Load => wgpu::LoadOp::Load,
+                            _ => wgpu::LoadOp::Clear(0),
+                        },
+                        store: wgpu::StoreOp::Store,
+                    }),
+                }),
                 occlusion_query_set: None,
                 timestamp_writes: None,
                 multiview_mask: None,
             });
 
-
-
-            for command in &mut self.command_list {
+            for command in commands {
                 match command {
                     CatEngineDrawCommand::Shader(shader, vertex_buffer, index_buffer, slot_num, bounds, vertices, indices, bind_groups) => {
                         render_pass.set_pipeline(shader.get_pipeline());
@@ -234,26 +544,13 @@ impl CatEngine {
                 
                         render_pass.draw_indexed(vertices.to_owned(), 0, indices.to_owned());
                     }
-                //     CatEngineDrawCommand::TextureShader(shader, vertex_buffer, index_buffer, slot_num, bounds, vertices, indices, surface, index, offsets) => {
-                //         render_pass.set_pipeline(shader.get_pipeline());
-                //         render_pass.set_bind_group(*index, surface.get_bind_group(), offsets);
-                //
-                //         match bounds {
-                //             math::Range::Range(r) => {
-                //                 render_pass.set_vertex_buffer(slot_num.to_owned(), vertex_buffer.get_buffer().slice(r.to_owned()));
-                //                 render_pass.set_index_buffer(index_buffer.get_buffer().slice(r.to_owned()), wgpu::IndexFormat::Uint16);
-                //             },
-                //             math::Range::Full => {
-                //                 render_pass.set_vertex_buffer(slot_num.to_owned(), vertex_buffer.get_buffer().slice(..));
-                //                 render_pass.set_index_buffer(index_buffer.get_buffer().slice(..), wgpu::IndexFormat::Uint16);
-                //             },
-                //         };
-                //
-                //         render_pass.draw_indexed(vertices.to_owned(), 0, indices.to_owned());
-                //     }
+                    CatEngineDrawCommand::SetRenderTarget(..) | CatEngineDrawCommand::SetRenderTargetWithDepth(..) | CatEngineDrawCommand::SetDepthOnlyTarget(..) => {}
                 }
             }
         }
+        if let Some(internal_resolution) = &self.internal_resolution {
+            internal_resolution.blit(&mut encoder, &surface_view, self.get_window_size());
+        }
         self.queue.submit(std::iter::once(encoder.finish()));
         self.queue.present(output);
 
@@ -264,48 +561,250 @@ impl CatEngine {
 
     pub fn resize(&mut self, width: u32, height: u32) {
         if width > 0 && height > 0 {
-            let max = 2048;
+            // The surface is a texture, so it can't outgrow what the device supports.
+            let max = self.device.limits().max_texture_dimension_2d;
             self.config.width = width.min(max);
             self.config.height = height.min(max);
-            self.surface.configure(&self.device, &self.config);
+            self.configure_surface();
             self.is_surface_configured = true;
-            self.width = width;
-            self.height = height;
+            self.update_size();
         }
     }
+
+    fn update_size(&mut self) {
+        (self.width, self.height) = match &self.internal_resolution {
+            Some(internal_resolution) => internal_resolution.get_size(),
+            None => self.get_window_size(),
+        };
+
+        // Anything still holding the old surface keeps drawing from the old texture.
+        let mut render_targets = std::mem::take(&mut self.render_targets);
+        for render_target in render_targets.iter_mut().flatten() {
+            if let Some(resized) = render_target.resized(self) {
+                *render_target = Arc::new(resized);
+            }
+        }
+        self.render_targets = render_targets;
+    }
+
+    // Size of the window surface in physical pixels.
+    pub fn get_window_size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    // Size of the window surface in logical pixels, which stay the same size on screens
+    // with a higher pixel density.
+    pub fn get_logical_size(&self) -> (f64, f64) {
+        (self.config.width as f64 / self.scale_factor, self.config.height as f64 / self.scale_factor)
+    }
+
+    pub fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    // Draws at a fixed size no matter how big the window is, the result gets scaled onto
+    // the window with the scaling mode. None draws at the window size again. Window sized
+    // render targets follow the internal resolution. Sizes past the device's texture limit
+    // are clamped to it.
+    pub fn set_internal_resolution(&mut self, resolution: Option<(u32, u32)>, scaling_mode: resolution::ScalingMode) {
+        let max = self.device.limits().max_texture_dimension_2d;
+        self.internal_resolution = resolution.map(|(width, height)| {
+            if width > max || height > max {
+                log::warn!("internal resolution {}x{} is clamped to the texture limit of {}", width, height, max);
+            }
+            resolution::InternalResolution::new(self, width.min(max), height.min(max), scaling_mode)
+        });
+        self.update_size();
+    }
+
+    pub fn get_internal_resolution(&self) -> Option<(u32, u32)> {
+        self.internal_resolution.as_ref().map(|internal_resolution| internal_resolution.get_size())
+    }
+
+    // Where the image ends up in the window.
+    pub fn get_viewport(&self) -> resolution::Viewport {
+        match &self.internal_resolution {
+            Some(internal_resolution) => internal_resolution.get_viewport(self.get_window_size()),
+            None => resolution::ScalingMode::Stretch.get_viewport(self.get_window_size(), self.get_window_size()),
+        }
+    }
+
+    // Turns a cursor position into the pixel it points at in the size programs draw at.
+    pub fn window_to_render(&self, position: math::Coordinate2D) -> Option<math::Coordinate2D> {
+        self.get_viewport().window_to_resolution(position, (self.width, self.height))
+    }
+
+    // The engine keeps the target so window sized ones follow resizes. Bind groups and
+    // sprites using it have to get it again after a WindowEvent::Resized.
+    pub fn create_render_target(&mut self, args: surface::SurfaceAttributes) -> RenderTarget {
+        let surface = surface::Surface::new_render_target(self, args);
+        self.add_render_target(surface)
+    }
+
+    // Same as create_render_target for depth textures, see SetRenderTargetWithDepth. Depth
+    // targets drawn together with the window should use the Config(1) size.
+    pub fn create_depth_target(&mut self, args: surface::SurfaceAttributes) -> RenderTarget {
+        let surface = surface::Surface::new_depth(self, args);
+        self.add_render_target(surface)
+    }
+
+    fn add_render_target(&mut self, surface: surface::Surface) -> RenderTarget {
+        match self.free_render_targets.pop() {
+            Some(index) => {
+                self.render_targets[index] = Some(Arc::new(surface));
+                RenderTarget { index, generation: self.render_target_generations[index] }
+            }
+            None => {
+                self.render_targets.push(Some(Arc::new(surface)));
+                self.render_target_generations.push(0);
+                RenderTarget { index: self.render_targets.len() - 1, generation: 0 }
+            }
+        }
+    }
+
+    // The texture lives on as long as something still holds the surface.
+    pub fn destroy_render_target(&mut self, render_target: RenderTarget) {
+        if self.contains_render_target(render_target) {
+            self.render_targets[render_target.index] = None;
+            self.render_target_generations[render_target.index] += 1;
+            self.free_render_targets.push(render_target.index);
+        }
+    }
+
+    pub fn contains_render_target(&self, render_target: RenderTarget) -> bool {
+        self.render_target_generations.get(render_target.index) == Some(&render_target.generation)
+            && self.render_targets[render_target.index].is_some()
+    }
+
+    // Cloning the Arc is how a target gets sampled, e.g. by a Sprite. None once the target was destroyed.
+    pub fn get_render_target(&self, render_target: RenderTarget) -> Option<&Arc<surface::Surface>> {
+        if !self.contains_render_target(render_target) {
+            return None;
+        }
+        self.render_targets[render_target.index].as_ref()
+    }
     
     pub fn create_bind_group_layout(&self, desc: wgpu::BindGroupLayoutDescriptor) -> Arc<wgpu::BindGroupLayout> {
         Arc::new(self.device.create_bind_group_layout(&desc))
     }
 
+    // Layouts with the same entries are only created once.
+    pub fn get_bind_group_layout(&self, entries: &[wgpu::BindGroupLayoutEntry]) -> Arc<wgpu::BindGroupLayout> {
+        let mut layouts = self.bind_group_layouts.lock().unwrap();
+        layouts.entry(entries.to_vec()).or_insert_with(|| {
+            self.create_bind_group_layout(wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries,
+            })
+        }).clone()
+    }
+
     pub fn create_bind_group(&self, desc: BindGroupDescriptor) -> Arc<BindGroup> {
         Arc::new(self.device.create_bind_group(&desc))
     }
 
+    // Presets are created once and shared by every caller afterwards.
+    pub fn get_sampler(&self, preset: sampler::SamplerPreset) -> Arc<sampler::Sampler> {
+        let mut samplers = self.samplers.lock().unwrap();
+        samplers.entry(preset).or_insert_with(|| Arc::new(sampler::Sampler::from_preset(self, preset))).clone()
+    }
+
     pub fn write_buffer(&mut self, buffer: &buffer::Buffer, index: u64, contents: &[u8]) {
         self.queue.write_buffer(buffer.get_buffer(), index, contents);
     }
+
+    pub fn get_time(&self) -> &time::Time {
+        &self.time
+    }
+
+    pub fn get_time_mut(&mut self) -> &mut time::Time {
+        &mut self.time
+    }
+
+    pub fn get_input(&self) -> &input::Input {
+        &self.input
+    }
+
+    pub fn set_run_mode(&mut self, run_mode: RunMode) { self.run_mode = run_mode; }
+
+    pub fn get_run_mode(&self) -> RunMode {
+        self.run_mode
+    }
+
+    // Modes the surface doesn't support fall back to Fifo, which every surface has.
+    // The Auto modes pick a supported mode themselves.
+    fn supported_present_mode(&self, present_mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let auto = matches!(present_mode, wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync);
+        if auto || self.present_modes.contains(&present_mode) {
+            present_mode
+        } else {
+            wgpu::PresentMode::Fifo
+        }
+    }
+
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        self.config.present_mode = self.supported_present_mode(present_mode);
+        if self.is_surface_configured {
+            self.configure_surface();
+        }
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.set_present_mode(if vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync });
+    }
+
+    pub fn get_present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    pub fn get_supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        &self.present_modes
+    }
 }
 
 pub trait Program {
     fn new(catengine: &mut CatEngine) -> Self where Self: Sized;
     fn update(&mut self, catengine: &mut CatEngine);
     fn handle_event(&mut self, catengine: &mut CatEngine, event: WindowEvent);
+    // Runs at the fixed timestep of catengine.get_time(), zero or more times before each update.
+    fn fixed_update(&mut self, _catengine: &mut CatEngine) {}
+    // The engine had to make a new GPU device. Shaders, buffers, surfaces, bind groups and
+    // anything else made before now are dead and have to be made again. Shader, Surface
+    // and SpriteBatcher can do that in place with recreate.
+    fn device_recreated(&mut self, _catengine: &mut CatEngine) {}
+    // The application went to the background, e.g. home was pressed on Android. Nothing is
+    // drawn until resumed, so this is the place to pause and autosave.
+    fn suspended(&mut self, _catengine: &mut CatEngine) {}
+    // Back from the background, only called after suspended.
+    fn resumed(&mut self, _catengine: &mut CatEngine) {}
+    fn focus_changed(&mut self, _catengine: &mut CatEngine, _focused: bool) {}
+    // The user tried to close the window, returning false keeps it open, e.g. to ask
+    // about unsaved changes first.
+    fn close_requested(&mut self, _catengine: &mut CatEngine) -> bool {
+        true
+    }
+    // Last call before the application shuts down, the engine is still usable.
+    fn exiting(&mut self, _catengine: &mut CatEngine) {}
 }
 
 // this will store the state of the game
 pub struct State<P: Program> {
     program: P,
     pub catengine: CatEngine,
+    // When the next frame is due in RunMode::CappedFps.
+    next_frame: std::time::Instant,
 }
 
 impl<P: Program> State<P> {
-    pub async fn new(window: Arc<Window>, width: u32, height: u32) -> anyhow::Result<Self> {        
-        let mut catengine = CatEngine::new(window).await.unwrap();
-        catengine.resize(width, height);
+    pub async fn new(window: Arc<Window>, config: &window::WindowConfig) -> anyhow::Result<Self> {
+        let size = window.inner_size();
+        let mut catengine = CatEngine::new(window, config.is_transparent()).await.unwrap();
+        // The window can open at another size than asked for, e.g. maximized or fullscreen.
+        catengine.resize(size.width, size.height);
         Ok(Self {
             program: P::new(&mut catengine),
             catengine,
+            next_frame: std::time::Instant::now(),
         })
     }
 
@@ -314,7 +813,71 @@ impl<P: Program> State<P> {
     }
  
     fn render(&mut self) {
+        // Without a surface to check against, recovery waits until the app is resumed.
+        if self.catengine.is_device_lost() && !self.catengine.suspended {
+            if let Err(error) = self.catengine.recreate_device() {
+                // Try again next frame, the GPU may still be coming back.
+                log::error!("recreating the GPU device failed: {}", error);
+                return;
+            }
+            self.program.device_recreated(&mut self.catengine);
+        }
+
+        let fixed_steps = self.catengine.time.begin_frame();
+        for _ in 0..fixed_steps {
+            self.program.fixed_update(&mut self.catengine);
+            self.catengine.time.end_fixed_step();
+        }
         self.program.update(&mut self.catengine);
+        self.catengine.input.end_frame();
+    }
+
+    fn suspend(&mut self) {
+        self.catengine.suspend();
+        self.program.suspended(&mut self.catengine);
+    }
+
+    fn resume(&mut self) {
+        if let Err(error) = self.catengine.resume() {
+            log::error!("recreating the window surface failed: {}", error);
+            return;
+        }
+        self.program.resumed(&mut self.catengine);
+    }
+
+    // Decides when the event loop wakes up next, called once it ran out of events.
+    fn schedule_frame(&mut self, event_loop: &ActiveEventLoop) {
+        if self.catengine.exit_requested {
+            event_loop.exit();
+            return;
+        }
+        if self.catengine.suspended {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        // The device is recovered on the next frame, which OnDemand and CappedFps programs
+        // could otherwise wait a long time for.
+        if self.catengine.is_device_lost() {
+            self.catengine.request_redraw();
+        }
+
+        match self.catengine.run_mode {
+            RunMode::Continuous => {
+                event_loop.set_control_flow(ControlFlow::Wait);
+                self.catengine.request_redraw();
+            }
+            RunMode::OnDemand => event_loop.set_control_flow(ControlFlow::Wait),
+            RunMode::CappedFps(fps) => {
+                let now = std::time::Instant::now();
+                if now >= self.next_frame {
+                    let interval = std::time::Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+                    // Stay on the frame grid unless a frame ran so late that catching up would burst.
+                    self.next_frame = (self.next_frame + interval).max(now);
+                    self.catengine.request_redraw();
+                }
+                event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_frame));
+            }
+        }
     }
 }
 
@@ -322,20 +885,22 @@ pub struct App<P: Program> {
     #[cfg(target_arch = "wasm32")]
     proxy: Option<winit::event_loop::EventLoopProxy<State>>,
     pub state: Option<State<P>>,
-    width: u32,
-    height: u32,
+    config: window::WindowConfig,
 }
 
 impl<P: Program> App<P> {
     pub fn new(#[cfg(target_arch = "wasm32")] event_loop: &EventLoop<State>, width: u32, height: u32) -> Self {
+        Self::with_config(#[cfg(target_arch = "wasm32")] event_loop, window::WindowConfig::new().with_size(width, height))
+    }
+
+    pub fn with_config(#[cfg(target_arch = "wasm32")] event_loop: &EventLoop<State>, config: window::WindowConfig) -> Self {
         #[cfg(target_arch = "wasm32")]
         let proxy = Some(event_loop.create_proxy());
         Self {
             state: None,
             #[cfg(target_arch = "wasm32")]
             proxy,
-            width,
-            height,
+            config,
         }
     }
 
@@ -351,8 +916,17 @@ impl<P: Program> App<P> {
 
 impl<P: Program + 'static> ApplicationHandler<State<P>> for App<P> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // The window survives a suspension, only the surface has to be made again. Platforms
+        // also send resumed without a suspension first, e.g. at startup, those are ignored.
+        if let Some(state) = &mut self.state {
+            if state.catengine.suspended {
+                state.resume();
+            }
+            return;
+        }
+
         #[allow(unused_mut)]
-        let mut window_attributes = Window::default_attributes().with_inner_size(PhysicalSize::new(self.width, self.height));
+        let mut window_attributes = self.config.to_attributes(event_loop.primary_monitor());
 
         #[cfg(target_arch = "wasm32")]
         {
@@ -374,7 +948,7 @@ impl<P: Program + 'static> ApplicationHandler<State<P>> for App<P> {
         {
             // If we are not on web we can use pollster to
             // await the window creation
-            self.state = Some(pollster::block_on(State::new(window, self.width, self.height)).unwrap());
+            self.state = Some(pollster::block_on(State::new(window, &self.config)).unwrap());
         }
 
         #[cfg(target_arch = "wasm32")]
@@ -420,18 +994,68 @@ impl<P: Program + 'static> ApplicationHandler<State<P>> for App<P> {
             None => return,
         };
 
+        // Resize first so the program already sees the new render targets.
+        match event {
+            WindowEvent::Resized(size) => state.catengine.resize(size.width, size.height),
+            // Moving to a screen with another pixel density changes the physical size.
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                state.catengine.scale_factor = scale_factor;
+                let size = state.catengine.window.inner_size();
+                state.catengine.resize(size.width, size.height);
+            }
+            _ => {}
+        }
+
+        // Reactive programs redraw whenever something might have changed.
+        let is_input = matches!(event,
+            WindowEvent::KeyboardInput { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::Touch(_)
+            | WindowEvent::Resized(_)
+            | WindowEvent::Focused(_)
+        );
+        if is_input && state.catengine.run_mode == RunMode::OnDemand {
+            state.catengine.request_redraw();
+        }
+
+        state.catengine.input.handle_event(&event);
         state.handle_event(event.clone());
 
         match event {
-            WindowEvent::CloseRequested => event_loop.exit(),
-            WindowEvent::Resized(size) => state.catengine.resize(size.width, size.height),
+            WindowEvent::CloseRequested if state.program.close_requested(&mut state.catengine) => event_loop.exit(),
+            WindowEvent::Focused(focused) => state.program.focus_changed(&mut state.catengine, focused),
             WindowEvent::RedrawRequested => {
                 state.render();
             }
             _ => {}
         }
     }
-    
+
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: DeviceId, event: DeviceEvent) {
+        if let Some(state) = &mut self.state {
+            state.catengine.input.handle_device_event(&event);
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            state.suspend();
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            state.program.exiting(&mut state.catengine);
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            state.schedule_frame(event_loop);
+        }
+    }
 }
 
 //pub fn run() -> anyhow::Result<()> {
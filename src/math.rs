@@ -1,9 +1,26 @@
+mod vector;
+mod matrix;
+mod quaternion;
+mod geometry;
+
+pub use vector::{Vec2, Vec3, Vec4};
+pub use matrix::{Mat3, Mat4};
+pub use quaternion::Quat;
+pub use geometry::{Aabb, Circle, Frustum, Obb, Plane, Ray, Rect, Sphere};
+
 #[derive(Debug, Clone)]
 pub struct Coordinate2D {
     pub x: f64,
     pub y: f64,
 }
 
+// Cursor positions from winit events.
+impl From<winit::dpi::PhysicalPosition<f64>> for Coordinate2D {
+    fn from(position: winit::dpi::PhysicalPosition<f64>) -> Self {
+        Self { x: position.x, y: position.y }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Coordinate3D {
     pub x: f64,
@@ -11,6 +28,30 @@ pub struct Coordinate3D {
     pub z: f64,
 }
 
+impl From<Coordinate2D> for Vec2 {
+    fn from(coordinate: Coordinate2D) -> Self {
+        Vec2::new(coordinate.x as f32, coordinate.y as f32)
+    }
+}
+
+impl From<Vec2> for Coordinate2D {
+    fn from(vector: Vec2) -> Self {
+        Self { x: vector.x as f64, y: vector.y as f64 }
+    }
+}
+
+impl From<Coordinate3D> for Vec3 {
+    fn from(coordinate: Coordinate3D) -> Self {
+        Vec3::new(coordinate.x as f32, coordinate.y as f32, coordinate.z as f32)
+    }
+}
+
+impl From<Vec3> for Coordinate3D {
+    fn from(vector: Vec3) -> Self {
+        Self { x: vector.x as f64, y: vector.y as f64, z: vector.z as f64 }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Range<T> {
     Range(std::ops::Range<T>),
@@ -0,0 +1,632 @@
+use super::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+// Axis aligned 2D rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Rect {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min: min.min(max), max: min.max(max) }
+    }
+
+    pub fn from_center_size(center: Vec2, size: Vec2) -> Self {
+        Self::new(center - size / 2.0, center + size / 2.0)
+    }
+
+    pub fn get_size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn get_center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    // Touching edges count as overlapping.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x && self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        self.intersects(other).then(|| Rect { min: self.min.max(other.min), max: self.max.min(other.max) })
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn expand(&self, amount: f32) -> Rect {
+        Rect::new(self.min - Vec2::splat(amount), self.max + Vec2::splat(amount))
+    }
+
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        point.max(self.min).min(self.max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        (point - self.center).length_squared() <= self.radius * self.radius
+    }
+
+    pub fn intersects_circle(&self, other: &Circle) -> bool {
+        let radius = self.radius + other.radius;
+        (other.center - self.center).length_squared() <= radius * radius
+    }
+
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        self.contains_point(rect.closest_point(self.center))
+    }
+
+    // Points inside the circle are returned as they are.
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let offset = point - self.center;
+        if offset.length_squared() <= self.radius * self.radius {
+            point
+        } else {
+            self.center + offset.normalize() * self.radius
+        }
+    }
+
+    pub fn get_bounds(&self) -> Rect {
+        Rect::from_center_size(self.center, Vec2::splat(self.radius * 2.0))
+    }
+}
+
+// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min: min.min(max), max: min.max(max) }
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    // None for an empty slice.
+    pub fn from_points(points: &[Vec3]) -> Option<Self> {
+        let first = *points.first()?;
+        Some(points.iter().fold(Self { min: first, max: first }, |aabb, point| Self { min: aabb.min.min(*point), max: aabb.max.max(*point) }))
+    }
+
+    pub fn get_center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn get_half_extents(&self) -> Vec3 {
+        (self.max - self.min) / 2.0
+    }
+
+    pub fn get_corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z
+    }
+
+    pub fn contains_aabb(&self, other: &Aabb) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    pub fn intersects_aabb(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x
+            && self.min.y <= other.max.y && self.max.y >= other.min.y
+            && self.min.z <= other.max.z && self.max.z >= other.min.z
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        sphere.contains_point(self.closest_point(sphere.center))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        point.max(self.min).min(self.max)
+    }
+
+    // Bounds of the box after the transform, which can be larger than the box itself.
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let corners = self.get_corners().map(|corner| matrix.transform_point3(corner));
+        Self::from_points(&corners).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        (point - self.center).length_squared() <= self.radius * self.radius
+    }
+
+    pub fn intersects_sphere(&self, other: &Sphere) -> bool {
+        let radius = self.radius + other.radius;
+        (other.center - self.center).length_squared() <= radius * radius
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        aabb.intersects_sphere(self)
+    }
+
+    // Points inside the sphere are returned as they are.
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        let offset = point - self.center;
+        if offset.length_squared() <= self.radius * self.radius {
+            point
+        } else {
+            self.center + offset.normalize() * self.radius
+        }
+    }
+
+    pub fn get_bounds(&self) -> Aabb {
+        Aabb::from_center_half_extents(self.center, Vec3::splat(self.radius))
+    }
+}
+
+// Points p with normal.dot(p) + distance == 0. The normal points to the positive side.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+impl Plane {
+    pub fn from_point_normal(point: Vec3, normal: Vec3) -> Self {
+        let normal = normal.normalize();
+        Self { normal, distance: -normal.dot(point) }
+    }
+
+    // Counter clockwise points give a normal facing the viewer.
+    pub fn from_points(a: Vec3, b: Vec3, c: Vec3) -> Self {
+        Self::from_point_normal(a, (b - a).cross(c - a))
+    }
+
+    pub fn normalize(&self) -> Self {
+        let length = self.normal.length();
+        Self { normal: self.normal / length, distance: self.distance / length }
+    }
+
+    // Positive in front of the plane, negative behind it.
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        point - self.normal * self.signed_distance(point)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    // The direction gets normalized so hit distances are in world units.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction: direction.normalize() }
+    }
+
+    // Picking ray through a screen position in render pixels from the top left, with e.g.
+    // Camera3D::get_view_projection. Cursor positions go through CatEngine::window_to_render
+    // first, or use Camera3D::window_ray.
+    pub fn from_screen(view_projection: &Mat4, screen: Vec2, screen_size: Vec2) -> Self {
+        let ndc = Vec2::new(screen.x / screen_size.x * 2.0 - 1.0, 1.0 - screen.y / screen_size.y * 2.0);
+        let inverse = view_projection.inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        Self::new(near, far - near)
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        self.at((point - self.origin).dot(self.direction).max(0.0))
+    }
+
+    // Distance to the first hit, 0 when the origin is inside the box.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = 0.0_f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            let inverse = 1.0 / self.direction[axis];
+            let mut t0 = (aabb.min[axis] - self.origin[axis]) * inverse;
+            let mut t1 = (aabb.max[axis] - self.origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN from a zero direction inside the slab is skipped by max/min.
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let offset = self.origin - sphere.center;
+        let b = offset.dot(self.direction);
+        let c = offset.length_squared() - sphere.radius * sphere.radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        if -b + root < 0.0 {
+            return None;
+        }
+        Some((-b - root).max(0.0))
+    }
+
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denominator = plane.normal.dot(self.direction);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        let distance = -plane.signed_distance(self.origin) / denominator;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    // Möller-Trumbore, hits both sides of the triangle.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse_determinant;
+        (distance >= 0.0).then_some(distance)
+    }
+
+    pub fn intersect_obb(&self, obb: &Obb) -> Option<f32> {
+        let inverse = obb.rotation.inverse();
+        let local = Ray { origin: inverse * (self.origin - obb.center), direction: inverse * self.direction };
+        local.intersect_aabb(&Aabb::from_center_half_extents(Vec3::ZERO, obb.half_extents))
+    }
+}
+
+// Camera view volume for culling, the planes face inwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    // left, right, bottom, top, near, far
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    // Expects wgpu's 0 to 1 depth range, like Camera3D::get_view_projection.
+    pub fn from_view_projection(view_projection: &Mat4) -> Self {
+        let rows = [0, 1, 2, 3].map(|index| view_projection.row(index));
+        let plane = |row: Vec4| Plane { normal: row.truncate(), distance: row.w }.normalize();
+        Self {
+            planes: [
+                plane(rows[3] + rows[0]),
+                plane(rows[3] - rows[0]),
+                plane(rows[3] + rows[1]),
+                plane(rows[3] - rows[1]),
+                plane(rows[2]),
+                plane(rows[3] - rows[2]),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    // Conservative, spheres near the corners can pass without touching the volume.
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    // Conservative in the same way as intersects_sphere.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner furthest along the plane normal.
+            let positive = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            plane.signed_distance(positive) >= 0.0
+        })
+    }
+}
+
+// Oriented bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Obb {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    pub rotation: Quat,
+}
+
+impl Obb {
+    pub fn new(center: Vec3, half_extents: Vec3, rotation: Quat) -> Self {
+        Self { center, half_extents, rotation }
+    }
+
+    pub fn get_axes(&self) -> [Vec3; 3] {
+        [self.rotation * Vec3::X, self.rotation * Vec3::Y, self.rotation * Vec3::Z]
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        let local = self.rotation.inverse() * (point - self.center);
+        (0..3).all(|axis| local[axis].abs() <= self.half_extents[axis])
+    }
+
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        let local = self.rotation.inverse() * (point - self.center);
+        self.center + self.rotation * local.max(-self.half_extents).min(self.half_extents)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        sphere.contains_point(self.closest_point(sphere.center))
+    }
+
+    // Separating axis test over the 15 candidate axes.
+    pub fn intersects_obb(&self, other: &Obb) -> bool {
+        let a_axes = self.get_axes();
+        let b_axes = other.get_axes();
+        let offset = other.center - self.center;
+
+        let mut axes = a_axes.to_vec();
+        axes.extend(b_axes);
+        for a in a_axes {
+            for b in b_axes {
+                let axis = a.cross(b);
+                // Parallel edges give no new axis.
+                if axis.length_squared() > 1e-6 {
+                    axes.push(axis.normalize());
+                }
+            }
+        }
+
+        axes.into_iter().all(|axis| {
+            let project = |axes: &[Vec3; 3], half_extents: Vec3| {
+                (0..3).map(|index| (axes[index].dot(axis) * half_extents[index]).abs()).sum::<f32>()
+            };
+            offset.dot(axis).abs() <= project(&a_axes, self.half_extents) + project(&b_axes, other.half_extents)
+        })
+    }
+
+    pub fn get_bounds(&self) -> Aabb {
+        let extent = self.get_axes().iter().zip([self.half_extents.x, self.half_extents.y, self.half_extents.z])
+            .fold(Vec3::ZERO, |extent, (axis, half)| extent + axis.abs() * half);
+        Aabb::from_center_half_extents(self.center, extent)
+    }
+}
+
+impl From<Aabb> for Obb {
+    fn from(aabb: Aabb) -> Self {
+        Self::new(aabb.get_center(), aabb.get_half_extents(), Quat::IDENTITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    fn assert_vec3_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn ray_hits_and_misses_aabb() {
+        let aabb = Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+        assert_near(Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X).intersect_aabb(&aabb).unwrap(), 4.0);
+        assert_near(Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z).intersect_aabb(&aabb).unwrap(), 4.0);
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), -Vec3::X).intersect_aabb(&aabb), None);
+        assert_eq!(Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::new(1.0, 0.1, 0.0)).intersect_aabb(&aabb), None);
+        // Parallel to a slab, inside and outside of it.
+        assert_near(Ray::new(Vec3::new(-5.0, 0.5, 0.5), Vec3::X).intersect_aabb(&aabb).unwrap(), 4.0);
+        assert_eq!(Ray::new(Vec3::new(-5.0, 1.5, 0.0), Vec3::X).intersect_aabb(&aabb), None);
+        // Starting inside.
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0)).intersect_aabb(&aabb), Some(0.0));
+    }
+
+    #[test]
+    fn ray_hits_and_misses_sphere() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -10.0), 2.0);
+        assert_near(Ray::new(Vec3::ZERO, -Vec3::Z).intersect_sphere(&sphere).unwrap(), 8.0);
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::Z).intersect_sphere(&sphere), None);
+        assert_eq!(Ray::new(Vec3::new(3.0, 0.0, 0.0), -Vec3::Z).intersect_sphere(&sphere), None);
+        // Grazing the edge.
+        assert_near(Ray::new(Vec3::new(2.0, 0.0, 0.0), -Vec3::Z).intersect_sphere(&sphere).unwrap(), 10.0);
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, -10.0), Vec3::X).intersect_sphere(&sphere), Some(0.0));
+    }
+
+    #[test]
+    fn ray_hits_and_misses_plane() {
+        let plane = Plane::from_point_normal(Vec3::new(0.0, 2.0, 0.0), Vec3::Y);
+        assert_near(Ray::new(Vec3::new(1.0, 5.0, 1.0), -Vec3::Y).intersect_plane(&plane).unwrap(), 3.0);
+        assert_near(Ray::new(Vec3::ZERO, Vec3::Y).intersect_plane(&plane).unwrap(), 2.0);
+        assert_eq!(Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::Y).intersect_plane(&plane), None);
+        assert_eq!(Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::X).intersect_plane(&plane), None);
+    }
+
+    #[test]
+    fn ray_hits_and_misses_triangle() {
+        let (a, b, c) = (Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_near(Ray::new(Vec3::new(0.0, 0.0, 3.0), -Vec3::Z).intersect_triangle(a, b, c).unwrap(), 3.0);
+        // Back face.
+        assert_near(Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::Z).intersect_triangle(a, b, c).unwrap(), 3.0);
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, 3.0), Vec3::Z).intersect_triangle(a, b, c), None);
+        assert_eq!(Ray::new(Vec3::new(0.9, 0.9, 3.0), -Vec3::Z).intersect_triangle(a, b, c), None);
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, 3.0), Vec3::X).intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn ray_hits_and_misses_obb() {
+        let obb = Obb::new(Vec3::new(5.0, 0.0, 0.0), Vec3::new(1.0, 0.1, 1.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_4));
+        // The ray runs along the rotated long axis.
+        let diagonal = Vec3::new(1.0, 1.0, 0.0);
+        assert_near(Ray::new(Vec3::new(5.0, 0.0, 0.0) - diagonal * 3.0, diagonal).intersect_obb(&obb).unwrap(), 3.0 * 2.0_f32.sqrt() - 1.0);
+        assert_eq!(Ray::new(Vec3::new(5.0, 0.0, 0.0) - Vec3::new(1.0, -1.0, 0.0) * 3.0 + Vec3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 1.0, 0.0)).intersect_obb(&obb), None);
+        assert_eq!(Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::Y).intersect_obb(&obb), Some(0.0));
+    }
+
+    #[test]
+    fn ray_from_screen_goes_through_the_center() {
+        let view_projection = Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0) * Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let ray = Ray::from_screen(&view_projection, Vec2::new(50.0, 50.0), Vec2::new(100.0, 100.0));
+        assert_vec3_near(ray.direction, -Vec3::Z);
+        assert_near(ray.intersect_sphere(&Sphere::new(Vec3::ZERO, 1.0)).unwrap(), 4.0 - 0.1);
+    }
+
+    #[test]
+    fn frustum_culls_with_zero_to_one_depth() {
+        let view_projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 10.0) * Mat4::look_at_rh(Vec3::ZERO, -Vec3::Z, Vec3::Y);
+        let frustum = Frustum::from_view_projection(&view_projection);
+        // The near plane sits at the near distance, not at the camera as with -1 to 1 depth.
+        assert_near(frustum.planes[4].signed_distance(Vec3::new(0.0, 0.0, -1.0)), 0.0);
+        assert_near(frustum.planes[5].signed_distance(Vec3::new(0.0, 0.0, -10.0)), 0.0);
+
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -5.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -0.5)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -11.0)));
+        assert!(!frustum.contains_point(Vec3::new(6.0, 0.0, -5.0)));
+
+        assert!(frustum.intersects_sphere(&Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0)));
+        assert!(frustum.intersects_sphere(&Sphere::new(Vec3::new(0.0, 0.0, -11.0), 2.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(Vec3::new(0.0, 0.0, 5.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(Vec3::new(10.0, 0.0, -5.0), 1.0)));
+
+        assert!(frustum.intersects_aabb(&Aabb::from_center_half_extents(Vec3::new(0.0, 0.0, -5.0), Vec3::splat(1.0))));
+        assert!(frustum.intersects_aabb(&Aabb::new(Vec3::new(-100.0, -1.0, -6.0), Vec3::new(100.0, 1.0, -4.0))));
+        assert!(!frustum.intersects_aabb(&Aabb::from_center_half_extents(Vec3::new(0.0, 0.0, -12.0), Vec3::splat(1.0))));
+        assert!(!frustum.intersects_aabb(&Aabb::from_center_half_extents(Vec3::new(0.0, 10.0, -5.0), Vec3::splat(1.0))));
+    }
+
+    #[test]
+    fn obb_overlap_uses_separating_axes() {
+        let a = Obb::new(Vec3::ZERO, Vec3::splat(1.0), Quat::IDENTITY);
+        assert!(a.intersects_obb(&Obb::new(Vec3::new(1.5, 0.0, 0.0), Vec3::splat(1.0), Quat::IDENTITY)));
+        assert!(!a.intersects_obb(&Obb::new(Vec3::new(2.5, 0.0, 0.0), Vec3::splat(1.0), Quat::IDENTITY)));
+
+        // Rotated 45 degrees the corner reaches sqrt(2) along x.
+        let rotated = Quat::from_rotation_z(std::f32::consts::FRAC_PI_4);
+        assert!(a.intersects_obb(&Obb::new(Vec3::new(2.3, 0.0, 0.0), Vec3::splat(1.0), rotated)));
+        assert!(!a.intersects_obb(&Obb::new(Vec3::new(2.5, 0.0, 0.0), Vec3::splat(1.0), rotated)));
+
+        // The axis aligned bounds overlap, only the diagonal separates them.
+        let thin = Obb::new(Vec3::new(1.6, 1.6, 0.0), Vec3::new(2.0, 0.1, 1.0), Quat::from_rotation_z(-std::f32::consts::FRAC_PI_4));
+        assert!(a.get_bounds().intersects_aabb(&thin.get_bounds()));
+        assert!(!a.intersects_obb(&thin));
+        assert!(Obb::from(Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0))).intersects_obb(&a));
+    }
+
+    #[test]
+    fn rect_and_circle_overlap() {
+        let rect = Rect::new(Vec2::new(2.0, 2.0), Vec2::ZERO);
+        assert_eq!(rect.min, Vec2::ZERO);
+        assert!(rect.intersects(&Rect::new(Vec2::new(2.0, 2.0), Vec2::new(3.0, 3.0))));
+        assert!(!rect.intersects(&Rect::new(Vec2::new(2.1, 0.0), Vec2::new(3.0, 3.0))));
+        assert_eq!(rect.intersection(&Rect::new(Vec2::new(1.0, 1.0), Vec2::new(3.0, 3.0))), Some(Rect::new(Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0))));
+        assert!(rect.contains_rect(&Rect::new(Vec2::new(0.5, 0.5), Vec2::new(1.0, 1.0))));
+
+        let circle = Circle::new(Vec2::new(3.0, 1.0), 1.0);
+        assert!(circle.intersects_rect(&rect));
+        assert!(!Circle::new(Vec2::new(3.0, 3.0), 1.0).intersects_rect(&rect));
+        assert!(Circle::new(Vec2::new(2.6, 2.6), 1.0).intersects_rect(&rect));
+        assert!(circle.intersects_circle(&Circle::new(Vec2::new(5.0, 1.0), 1.0)));
+        assert!(!circle.intersects_circle(&Circle::new(Vec2::new(5.1, 1.0), 1.0)));
+    }
+
+    #[test]
+    fn closest_points() {
+        let rect = Rect::new(Vec2::ZERO, Vec2::new(2.0, 2.0));
+        assert_eq!(rect.closest_point(Vec2::new(5.0, -1.0)), Vec2::new(2.0, 0.0));
+        assert_eq!(rect.closest_point(Vec2::new(1.0, 1.0)), Vec2::new(1.0, 1.0));
+        assert_eq!(Circle::new(Vec2::ZERO, 2.0).closest_point(Vec2::new(0.0, 5.0)), Vec2::new(0.0, 2.0));
+
+        let aabb = Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+        assert_eq!(aabb.closest_point(Vec3::new(3.0, 0.5, -4.0)), Vec3::new(1.0, 0.5, -1.0));
+        assert_vec3_near(Sphere::new(Vec3::ZERO, 2.0).closest_point(Vec3::new(0.0, 0.0, 4.0)), Vec3::new(0.0, 0.0, 2.0));
+        assert_vec3_near(Sphere::new(Vec3::ZERO, 2.0).closest_point(Vec3::new(0.0, 1.0, 0.0)), Vec3::new(0.0, 1.0, 0.0));
+
+        let plane = Plane::from_points(Vec3::ZERO, Vec3::X, Vec3::Y);
+        assert_vec3_near(plane.normal, Vec3::Z);
+        assert_vec3_near(plane.closest_point(Vec3::new(1.0, 2.0, 3.0)), Vec3::new(1.0, 2.0, 0.0));
+
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        assert_vec3_near(ray.closest_point(Vec3::new(3.0, 4.0, 0.0)), Vec3::new(3.0, 0.0, 0.0));
+        assert_vec3_near(ray.closest_point(Vec3::new(-3.0, 4.0, 0.0)), Vec3::ZERO);
+
+        let obb = Obb::new(Vec3::ZERO, Vec3::new(1.0, 1.0, 1.0), Quat::from_rotation_z(std::f32::consts::FRAC_PI_4));
+        let corner = obb.closest_point(Vec3::new(5.0, 0.0, 0.0));
+        assert_vec3_near(corner, Vec3::new(2.0_f32.sqrt(), 0.0, 0.0));
+        assert!(obb.contains_point(corner * 0.99));
+    }
+}
@@ -0,0 +1,452 @@
+use std::ops::{Mul, MulAssign};
+use super::{Quat, Vec2, Vec3, Vec4};
+
+// Column major 3x3 matrix, handy for 2D transforms and normal matrices. WGSL pads every
+// column of a mat3x3 to 16 bytes, use to_cols_array_padded when writing it to a uniform.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3 {
+    pub cols: [Vec3; 3],
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat3 {
+    pub const ZERO: Self = Self { cols: [Vec3::ZERO; 3] };
+    pub const IDENTITY: Self = Self { cols: [Vec3::X, Vec3::Y, Vec3::Z] };
+
+    pub const fn from_cols(x_axis: Vec3, y_axis: Vec3, z_axis: Vec3) -> Self {
+        Self { cols: [x_axis, y_axis, z_axis] }
+    }
+
+    pub fn from_cols_array_2d(cols: &[[f32; 3]; 3]) -> Self {
+        Self { cols: cols.map(Vec3::from) }
+    }
+
+    pub fn to_cols_array_2d(&self) -> [[f32; 3]; 3] {
+        self.cols.map(Vec3::to_array)
+    }
+
+    pub fn to_cols_array_padded(&self) -> [[f32; 4]; 3] {
+        self.cols.map(|col| col.extend(0.0).to_array())
+    }
+
+    pub fn from_mat4(matrix: Mat4) -> Self {
+        Self::from_cols(matrix.cols[0].truncate(), matrix.cols[1].truncate(), matrix.cols[2].truncate())
+    }
+
+    pub fn from_quat(rotation: Quat) -> Self {
+        Self::from_mat4(Mat4::from_quat(rotation))
+    }
+
+    // 2D transforms with the translation in the third column.
+    pub fn from_translation(translation: Vec2) -> Self {
+        Self::from_cols(Vec3::X, Vec3::Y, translation.extend(1.0))
+    }
+
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols(Vec3::new(cos, sin, 0.0), Vec3::new(-sin, cos, 0.0), Vec3::Z)
+    }
+
+    pub fn from_scale(scale: Vec2) -> Self {
+        Self::from_cols(Vec3::new(scale.x, 0.0, 0.0), Vec3::new(0.0, scale.y, 0.0), Vec3::Z)
+    }
+
+    pub fn from_scale_angle_translation(scale: Vec2, angle: f32, translation: Vec2) -> Self {
+        Self::from_translation(translation) * Self::from_angle(angle) * Self::from_scale(scale)
+    }
+
+    pub fn row(&self, index: usize) -> Vec3 {
+        Vec3::new(self.cols[0][index], self.cols[1][index], self.cols[2][index])
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self::from_cols(self.row(0), self.row(1), self.row(2))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.cols[2].dot(self.cols[0].cross(self.cols[1]))
+    }
+
+    // Singular matrices come out as NaN.
+    pub fn inverse(&self) -> Self {
+        let [a, b, c] = self.cols;
+        let inverse_determinant = 1.0 / self.determinant();
+        Self::from_cols(b.cross(c), c.cross(a), a.cross(b)).transpose() * inverse_determinant
+    }
+
+    pub fn transform_point2(&self, point: Vec2) -> Vec2 {
+        (*self * point.extend(1.0)).truncate()
+    }
+
+    pub fn transform_vector2(&self, vector: Vec2) -> Vec2 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safe because the struct is repr(C) and only made of f32s.
+        unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>()) }
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self { cols: other.cols.map(|col| self * col) }
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, vector: Vec3) -> Vec3 {
+        self.cols[0] * vector.x + self.cols[1] * vector.y + self.cols[2] * vector.z
+    }
+}
+
+impl Mul<f32> for Mat3 {
+    type Output = Self;
+
+    fn mul(self, scalar: f32) -> Self {
+        Self { cols: self.cols.map(|col| col * scalar) }
+    }
+}
+
+impl MulAssign for Mat3 {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+// Column major 4x4 matrix with the same layout as a WGSL mat4x4<f32>. Projections are
+// right handed with the 0 to 1 depth range wgpu uses.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub cols: [Vec4; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const ZERO: Self = Self { cols: [Vec4::ZERO; 4] };
+    pub const IDENTITY: Self = Self { cols: [Vec4::X, Vec4::Y, Vec4::Z, Vec4::W] };
+
+    pub const fn from_cols(x_axis: Vec4, y_axis: Vec4, z_axis: Vec4, w_axis: Vec4) -> Self {
+        Self { cols: [x_axis, y_axis, z_axis, w_axis] }
+    }
+
+    pub fn from_cols_array_2d(cols: &[[f32; 4]; 4]) -> Self {
+        Self { cols: cols.map(Vec4::from) }
+    }
+
+    pub fn to_cols_array_2d(&self) -> [[f32; 4]; 4] {
+        self.cols.map(Vec4::to_array)
+    }
+
+    pub fn from_mat3(matrix: Mat3) -> Self {
+        Self::from_cols(matrix.cols[0].extend(0.0), matrix.cols[1].extend(0.0), matrix.cols[2].extend(0.0), Vec4::W)
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self::from_cols(Vec4::X, Vec4::Y, Vec4::Z, translation.extend(1.0))
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self::from_cols(Vec4::X * scale.x, Vec4::Y * scale.y, Vec4::Z * scale.z, Vec4::W)
+    }
+
+    pub fn from_quat(rotation: Quat) -> Self {
+        let Quat { x, y, z, w } = rotation;
+        let (x2, y2, z2) = (x + x, y + y, z + z);
+        let (xx, xy, xz) = (x * x2, x * y2, x * z2);
+        let (yy, yz, zz) = (y * y2, y * z2, z * z2);
+        let (wx, wy, wz) = (w * x2, w * y2, w * z2);
+
+        Self::from_cols(
+            Vec4::new(1.0 - (yy + zz), xy + wz, xz - wy, 0.0),
+            Vec4::new(xy - wz, 1.0 - (xx + zz), yz + wx, 0.0),
+            Vec4::new(xz + wy, yz - wx, 1.0 - (xx + yy), 0.0),
+            Vec4::W,
+        )
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Self {
+        Self::from_quat(Quat::from_rotation_x(angle))
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Self {
+        Self::from_quat(Quat::from_rotation_y(angle))
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Self {
+        Self::from_quat(Quat::from_rotation_z(angle))
+    }
+
+    // Scales first, then rotates, then translates.
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+        let rotation = Self::from_quat(rotation);
+        Self::from_cols(
+            rotation.cols[0] * scale.x,
+            rotation.cols[1] * scale.y,
+            rotation.cols[2] * scale.z,
+            translation.extend(1.0),
+        )
+    }
+
+    // Vertical field of view in radians.
+    pub fn perspective_rh(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let focal = 1.0 / (fov_y / 2.0).tan();
+        Self::from_cols(
+            Vec4::new(focal / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, focal, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, far / (near - far), -1.0),
+            Vec4::new(0.0, 0.0, near * far / (near - far), 0.0),
+        )
+    }
+
+    pub fn orthographic_rh(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let width = right - left;
+        let height = top - bottom;
+        let depth = near - far;
+        Self::from_cols(
+            Vec4::new(2.0 / width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / height, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, 1.0 / depth, 0.0),
+            Vec4::new(-(right + left) / width, -(top + bottom) / height, near / depth, 1.0),
+        )
+    }
+
+    pub fn look_to_rh(eye: Vec3, direction: Vec3, up: Vec3) -> Self {
+        let forward = direction.normalize();
+        let side = forward.cross(up).normalize();
+        let up = side.cross(forward);
+        Self::from_cols(
+            Vec4::new(side.x, up.x, -forward.x, 0.0),
+            Vec4::new(side.y, up.y, -forward.y, 0.0),
+            Vec4::new(side.z, up.z, -forward.z, 0.0),
+            Vec4::new(-side.dot(eye), -up.dot(eye), forward.dot(eye), 1.0),
+        )
+    }
+
+    pub fn look_at_rh(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        Self::look_to_rh(eye, target - eye, up)
+    }
+
+    pub fn row(&self, index: usize) -> Vec4 {
+        Vec4::new(self.cols[0][index], self.cols[1][index], self.cols[2][index], self.cols[3][index])
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.cofactor_pairs();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    // 2x2 determinants of the upper and lower halves, shared by determinant and inverse.
+    fn cofactor_pairs(&self) -> ([f32; 6], [f32; 6]) {
+        let m = self.to_cols_array_2d();
+        let s = [
+            m[0][0] * m[1][1] - m[1][0] * m[0][1],
+            m[0][0] * m[1][2] - m[1][0] * m[0][2],
+            m[0][0] * m[1][3] - m[1][0] * m[0][3],
+            m[0][1] * m[1][2] - m[1][1] * m[0][2],
+            m[0][1] * m[1][3] - m[1][1] * m[0][3],
+            m[0][2] * m[1][3] - m[1][2] * m[0][3],
+        ];
+        let c = [
+            m[2][0] * m[3][1] - m[3][0] * m[2][1],
+            m[2][0] * m[3][2] - m[3][0] * m[2][2],
+            m[2][0] * m[3][3] - m[3][0] * m[2][3],
+            m[2][1] * m[3][2] - m[3][1] * m[2][2],
+            m[2][1] * m[3][3] - m[3][1] * m[2][3],
+            m[2][2] * m[3][3] - m[3][2] * m[2][3],
+        ];
+        (s, c)
+    }
+
+    // Singular matrices come out as NaN.
+    pub fn inverse(&self) -> Self {
+        let m = self.to_cols_array_2d();
+        let (s, c) = self.cofactor_pairs();
+        let inverse_determinant = 1.0 / (s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]);
+
+        let result = [
+            [
+                m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3],
+                -m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3],
+                m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3],
+                -m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3],
+            ],
+            [
+                -m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1],
+                m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1],
+                -m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1],
+                m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1],
+            ],
+            [
+                m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0],
+                -m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0],
+                m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0],
+                -m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0],
+            ],
+            [
+                -m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0],
+                m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0],
+                -m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0],
+                m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0],
+            ],
+        ];
+        Self::from_cols_array_2d(&result) * inverse_determinant
+    }
+
+    pub fn transform_point3(&self, point: Vec3) -> Vec3 {
+        (*self * point.extend(1.0)).truncate()
+    }
+
+    pub fn transform_vector3(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+
+    // Divides by w afterwards, for points going through a projection.
+    pub fn project_point3(&self, point: Vec3) -> Vec3 {
+        let result = *self * point.extend(1.0);
+        result.truncate() / result.w
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safe because the struct is repr(C) and only made of f32s.
+        unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>()) }
+    }
+}
+
+impl From<[[f32; 4]; 4]> for Mat4 {
+    fn from(cols: [[f32; 4]; 4]) -> Self {
+        Self::from_cols_array_2d(&cols)
+    }
+}
+
+impl From<Mat4> for [[f32; 4]; 4] {
+    fn from(matrix: Mat4) -> Self {
+        matrix.to_cols_array_2d()
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self { cols: other.cols.map(|col| self * col) }
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, vector: Vec4) -> Vec4 {
+        self.cols[0] * vector.x + self.cols[1] * vector.y + self.cols[2] * vector.z + self.cols[3] * vector.w
+    }
+}
+
+impl Mul<f32> for Mat4 {
+    type Output = Self;
+
+    fn mul(self, scalar: f32) -> Self {
+        Self { cols: self.cols.map(|col| col * scalar) }
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec3_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_mat4_near(a: Mat4, b: Mat4) {
+        for (a, b) in a.cols.iter().zip(b.cols) {
+            assert!((*a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn inverse_round_trips() {
+        let rotation = Quat::from_axis_angle(Vec3::new(1.0, 2.0, 3.0).normalize(), 0.7);
+        let matrix = Mat4::from_scale_rotation_translation(Vec3::new(2.0, 3.0, 4.0), rotation, Vec3::new(1.0, -2.0, 5.0));
+        assert!((matrix.determinant() - 24.0).abs() < 1e-3);
+        assert_mat4_near(matrix * matrix.inverse(), Mat4::IDENTITY);
+        assert_mat4_near(matrix.inverse() * matrix, Mat4::IDENTITY);
+        assert_mat4_near(Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)).inverse(), Mat4::from_translation(Vec3::new(-1.0, -2.0, -3.0)));
+
+        let point = Vec3::new(0.5, -1.5, 2.0);
+        assert_vec3_near(matrix.inverse().transform_point3(matrix.transform_point3(point)), point);
+    }
+
+    #[test]
+    fn inverse_of_singular_matrix_is_nan() {
+        assert!(Mat4::from_scale(Vec3::new(1.0, 0.0, 1.0)).inverse().cols[0].x.is_nan());
+    }
+
+    #[test]
+    fn mat3_inverse_round_trips() {
+        let matrix = Mat3::from_scale_angle_translation(Vec2::new(2.0, 0.5), 0.3, Vec2::new(4.0, -1.0));
+        let product = matrix * matrix.inverse();
+        for (col, identity) in product.cols.iter().zip(Mat3::IDENTITY.cols) {
+            assert_vec3_near(*col, identity);
+        }
+        assert_eq!(Mat3::IDENTITY.to_cols_array_padded()[1], [0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_zero_and_one() {
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 2.0, 0.1, 100.0);
+        assert_vec3_near(projection.project_point3(Vec3::new(0.0, 0.0, -0.1)), Vec3::new(0.0, 0.0, 0.0));
+        assert_vec3_near(projection.project_point3(Vec3::new(0.0, 0.0, -100.0)), Vec3::new(0.0, 0.0, 1.0));
+        // The corner of the near plane, 90 degrees high and twice as wide.
+        assert_vec3_near(projection.project_point3(Vec3::new(0.2, 0.1, -0.1)), Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_to_clip_space() {
+        // Pixel coordinates with y going down, like the sprite batcher uses.
+        let projection = Mat4::orthographic_rh(0.0, 800.0, 600.0, 0.0, 0.0, 10.0);
+        assert_vec3_near(projection.transform_point3(Vec3::new(0.0, 600.0, 0.0)), Vec3::new(-1.0, -1.0, 0.0));
+        assert_vec3_near(projection.transform_point3(Vec3::new(800.0, 0.0, -10.0)), Vec3::new(1.0, 1.0, 1.0));
+        assert_vec3_near(projection.transform_point3(Vec3::new(400.0, 300.0, -5.0)), Vec3::new(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn look_at_puts_the_target_in_front() {
+        let eye = Vec3::new(0.0, 0.0, 5.0);
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+        assert_vec3_near(view.transform_point3(Vec3::ZERO), Vec3::new(0.0, 0.0, -5.0));
+        assert_vec3_near(view.transform_point3(eye), Vec3::ZERO);
+        assert_vec3_near(view.transform_point3(Vec3::new(1.0, 0.0, 5.0)), Vec3::X);
+
+        let eye = Vec3::new(3.0, 4.0, -2.0);
+        let view = Mat4::look_at_rh(eye, Vec3::new(1.0, 1.0, 1.0), Vec3::Y);
+        assert_vec3_near(view.inverse().transform_point3(Vec3::ZERO), eye);
+        let forward = view.transform_vector3((Vec3::new(1.0, 1.0, 1.0) - eye).normalize());
+        assert_vec3_near(forward, Vec3::new(0.0, 0.0, -1.0));
+    }
+}
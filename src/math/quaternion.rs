@@ -0,0 +1,255 @@
+use std::ops::{Mul, MulAssign, Neg};
+use super::{Mat3, Vec3, Vec4};
+
+// Unit quaternion for rotations, same memory layout as a Vec4.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    // The caller makes sure the values form a unit quaternion.
+    pub const fn from_xyzw(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    // Counter clockwise around the axis when looking down it, the axis gets normalized.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let axis = axis.normalize() * sin;
+        Self::from_xyzw(axis.x, axis.y, axis.z, cos)
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::X, angle)
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::Y, angle)
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::Z, angle)
+    }
+
+    // Yaw around y, then pitch around x, then roll around z, the usual order for cameras
+    // and characters in a y up world.
+    pub fn from_euler_yxz(yaw: f32, pitch: f32, roll: f32) -> Self {
+        Self::from_rotation_y(yaw) * Self::from_rotation_x(pitch) * Self::from_rotation_z(roll)
+    }
+
+    // The columns have to be orthonormal.
+    pub fn from_mat3(matrix: &Mat3) -> Self {
+        let [x_axis, y_axis, z_axis] = matrix.cols;
+        let trace = x_axis.x + y_axis.y + z_axis.z;
+        let quat = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::from_xyzw((y_axis.z - z_axis.y) / s, (z_axis.x - x_axis.z) / s, (x_axis.y - y_axis.x) / s, s / 4.0)
+        } else if x_axis.x > y_axis.y && x_axis.x > z_axis.z {
+            let s = (1.0 + x_axis.x - y_axis.y - z_axis.z).sqrt() * 2.0;
+            Self::from_xyzw(s / 4.0, (y_axis.x + x_axis.y) / s, (z_axis.x + x_axis.z) / s, (y_axis.z - z_axis.y) / s)
+        } else if y_axis.y > z_axis.z {
+            let s = (1.0 + y_axis.y - x_axis.x - z_axis.z).sqrt() * 2.0;
+            Self::from_xyzw((y_axis.x + x_axis.y) / s, s / 4.0, (z_axis.y + y_axis.z) / s, (z_axis.x - x_axis.z) / s)
+        } else {
+            let s = (1.0 + z_axis.z - x_axis.x - y_axis.y).sqrt() * 2.0;
+            Self::from_xyzw((z_axis.x + x_axis.z) / s, (z_axis.y + y_axis.z) / s, s / 4.0, (x_axis.y - y_axis.x) / s)
+        };
+        quat.normalize()
+    }
+
+    // Shortest rotation turning one direction into the other.
+    pub fn from_rotation_arc(from: Vec3, to: Vec3) -> Self {
+        let from = from.normalize();
+        let to = to.normalize();
+        let dot = from.dot(to);
+        if dot < -0.999_999 {
+            // Opposite directions, any perpendicular axis works.
+            let axis = if from.x.abs() < 0.9 { Vec3::X.cross(from) } else { Vec3::Y.cross(from) };
+            return Self::from_axis_angle(axis, std::f32::consts::PI);
+        }
+        let axis = from.cross(to);
+        Self::from_xyzw(axis.x, axis.y, axis.z, 1.0 + dot).normalize()
+    }
+
+    pub fn to_axis_angle(self) -> (Vec3, f32) {
+        let sin = (1.0 - self.w * self.w).max(0.0).sqrt();
+        let angle = 2.0 * self.w.clamp(-1.0, 1.0).acos();
+        if sin < 1e-6 {
+            (Vec3::X, angle)
+        } else {
+            (Vec3::new(self.x, self.y, self.z) / sin, angle)
+        }
+    }
+
+    fn to_vec4(self) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, self.w)
+    }
+
+    fn from_vec4(vector: Vec4) -> Self {
+        Self::from_xyzw(vector.x, vector.y, vector.z, vector.w)
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.to_vec4().dot(other.to_vec4())
+    }
+
+    pub fn length(self) -> f32 {
+        self.to_vec4().length()
+    }
+
+    pub fn normalize(self) -> Self {
+        Self::from_vec4(self.to_vec4().normalize())
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self::from_xyzw(-self.x, -self.y, -self.z, self.w)
+    }
+
+    // Same as the conjugate for unit quaternions.
+    pub fn inverse(self) -> Self {
+        Self::from_vec4(self.conjugate().to_vec4() / self.dot(self))
+    }
+
+    // Normalized linear interpolation, cheaper than slerp and fine for small angles.
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+        Self::from_vec4(self.to_vec4().lerp(other.to_vec4(), t)).normalize()
+    }
+
+    // Constant speed interpolation along the shortest arc.
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        let mut dot = self.dot(other);
+        let other = if dot < 0.0 {
+            dot = -dot;
+            -other
+        } else {
+            other
+        };
+
+        if dot > 0.9995 {
+            return self.lerp(other, t);
+        }
+
+        let theta = dot.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        Self::from_vec4(self.to_vec4() * a + other.to_vec4() * b)
+    }
+
+    pub fn angle_between(self, other: Self) -> f32 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safe because the struct is repr(C) and only made of f32s.
+        unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>()) }
+    }
+}
+
+impl Neg for Quat {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from_xyzw(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+// Applies other first, then self.
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::from_xyzw(
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        )
+    }
+}
+
+impl MulAssign for Quat {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+// Rotates the vector.
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, vector: Vec3) -> Vec3 {
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = axis.cross(vector) * 2.0;
+        vector + t * self.w + axis.cross(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    fn assert_quat_near(a: Quat, b: Quat) {
+        // q and -q are the same rotation.
+        assert!(a.angle_between(b) < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    // Round trips through a matrix and returns the diagonal, to tell which branch ran.
+    fn round_trip(rotation: Quat) -> Vec3 {
+        let matrix = Mat3::from_quat(rotation);
+        assert_quat_near(Quat::from_mat3(&matrix), rotation);
+        Vec3::new(matrix.cols[0].x, matrix.cols[1].y, matrix.cols[2].z)
+    }
+
+    #[test]
+    fn from_mat3_covers_every_trace_branch() {
+        let diagonal = round_trip(Quat::from_axis_angle(Vec3::new(1.0, 2.0, 3.0).normalize(), 0.5));
+        assert!(diagonal.x + diagonal.y + diagonal.z > 0.0);
+
+        let diagonal = round_trip(Quat::from_axis_angle(Vec3::new(1.0, 0.2, 0.1).normalize(), 3.0));
+        assert!(diagonal.x + diagonal.y + diagonal.z <= 0.0 && diagonal.x > diagonal.y && diagonal.x > diagonal.z);
+
+        let diagonal = round_trip(Quat::from_axis_angle(Vec3::new(0.2, 1.0, 0.1).normalize(), 3.0));
+        assert!(diagonal.x + diagonal.y + diagonal.z <= 0.0 && diagonal.y > diagonal.x && diagonal.y > diagonal.z);
+
+        let diagonal = round_trip(Quat::from_axis_angle(Vec3::new(0.1, 0.2, 1.0).normalize(), 3.0));
+        assert!(diagonal.x + diagonal.y + diagonal.z <= 0.0 && diagonal.z > diagonal.x && diagonal.z > diagonal.y);
+    }
+
+    #[test]
+    fn slerp_moves_at_constant_speed() {
+        let from = Quat::IDENTITY;
+        let to = Quat::from_rotation_z(FRAC_PI_2);
+        assert_quat_near(from.slerp(to, 0.0), from);
+        assert_quat_near(from.slerp(to, 1.0), to);
+        assert_quat_near(from.slerp(to, 0.5), Quat::from_rotation_z(FRAC_PI_4));
+        for t in [0.1, 0.25, 0.8] {
+            assert!((from.angle_between(from.slerp(to, t)) - FRAC_PI_2 * t).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn slerp_takes_the_shortest_arc() {
+        let from = Quat::IDENTITY;
+        let to = Quat::from_rotation_y(FRAC_PI_2);
+        assert_quat_near(from.slerp(-to, 0.5), Quat::from_rotation_y(FRAC_PI_4));
+        // Nearly equal rotations go through the lerp fallback.
+        let close = Quat::from_rotation_y(0.001);
+        assert_quat_near(from.slerp(close, 0.5), Quat::from_rotation_y(0.0005));
+    }
+}
@@ -0,0 +1,265 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// Operators and helpers shared by every vector size, all done component wise.
+macro_rules! vector {
+    ($name:ident, $size:literal, $($field:ident),+) => {
+        #[repr(C)]
+        #[derive(Debug, Clone, Copy, PartialEq, Default)]
+        pub struct $name {
+            $(pub $field: f32),+
+        }
+
+        impl $name {
+            pub const ZERO: Self = Self { $($field: 0.0),+ };
+            pub const ONE: Self = Self { $($field: 1.0),+ };
+
+            pub const fn new($($field: f32),+) -> Self {
+                Self { $($field),+ }
+            }
+
+            pub const fn splat(value: f32) -> Self {
+                Self { $($field: value),+ }
+            }
+
+            pub fn dot(self, other: Self) -> f32 {
+                0.0 $(+ self.$field * other.$field)+
+            }
+
+            pub fn length_squared(self) -> f32 {
+                self.dot(self)
+            }
+
+            pub fn length(self) -> f32 {
+                self.length_squared().sqrt()
+            }
+
+            pub fn distance(self, other: Self) -> f32 {
+                (self - other).length()
+            }
+
+            // Zero length vectors come out as NaN, see normalize_or_zero.
+            pub fn normalize(self) -> Self {
+                self / self.length()
+            }
+
+            pub fn normalize_or_zero(self) -> Self {
+                let length = self.length();
+                if length > 0.0 { self / length } else { Self::ZERO }
+            }
+
+            pub fn lerp(self, other: Self, t: f32) -> Self {
+                self + (other - self) * t
+            }
+
+            pub fn min(self, other: Self) -> Self {
+                Self { $($field: self.$field.min(other.$field)),+ }
+            }
+
+            pub fn max(self, other: Self) -> Self {
+                Self { $($field: self.$field.max(other.$field)),+ }
+            }
+
+            pub fn abs(self) -> Self {
+                Self { $($field: self.$field.abs()),+ }
+            }
+
+            pub fn to_array(self) -> [f32; $size] {
+                [$(self.$field),+]
+            }
+
+            // Raw bytes in GPU layout, ready for CatEngine::write_buffer. WGSL aligns a vec3 in
+            // a uniform or storage struct to 16 bytes, so a Vec3 followed by anything but an f32
+            // needs Vec3::to_array_padded instead.
+            pub fn as_bytes(&self) -> &[u8] {
+                // Safe because the struct is repr(C) and only made of f32s.
+                unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>()) }
+            }
+        }
+
+        impl From<[f32; $size]> for $name {
+            fn from(array: [f32; $size]) -> Self {
+                let [$($field),+] = array;
+                Self { $($field),+ }
+            }
+        }
+
+        impl From<$name> for [f32; $size] {
+            fn from(vector: $name) -> Self {
+                vector.to_array()
+            }
+        }
+
+        impl Index<usize> for $name {
+            type Output = f32;
+
+            fn index(&self, index: usize) -> &f32 {
+                [$(&self.$field),+][index]
+            }
+        }
+
+        impl IndexMut<usize> for $name {
+            fn index_mut(&mut self, index: usize) -> &mut f32 {
+                [$(&mut self.$field),+].into_iter().nth(index).expect("vector index out of range")
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self {
+                Self { $($field: self.$field + other.$field),+ }
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                Self { $($field: self.$field - other.$field),+ }
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+
+            fn mul(self, other: Self) -> Self {
+                Self { $($field: self.$field * other.$field),+ }
+            }
+        }
+
+        impl Div for $name {
+            type Output = Self;
+
+            fn div(self, other: Self) -> Self {
+                Self { $($field: self.$field / other.$field),+ }
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+
+            fn mul(self, scalar: f32) -> Self {
+                Self { $($field: self.$field * scalar),+ }
+            }
+        }
+
+        impl Mul<$name> for f32 {
+            type Output = $name;
+
+            fn mul(self, vector: $name) -> $name {
+                vector * self
+            }
+        }
+
+        impl Div<f32> for $name {
+            type Output = Self;
+
+            fn div(self, scalar: f32) -> Self {
+                Self { $($field: self.$field / scalar),+ }
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self { $($field: -self.$field),+ }
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign<f32> for $name {
+            fn mul_assign(&mut self, scalar: f32) {
+                *self = *self * scalar;
+            }
+        }
+
+        impl DivAssign<f32> for $name {
+            fn div_assign(&mut self, scalar: f32) {
+                *self = *self / scalar;
+            }
+        }
+    };
+}
+
+vector!(Vec2, 2, x, y);
+vector!(Vec3, 3, x, y, z);
+vector!(Vec4, 4, x, y, z, w);
+
+impl Vec2 {
+    pub const X: Self = Self::new(1.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0);
+
+    pub fn extend(self, z: f32) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
+    }
+
+    // Rotated 90 degrees counter clockwise.
+    pub fn perp(self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+
+    // z of the 3D cross product, the signed area of the parallelogram.
+    pub fn perp_dot(self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin)
+    }
+
+    pub fn rotate(self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+impl Vec3 {
+    pub const X: Self = Self::new(1.0, 0.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0, 0.0);
+    pub const Z: Self = Self::new(0.0, 0.0, 1.0);
+
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub fn truncate(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    // Takes the 16 bytes a WGSL vec3 occupies in a uniform.
+    pub fn to_array_padded(self) -> [f32; 4] {
+        self.extend(0.0).to_array()
+    }
+}
+
+impl Vec4 {
+    pub const X: Self = Self::new(1.0, 0.0, 0.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0, 0.0, 0.0);
+    pub const Z: Self = Self::new(0.0, 0.0, 1.0, 0.0);
+    pub const W: Self = Self::new(0.0, 0.0, 0.0, 1.0);
+
+    pub fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{CatEngine, math::Coordinate2D, sampler::SamplerPreset, surface::{Surface, SurfaceAttributes}};
+
+// How a fixed internal resolution is shown in a window of another size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingMode {
+    // Fills the window, distorting the image when the aspect ratios differ.
+    Stretch,
+    // As large as fits with the same aspect ratio, black bars fill the rest.
+    #[default]
+    Letterbox,
+    // The largest whole multiple that fits, so pixel art stays crisp. Windows smaller than
+    // the resolution fall back to Letterbox.
+    IntegerScale,
+}
+
+// Rectangle of the window the image ends up in, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ScalingMode {
+    pub fn get_viewport(self, resolution: (u32, u32), window: (u32, u32)) -> Viewport {
+        let (width, height) = (resolution.0.max(1) as f32, resolution.1.max(1) as f32);
+        let (window_width, window_height) = (window.0 as f32, window.1 as f32);
+        let fit = (window_width / width).min(window_height / height);
+
+        let scale = match self {
+            ScalingMode::Stretch => return Viewport { x: 0.0, y: 0.0, width: window_width, height: window_height },
+            ScalingMode::IntegerScale if fit >= 1.0 => fit.floor(),
+            _ => fit,
+        };
+        // Whole pixel offsets, so integer scaling lines up with the window pixels. The
+        // clamps catch rounding, wgpu rejects viewports reaching outside the target.
+        let x = ((window_width - width * scale) / 2.0).floor().max(0.0);
+        let y = ((window_height - height * scale) / 2.0).floor().max(0.0);
+        Viewport {
+            x,
+            y,
+            width: (width * scale).min(window_width - x),
+            height: (height * scale).min(window_height - y),
+        }
+    }
+}
+
+impl Viewport {
+    // None when the position is outside the viewport, e.g. on a letterbox bar.
+    pub fn window_to_resolution(&self, position: Coordinate2D, resolution: (u32, u32)) -> Option<Coordinate2D> {
+        let x = (position.x - self.x as f64) / self.width as f64;
+        let y = (position.y - self.y as f64) / self.height as f64;
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return None;
+        }
+        Some(Coordinate2D { x: x * resolution.0 as f64, y: y * resolution.1 as f64 })
+    }
+}
+
+// Offscreen target the window draws go into, scaled onto the window at the end of the frame.
+pub(crate) struct InternalResolution {
+    target: Surface,
+    width: u32,
+    height: u32,
+    scaling_mode: ScalingMode,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl InternalResolution {
+    pub(crate) fn new(catengine: &CatEngine, width: u32, height: u32, scaling_mode: ScalingMode) -> Self {
+        let mut args = SurfaceAttributes::default_attributes_render_target(catengine);
+        args.set_width_height_to_specific(width.max(1), height.max(1));
+        args.set_label(Some("internal resolution target"));
+        args.set_sampler_preset(if scaling_mode == ScalingMode::IntegerScale { SamplerPreset::PixelArt } else { SamplerPreset::Linear });
+        let target = Surface::new_render_target(catengine, args);
+
+        let layout = catengine.get_bind_group_layout(&[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]);
+        let bind_group = catengine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("internal resolution bind group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(target.get_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(target.get_sampler()),
+                },
+            ],
+        });
+
+        let shader = catengine.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blit.wgsl").into()),
+        });
+        let pipeline_layout = catengine.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let pipeline = catengine.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: catengine.config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Self { target, width: width.max(1), height: height.max(1), scaling_mode, pipeline, bind_group }
+    }
+
+    pub(crate) fn get_target(&self) -> &Surface {
+        &self.target
+    }
+
+    pub(crate) fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub(crate) fn get_scaling_mode(&self) -> ScalingMode {
+        self.scaling_mode
+    }
+
+    pub(crate) fn get_viewport(&self, window: (u32, u32)) -> Viewport {
+        self.scaling_mode.get_viewport(self.get_size(), window)
+    }
+
+    pub(crate) fn blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, window: (u32, u32)) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    // Black bars around letterboxed images.
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+
+        let viewport = self.get_viewport(window);
+        render_pass.set_viewport(viewport.x, viewport.y, viewport.width, viewport.height, 0.0, 1.0);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport { x, y, width, height }
+    }
+
+    #[test]
+    fn letterbox_keeps_the_aspect_ratio() {
+        // Wider window, bars left and right.
+        assert_eq!(ScalingMode::Letterbox.get_viewport((320, 180), (1000, 360)), viewport(180.0, 0.0, 640.0, 360.0));
+        // Taller window, bars at the top and bottom.
+        assert_eq!(ScalingMode::Letterbox.get_viewport((320, 180), (640, 600)), viewport(0.0, 120.0, 640.0, 360.0));
+        assert_eq!(ScalingMode::Letterbox.get_viewport((320, 180), (1280, 720)), viewport(0.0, 0.0, 1280.0, 720.0));
+        // Smaller than the resolution scales down.
+        assert_eq!(ScalingMode::Letterbox.get_viewport((320, 180), (160, 100)), viewport(0.0, 5.0, 160.0, 90.0));
+    }
+
+    #[test]
+    fn integer_scale_uses_whole_multiples() {
+        assert_eq!(ScalingMode::IntegerScale.get_viewport((320, 180), (1000, 600)), viewport(20.0, 30.0, 960.0, 540.0));
+        assert_eq!(ScalingMode::IntegerScale.get_viewport((320, 180), (700, 2000)), viewport(30.0, 820.0, 640.0, 360.0));
+        // Below 1x it falls back to letterboxing.
+        assert_eq!(ScalingMode::IntegerScale.get_viewport((320, 180), (160, 100)), viewport(0.0, 5.0, 160.0, 90.0));
+    }
+
+    #[test]
+    fn stretch_fills_the_window() {
+        assert_eq!(ScalingMode::Stretch.get_viewport((320, 180), (1000, 360)), viewport(0.0, 0.0, 1000.0, 360.0));
+        assert_eq!(ScalingMode::Stretch.get_viewport((320, 180), (100, 50)), viewport(0.0, 0.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn viewports_stay_inside_odd_windows() {
+        for window in [(1001, 601), (333, 777), (1, 1), (0, 0)] {
+            for mode in [ScalingMode::Letterbox, ScalingMode::IntegerScale, ScalingMode::Stretch] {
+                let viewport = mode.get_viewport((320, 180), window);
+                assert!(viewport.x >= 0.0 && viewport.y >= 0.0, "{:?} {:?}", mode, viewport);
+                assert!(viewport.x + viewport.width <= window.0 as f32, "{:?} {:?}", mode, viewport);
+                assert!(viewport.y + viewport.height <= window.1 as f32, "{:?} {:?}", mode, viewport);
+            }
+        }
+    }
+
+    #[test]
+    fn window_positions_map_into_the_resolution() {
+        let resolution = (320, 180);
+        let viewport = ScalingMode::Letterbox.get_viewport(resolution, (1000, 360));
+        let map = |x, y| viewport.window_to_resolution(Coordinate2D { x, y }, resolution).map(|point| (point.x, point.y));
+        assert_eq!(map(180.0, 0.0), Some((0.0, 0.0)));
+        assert_eq!(map(500.0, 180.0), Some((160.0, 90.0)));
+        assert_eq!(map(819.0, 359.0), Some((319.5, 179.5)));
+        // On the bars and past the far edges.
+        assert_eq!(map(179.0, 100.0), None);
+        assert_eq!(map(820.0, 100.0), None);
+        assert_eq!(map(500.0, 360.0), None);
+        assert_eq!(map(-1.0, 100.0), None);
+
+        let viewport = ScalingMode::Letterbox.get_viewport(resolution, (640, 600));
+        let map = |x, y| viewport.window_to_resolution(Coordinate2D { x, y }, resolution).map(|point| (point.x, point.y));
+        assert_eq!(map(320.0, 60.0), None);
+        assert_eq!(map(320.0, 480.0), None);
+        assert_eq!(map(320.0, 300.0), Some((160.0, 90.0)));
+
+        // A window smaller than the resolution reaches every internal pixel.
+        let viewport = ScalingMode::Letterbox.get_viewport(resolution, (160, 100));
+        let map = |x, y| viewport.window_to_resolution(Coordinate2D { x, y }, resolution).map(|point| (point.x, point.y));
+        assert_eq!(map(80.0, 50.0), Some((160.0, 90.0)));
+        assert_eq!(map(159.5, 94.5), Some((319.0, 179.0)));
+        assert_eq!(map(80.0, 2.0), None);
+    }
+}
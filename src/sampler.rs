@@ -0,0 +1,146 @@
+use crate::CatEngine;
+
+pub use wgpu::{AddressMode, FilterMode, MipmapFilterMode, SamplerBorderColor, CompareFunction, SamplerDescriptor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SamplerPreset {
+    // Nearest filtering everywhere, keeps pixel art crisp.
+    PixelArt,
+    Linear,
+    Trilinear,
+    // Trilinear with the given anisotropy clamp (1 to 16).
+    Anisotropic(u16),
+    Repeat,
+    Mirror,
+    // Needs Features::ADDRESS_MODE_CLAMP_TO_BORDER on the adapter (or ADDRESS_MODE_CLAMP_TO_ZERO
+    // for the Zero color), clamps to the edge otherwise.
+    ClampToBorder(SamplerBorderColor),
+    // Depth comparison sampler for shadow maps.
+    Comparison,
+}
+
+impl SamplerPreset {
+    pub fn descriptor(&self) -> SamplerDescriptor<'static> {
+        let linear = SamplerDescriptor {
+            label: Some("linear sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: MipmapFilterMode::Nearest,
+            ..Default::default()
+        };
+
+        match *self {
+            SamplerPreset::PixelArt => SamplerDescriptor {
+                label: Some("pixel art sampler"),
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                ..linear
+            },
+            SamplerPreset::Linear => linear,
+            SamplerPreset::Trilinear => SamplerDescriptor {
+                label: Some("trilinear sampler"),
+                mipmap_filter: MipmapFilterMode::Linear,
+                ..linear
+            },
+            SamplerPreset::Anisotropic(anisotropy_clamp) => SamplerDescriptor {
+                label: Some("anisotropic sampler"),
+                mipmap_filter: MipmapFilterMode::Linear,
+                anisotropy_clamp: anisotropy_clamp.clamp(1, 16),
+                ..linear
+            },
+            SamplerPreset::Repeat => SamplerDescriptor {
+                label: Some("repeat sampler"),
+                address_mode_u: AddressMode::Repeat,
+                address_mode_v: AddressMode::Repeat,
+                address_mode_w: AddressMode::Repeat,
+                ..linear
+            },
+            SamplerPreset::Mirror => SamplerDescriptor {
+                label: Some("mirror sampler"),
+                address_mode_u: AddressMode::MirrorRepeat,
+                address_mode_v: AddressMode::MirrorRepeat,
+                address_mode_w: AddressMode::MirrorRepeat,
+                ..linear
+            },
+            SamplerPreset::ClampToBorder(border_color) => SamplerDescriptor {
+                label: Some("clamp to border sampler"),
+                address_mode_u: AddressMode::ClampToBorder,
+                address_mode_v: AddressMode::ClampToBorder,
+                address_mode_w: AddressMode::ClampToBorder,
+                border_color: Some(border_color),
+                ..linear
+            },
+            SamplerPreset::Comparison => SamplerDescriptor {
+                label: Some("comparison sampler"),
+                compare: Some(CompareFunction::LessEqual),
+                lod_min_clamp: 0.0,
+                lod_max_clamp: 100.0,
+                ..linear
+            },
+        }
+    }
+}
+
+pub struct Sampler {
+    sampler: wgpu::Sampler,
+    // Kept without the label so the sampler can be made again on a new device.
+    descriptor: SamplerDescriptor<'static>,
+    filtering: bool,
+    comparison: bool,
+}
+
+impl Sampler {
+    pub fn new(catengine: &CatEngine, descriptor: &SamplerDescriptor) -> Self {
+        let features = catengine.device.features();
+        let border_supported = features.contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER)
+            || (descriptor.border_color == Some(SamplerBorderColor::Zero) && features.contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_ZERO));
+        let address_modes = [descriptor.address_mode_u, descriptor.address_mode_v, descriptor.address_mode_w];
+
+        let mut fallback = descriptor.clone();
+        if address_modes.contains(&AddressMode::ClampToBorder) && !border_supported {
+            log::warn!("the device can't clamp samplers to a border color, clamping to the edge instead");
+            let to_edge = |mode| if mode == AddressMode::ClampToBorder { AddressMode::ClampToEdge } else { mode };
+            fallback.address_mode_u = to_edge(descriptor.address_mode_u);
+            fallback.address_mode_v = to_edge(descriptor.address_mode_v);
+            fallback.address_mode_w = to_edge(descriptor.address_mode_w);
+            fallback.border_color = None;
+        }
+
+        Self {
+            sampler: catengine.device.create_sampler(&fallback),
+            descriptor: descriptor.map_label(|_| None),
+            filtering: descriptor.mag_filter == FilterMode::Linear
+                || descriptor.min_filter == FilterMode::Linear
+                || descriptor.mipmap_filter == MipmapFilterMode::Linear,
+            comparison: descriptor.compare.is_some(),
+        }
+    }
+
+    // Not cached, use CatEngine::get_sampler to share one sampler per preset.
+    pub fn from_preset(catengine: &CatEngine, preset: SamplerPreset) -> Self {
+        Self::new(catengine, &preset.descriptor())
+    }
+
+    pub(crate) fn recreate(&self, catengine: &CatEngine) -> Self {
+        Self::new(catengine, &self.descriptor)
+    }
+
+    pub fn get_sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    pub fn get_descriptor(&self) -> &SamplerDescriptor<'static> {
+        &self.descriptor
+    }
+
+    pub fn is_filtering(&self) -> bool {
+        self.filtering
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        self.comparison
+    }
+}
@@ -45,58 +45,105 @@ pub struct ShaderAttributes {
 
 pub struct Shader {
     render_pipeline: RenderPipeline,
+    // What the pipeline was made from, so recreate can make it again. None for the
+    // engine's built in pipelines.
+    params: Option<ShaderParams>,
+}
+
+struct ShaderParams {
+    location: &'static str,
+    vertex_buffer_layouts: Vec<Option<(wgpu::BufferAddress, wgpu::VertexStepMode, Vec<wgpu::VertexAttribute>)>>,
+    vertex_function_name: String,
+    fragment_function_name: Option<String>,
+    topology: wgpu::PrimitiveTopology,
+    front_face: wgpu::FrontFace,
+    cull_mode: Option<wgpu::Face>,
+    depth_stencil: Option<DepthStencilState>,
 }
 
 pub use wgpu::Face;
 pub use wgpu::FrontFace;
 pub use wgpu::PrimitiveTopology;
 pub use wgpu::VertexBufferLayout;
+pub use wgpu::{CompareFunction, DepthStencilState};
+
+// Writes depth and keeps the nearest fragment, what most 3D drawing wants.
+pub fn default_depth_stencil(format: wgpu::TextureFormat) -> DepthStencilState {
+    DepthStencilState {
+        format,
+        depth_write_enabled: Some(true),
+        depth_compare: Some(CompareFunction::Less),
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
 
 impl Shader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(location: &'static str, catengine: &mut crate::CatEngine, vertex_buffer_layouts: Option<&[Option<VertexBufferLayout>]>, vertex_function_name: &str, framgment_function_name: &str, topology: wgpu::PrimitiveTopology, front_face: wgpu::FrontFace, cull_mode: Option<wgpu::Face>, bind_group_layouts: &[Option<&crate::bindgroup::BindGroupLayout>]) -> Result<Shader, ShaderError> {
+        let params = ShaderParams::new(location, vertex_buffer_layouts, vertex_function_name, Some(framgment_function_name), topology, front_face, cull_mode, None);
+        Self::build(catengine, params, bind_group_layouts)
+    }
+
+    // Same as new but tests against the depth attachment of the pass it is drawn in. Without
+    // a fragment function nothing but depth is written, for shadow maps and depth prepasses.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_depth(location: &'static str, catengine: &mut crate::CatEngine, vertex_buffer_layouts: Option<&[Option<VertexBufferLayout>]>, vertex_function_name: &str, framgment_function_name: Option<&str>, topology: wgpu::PrimitiveTopology, front_face: wgpu::FrontFace, cull_mode: Option<wgpu::Face>, bind_group_layouts: &[Option<&crate::bindgroup::BindGroupLayout>], depth_stencil: DepthStencilState) -> Result<Shader, ShaderError> {
+        let params = ShaderParams::new(location, vertex_buffer_layouts, vertex_function_name, framgment_function_name, topology, front_face, cull_mode, Some(depth_stencil));
+        Self::build(catengine, params, bind_group_layouts)
+    }
+
+    // Makes the pipeline again on the engine's current device, see Program::device_recreated.
+    // The bind group layouts of the old device are dead too, so the new ones are passed in.
+    pub fn recreate(&mut self, catengine: &mut crate::CatEngine, bind_group_layouts: &[Option<&crate::bindgroup::BindGroupLayout>]) -> Result<(), ShaderError> {
+        let params = self.params.take().expect("built in shaders are recreated by the renderer that made them");
+        *self = Self::build(catengine, params, bind_group_layouts)?;
+        Ok(())
+    }
+
+    fn build(catengine: &mut crate::CatEngine, params: ShaderParams, bind_group_layouts: &[Option<&crate::bindgroup::BindGroupLayout>]) -> Result<Shader, ShaderError> {
         let shader = catengine.device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(std::fs::read_to_string(location).unwrap().into()),
+            source: wgpu::ShaderSource::Wgsl(std::fs::read_to_string(params.location).unwrap().into()),
         });
+        let vertex_buffer_layouts: Vec<Option<VertexBufferLayout>> = params.vertex_buffer_layouts.iter()
+            .map(|layout| layout.as_ref().map(|(array_stride, step_mode, attributes)| VertexBufferLayout { array_stride: *array_stride, step_mode: *step_mode, attributes }))
+            .collect();
 
         let render_pipeline_layout =
             catengine.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
 
-                bind_group_layouts: bind_group_layouts,
+                bind_group_layouts,
                 immediate_size: 0,
             }
         );
 
+        let color_targets = [Some(wgpu::ColorTargetState {
+            format: catengine.config.format,
+            blend: Some(wgpu::BlendState::REPLACE),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
         let render_pipeline = catengine.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
-                entry_point: Some(vertex_function_name),
-                buffers: {
-                    match vertex_buffer_layouts {
-                        Some(v) => v,
-                        None => &[]
-                    }
-                },
+                entry_point: Some(&params.vertex_function_name),
+                buffers: &vertex_buffer_layouts,
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
-            fragment: Some(wgpu::FragmentState {
+            fragment: params.fragment_function_name.as_deref().map(|framgment_function_name| wgpu::FragmentState {
                 module: &shader,
                 entry_point: Some(framgment_function_name),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: catengine.config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                targets: &color_targets,
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             }),
             primitive: wgpu::PrimitiveState {
-                topology,
+                topology: params.topology,
                 strip_index_format: None,
-                front_face,
-                cull_mode,
+                front_face: params.front_face,
+                cull_mode: params.cull_mode,
                 // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
                 polygon_mode: wgpu::PolygonMode::Fill,
                 // Requires Features::DEPTH_CLIP_CONTROL
@@ -104,7 +151,7 @@ impl Shader {
                 // Requires Features::CONSERVATIVE_RASTERIZATION
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil: params.depth_stencil.clone(),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
@@ -114,10 +161,33 @@ impl Shader {
             cache: None,
         });
 
-        Ok(Self{ render_pipeline })
+        Ok(Self{ render_pipeline, params: Some(params) })
+    }
+
+    // For the engine's built in renderers, which set up their own pipelines.
+    pub(crate) fn from_pipeline(render_pipeline: RenderPipeline) -> Self {
+        Self { render_pipeline, params: None }
     }
 
     pub fn get_pipeline(&self) -> &RenderPipeline {
         &self.render_pipeline
     }
 }
+
+impl ShaderParams {
+    #[allow(clippy::too_many_arguments)]
+    fn new(location: &'static str, vertex_buffer_layouts: Option<&[Option<VertexBufferLayout>]>, vertex_function_name: &str, fragment_function_name: Option<&str>, topology: wgpu::PrimitiveTopology, front_face: wgpu::FrontFace, cull_mode: Option<wgpu::Face>, depth_stencil: Option<DepthStencilState>) -> Self {
+        Self {
+            location,
+            vertex_buffer_layouts: vertex_buffer_layouts.unwrap_or(&[]).iter()
+                .map(|layout| layout.as_ref().map(|layout| (layout.array_stride, layout.step_mode, layout.attributes.to_vec())))
+                .collect(),
+            vertex_function_name: vertex_function_name.to_string(),
+            fragment_function_name: fragment_function_name.map(str::to_string),
+            topology,
+            front_face,
+            cull_mode,
+            depth_stencil,
+        }
+    }
+}
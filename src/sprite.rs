@@ -0,0 +1,376 @@
+use std::{collections::HashMap, sync::Arc};
+use wgpu::{BindGroup, BindGroupLayout};
+use crate::{CatEngine, CatEngineDrawCommand, atlas::UvRect, color::Color, bindgroup::BindGroupBuilder, buffer::{Buffer, BufferUsages}, math, shader::Shader, surface::Surface};
+
+// Four vertices and six u16 indices per sprite, so one draw covers at most this many.
+const MAX_SPRITES_PER_BATCH: usize = 16384;
+// position, uv and color as f32
+const VERTEX_SIZE: usize = (2 + 2 + 4) * 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BlendMode {
+    Alpha,
+    // For textures whose color is already multiplied by alpha.
+    PremultipliedAlpha,
+    Additive,
+    Multiply,
+    Opaque,
+}
+
+impl BlendMode {
+    fn blend_state(&self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::PremultipliedAlpha => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            BlendMode::Multiply => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Sprite {
+    pub texture: Arc<Surface>,
+    // Where the origin of the sprite ends up, in pixels from the top left of the window
+    // unless the batcher has its own projection.
+    pub position: math::Coordinate2D,
+    pub size: math::Coordinate2D,
+    // Radians, clockwise around the origin.
+    pub rotation: f64,
+    // Pivot for position and rotation, (0, 0) is the top left corner and (1, 1) the bottom right.
+    pub origin: math::Coordinate2D,
+    pub tint: Color,
+    pub uv: UvRect,
+    // Lower layers are drawn first.
+    pub layer: i32,
+    pub blend_mode: BlendMode,
+}
+
+impl Sprite {
+    pub fn new(texture: Arc<Surface>, position: math::Coordinate2D, size: math::Coordinate2D) -> Self {
+        Self {
+            texture,
+            position,
+            size,
+            rotation: 0.0,
+            origin: math::Coordinate2D { x: 0.0, y: 0.0 },
+            tint: Color::WHITE,
+            uv: UvRect::FULL,
+            layer: 0,
+            blend_mode: BlendMode::Alpha,
+        }
+    }
+
+    // Uses an image of a texture atlas, see TextureAtlas::get_sprite.
+    pub fn from_atlas(sprite: (Arc<Surface>, UvRect), position: math::Coordinate2D, size: math::Coordinate2D) -> Self {
+        Self {
+            uv: sprite.1,
+            ..Self::new(sprite.0, position, size)
+        }
+    }
+
+    fn write_vertices(&self, vertices: &mut Vec<u8>, format: wgpu::TextureFormat) {
+        let tint = self.tint.for_format(format);
+        let (sin, cos) = self.rotation.sin_cos();
+        let corners = [
+            (0.0, 0.0, self.uv.min_x, self.uv.min_y),
+            (1.0, 0.0, self.uv.max_x, self.uv.min_y),
+            (1.0, 1.0, self.uv.max_x, self.uv.max_y),
+            (0.0, 1.0, self.uv.min_x, self.uv.max_y),
+        ];
+
+        for (corner_x, corner_y, u, v) in corners {
+            let x = (corner_x - self.origin.x) * self.size.x;
+            let y = (corner_y - self.origin.y) * self.size.y;
+            let position = [
+                (self.position.x + x * cos - y * sin) as f32,
+                (self.position.y + x * sin + y * cos) as f32,
+            ];
+            for value in position.into_iter().chain([u, v]).chain(tint.to_array()) {
+                vertices.extend_from_slice(&value.to_ne_bytes());
+            }
+        }
+    }
+}
+
+// Collects sprites during a frame and turns them into as few draw commands as possible.
+// Sprites are stably sorted by layer, so within a layer they keep the order they were
+// drawn in. Consecutive sprites sharing a texture and blend mode end up in the same draw.
+pub struct SpriteBatcher {
+    sprites: Vec<Sprite>,
+    projection: Option<math::Mat4>,
+    globals_layout: Arc<BindGroupLayout>,
+    texture_layout: Arc<BindGroupLayout>,
+    // Keyed by the depth format of the pass as well, sprites ignore depth but must match it.
+    pipelines: HashMap<(BlendMode, Option<wgpu::TextureFormat>), Arc<Shader>>,
+    bind_groups: HashMap<usize, (Arc<Surface>, Arc<BindGroup>)>,
+    index_buffer: Arc<Buffer>,
+    // Reused once no pending draw command holds them anymore.
+    vertex_buffers: Vec<Arc<Buffer>>,
+    globals: Vec<(Arc<Buffer>, Arc<BindGroup>)>,
+}
+
+impl SpriteBatcher {
+    pub fn new(catengine: &CatEngine) -> Self {
+        let globals_layout = catengine.get_bind_group_layout(&[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }]);
+        let texture_layout = catengine.get_bind_group_layout(&[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]);
+
+        let indices: Vec<u8> = (0..MAX_SPRITES_PER_BATCH as u16)
+            .flat_map(|sprite| [0, 1, 2, 2, 3, 0].map(|corner| sprite * 4 + corner))
+            .flat_map(|index| index.to_ne_bytes())
+            .collect();
+        let index_buffer = Arc::new(Buffer::new(catengine, &indices, Some("sprite index buffer"), BufferUsages::INDEX));
+
+        Self {
+            sprites: vec![],
+            projection: None,
+            globals_layout,
+            texture_layout,
+            pipelines: HashMap::new(),
+            bind_groups: HashMap::new(),
+            index_buffer,
+            vertex_buffers: vec![],
+            globals: vec![],
+        }
+    }
+
+    // Makes the pipelines and buffers again on the engine's current device, see
+    // Program::device_recreated. Sprites drawn since the last flush are dropped, their
+    // surfaces belong to the old device.
+    pub fn recreate(&mut self, catengine: &CatEngine) {
+        let projection = self.projection;
+        *self = Self::new(catengine);
+        self.projection = projection;
+    }
+
+    // None uses pixel coordinates over the window, with y going down.
+    pub fn set_projection(&mut self, projection: Option<math::Mat4>) { self.projection = projection; }
+
+    pub fn draw(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    // Turns the sprites drawn since the last flush into draw commands on the engine.
+    pub fn flush(&mut self, catengine: &mut CatEngine) {
+        if self.sprites.is_empty() {
+            return;
+        }
+
+        let mut sprites = std::mem::take(&mut self.sprites);
+        sprites.sort_by_key(|sprite| sprite.layer);
+
+        // The pass the sprites land in is set by the last target command queued before the flush.
+        let depth_format = match catengine.command_list.iter().rev().find(|command| !matches!(command, CatEngineDrawCommand::Shader(..))) {
+            Some(CatEngineDrawCommand::SetRenderTargetWithDepth(_, _, depth, _)) => catengine.get_render_target(*depth).map(|surface| surface.get_format()),
+            Some(CatEngineDrawCommand::SetDepthOnlyTarget(..)) => panic!("sprites can't be flushed into a depth only pass, they need a color target"),
+            _ => None,
+        };
+
+        let globals = self.globals(catengine);
+
+        let mut start = 0;
+        while start < sprites.len() {
+            let first = &sprites[start];
+            let texture_key = Arc::as_ptr(&first.texture) as usize;
+            let blend_mode = first.blend_mode;
+            let count = sprites[start..].iter()
+                .take(MAX_SPRITES_PER_BATCH)
+                .take_while(|sprite| sprite.blend_mode == blend_mode && Arc::as_ptr(&sprite.texture) as usize == texture_key)
+                .count();
+
+            let mut vertices = Vec::with_capacity(count * 4 * VERTEX_SIZE);
+            for sprite in &sprites[start..start + count] {
+                sprite.write_vertices(&mut vertices, catengine.config.format);
+            }
+            let vertex_buffer = self.vertex_buffer(catengine, vertices.len() as u64);
+            catengine.queue.write_buffer(vertex_buffer.get_buffer(), 0, &vertices);
+
+            let texture_bind_group = self.texture_bind_group(catengine, &first.texture);
+            let pipeline = self.pipeline(catengine, blend_mode, depth_format);
+
+            catengine.command_list.push(CatEngineDrawCommand::Shader(
+                pipeline,
+                vertex_buffer,
+                self.index_buffer.clone(),
+                0,
+                math::Range::Full,
+                0..(count * 6) as u32,
+                0..1,
+                vec![(globals.clone(), 0, vec![]), (texture_bind_group, 1, vec![])],
+            ));
+            start += count;
+        }
+
+        // Forget the bind groups of textures nobody else holds anymore.
+        self.bind_groups.retain(|_, (texture, _)| Arc::strong_count(texture) > 1);
+    }
+
+    fn globals(&mut self, catengine: &CatEngine) -> Arc<BindGroup> {
+        let projection = self.projection.unwrap_or_else(|| {
+            let (width, height) = (catengine.width.max(1) as f32, catengine.height.max(1) as f32);
+            math::Mat4::orthographic_rh(0.0, width, height, 0.0, 0.0, 1.0)
+        });
+        let contents = projection.as_bytes();
+
+        let free = self.globals.iter().find(|(_, bind_group)| Arc::strong_count(bind_group) == 1).cloned();
+        let (buffer, bind_group) = free.unwrap_or_else(|| {
+            let buffer = Arc::new(Buffer::new_empty(catengine, contents.len() as u64, Some("sprite globals"), BufferUsages::UNIFORM | BufferUsages::COPY_DST));
+            let bind_group = BindGroupBuilder::new()
+                .with_visibility(wgpu::ShaderStages::VERTEX)
+                .buffer(&buffer)
+                .build(catengine);
+            self.globals.push((buffer.clone(), bind_group.clone()));
+            (buffer, bind_group)
+        });
+        catengine.queue.write_buffer(buffer.get_buffer(), 0, contents);
+        bind_group
+    }
+
+    fn vertex_buffer(&mut self, catengine: &CatEngine, size: u64) -> Arc<Buffer> {
+        if let Some(buffer) = self.vertex_buffers.iter().find(|buffer| Arc::strong_count(buffer) == 1 && buffer.get_buffer().size() >= size) {
+            return buffer.clone();
+        }
+
+        let buffer = Arc::new(Buffer::new_empty(catengine, size.next_power_of_two(), Some("sprite vertex buffer"), BufferUsages::VERTEX | BufferUsages::COPY_DST));
+        self.vertex_buffers.push(buffer.clone());
+        buffer
+    }
+
+    fn texture_bind_group(&mut self, catengine: &CatEngine, texture: &Arc<Surface>) -> Arc<BindGroup> {
+        // The layout above takes filterable color textures, anything else is turned away here
+        // rather than failing inside wgpu.
+        assert!(
+            texture.get_sample_type() == wgpu::TextureSampleType::Float { filterable: true } && !texture.get_shared_sampler().is_comparison(),
+            "{:?} surfaces can't be drawn as sprites, they need a filterable color format and a non comparison sampler",
+            texture.get_format(),
+        );
+        let texture_layout = &self.texture_layout;
+        self.bind_groups.entry(Arc::as_ptr(texture) as usize).or_insert_with(|| {
+            let bind_group = catengine.create_bind_group(wgpu::BindGroupDescriptor {
+                label: Some("sprite texture bind group"),
+                layout: texture_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(texture.get_view()),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(texture.get_sampler()),
+                    },
+                ],
+            });
+            (texture.clone(), bind_group)
+        }).1.clone()
+    }
+
+    fn pipeline(&mut self, catengine: &CatEngine, blend_mode: BlendMode, depth_format: Option<wgpu::TextureFormat>) -> Arc<Shader> {
+        let globals_layout = &self.globals_layout;
+        let texture_layout = &self.texture_layout;
+        self.pipelines.entry((blend_mode, depth_format)).or_insert_with(|| {
+            let shader = catengine.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Sprite Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("sprite.wgsl").into()),
+            });
+
+            let layout = catengine.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Sprite Pipeline Layout"),
+                bind_group_layouts: &[Some(globals_layout), Some(texture_layout)],
+                immediate_size: 0,
+            });
+
+            let render_pipeline = catengine.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Sprite Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Some(wgpu::VertexBufferLayout {
+                        array_stride: VERTEX_SIZE as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4],
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: catengine.config.format,
+                        blend: Some(blend_mode.blend_state()),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Cw,
+                    // Flipped sprites come out with the other winding.
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                // Sprites neither test nor write depth, they are ordered by layer.
+                depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                    format,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::Always),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview_mask: None,
+                cache: None,
+            });
+
+            Arc::new(Shader::from_pipeline(render_pipeline))
+        }).clone()
+    }
+}
@@ -1,18 +1,59 @@
-use wgpu::{Origin3d, Sampler, TextureAspect, TextureView};
-use crate::CatEngine;
+use image::{DynamicImage, imageops::FilterType};
+use std::sync::Arc;
+use wgpu::{Origin3d, Texture, TextureAspect, TextureSampleType, TextureView, TextureViewDimension, util::DeviceExt};
+use crate::{CatEngine, color::{linear_to_srgb, srgb_to_linear}, compressed::CompressedImage, sampler::{Sampler, SamplerPreset}};
 
 pub use wgpu::{TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor, SamplerDescriptor};
 
 enum WindowWidthHeightAttr {
     Dimension,
     Specific(u32, u32),
-    Config,
+    // Window size divided by the value, 1 keeps the full size.
+    Config(u32),
 }
 
 pub enum MultiplierValue {
     Numer(u32),
     MultiplierByWidth(u32),
     MultiplierByHeight(u32),
+    // Bytes per row (or rows per image) taken from the texel block size of the format.
+    FormatBlockSize,
+}
+
+impl MultiplierValue {
+    fn bytes_per_row(&self, dimensions: (u32, u32), format: TextureFormat) -> u32 {
+        match self {
+            MultiplierValue::Numer(v) => *v,
+            MultiplierValue::MultiplierByWidth(v) => v * dimensions.0,
+            MultiplierValue::MultiplierByHeight(v) => v * dimensions.1,
+            MultiplierValue::FormatBlockSize => row_bytes(dimensions.0, format),
+        }
+    }
+
+    fn rows_per_image(&self, dimensions: (u32, u32), format: TextureFormat) -> u32 {
+        match self {
+            MultiplierValue::FormatBlockSize => dimensions.1.div_ceil(format.block_dimensions().1),
+            other => other.bytes_per_row(dimensions, format),
+        }
+    }
+}
+
+// Where the sampler of a surface comes from: a one-off descriptor, the shared
+// sampler of a preset, or a sampler object the caller already has.
+enum SurfaceSampler {
+    Descriptor(SamplerDescriptor<'static>),
+    Preset(SamplerPreset),
+    Shared(Arc<Sampler>),
+}
+
+impl SurfaceSampler {
+    fn create(&self, catengine: &CatEngine) -> Arc<Sampler> {
+        match self {
+            SurfaceSampler::Descriptor(descriptor) => Arc::new(Sampler::new(catengine, descriptor)),
+            SurfaceSampler::Preset(preset) => catengine.get_sampler(*preset),
+            SurfaceSampler::Shared(sampler) => sampler.clone(),
+        }
+    }
 }
 
 pub struct SurfaceAttributes {
@@ -31,7 +72,8 @@ pub struct SurfaceAttributes {
     bytes_per_row: Option<MultiplierValue>,
     rows_per_image: MultiplierValue,
     texture_view_descriptor: TextureViewDescriptor<'static>,
-    sampler_descriptor: SamplerDescriptor<'static>,
+    sampler: SurfaceSampler,
+    generate_mipmaps: bool,
 }
 
 impl SurfaceAttributes {
@@ -49,10 +91,10 @@ impl SurfaceAttributes {
             origin: wgpu::Origin3d::ZERO,
             aspect: TextureAspect::All,
             offset: 0,
-            bytes_per_row: Some(MultiplierValue::MultiplierByWidth(4)),
-            rows_per_image: MultiplierValue::MultiplierByHeight(1),
+            bytes_per_row: Some(MultiplierValue::FormatBlockSize),
+            rows_per_image: MultiplierValue::FormatBlockSize,
             texture_view_descriptor: TextureViewDescriptor::default(),
-            sampler_descriptor: wgpu::SamplerDescriptor {
+            sampler: SurfaceSampler::Descriptor(wgpu::SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
                 address_mode_v: wgpu::AddressMode::ClampToEdge,
                 address_mode_w: wgpu::AddressMode::ClampToEdge,
@@ -60,47 +102,96 @@ impl SurfaceAttributes {
                 min_filter: wgpu::FilterMode::Nearest,
                 mipmap_filter: wgpu::MipmapFilterMode::Nearest,
                 ..Default::default()
-            }
+            }),
+            generate_mipmaps: false,
+        }
+    }
+
+    pub fn default_attributes_2d_pixel_art() -> Self {
+        Self {
+            sampler: SurfaceSampler::Preset(SamplerPreset::PixelArt),
+            ..Self::default_attributes_2d()
+        }
+    }
+
+    // Same as default_attributes_2d but the full mip chain gets generated and
+    // sampled with linear filtering between levels.
+    pub fn default_attributes_2d_trilinear() -> Self {
+        Self {
+            generate_mipmaps: true,
+            sampler: SurfaceSampler::Preset(SamplerPreset::Trilinear),
+            ..Self::default_attributes_2d()
         }
     }
     
+    // For Radiance .hdr / EXR skies and light probes, keeps values above 1.0.
+    // Rgba16Float stays filterable everywhere, unlike Rgba32Float.
+    pub fn default_attributes_hdr() -> Self {
+        Self {
+            format: TextureFormat::Rgba16Float,
+            label: Some("hdr texture"),
+            sampler: SurfaceSampler::Preset(SamplerPreset::Trilinear),
+            ..Self::default_attributes_2d()
+        }
+    }
+
+    // Meant for Surface::new_depth. The format can be switched with set_format to
+    // any of Depth32Float, Depth24PlusStencil8 or Depth16Unorm.
     pub fn default_attributes_depth() -> Self {
         Self {
-            width_height_attr: WindowWidthHeightAttr::Config,
+            width_height_attr: WindowWidthHeightAttr::Config(1),
             depth_or_array_layers: 1,
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
+            format: TextureFormat::Depth32Float,
             usages: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
-            label: Some("diffuse texture"),
+            label: Some("depth texture"),
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
             aspect: TextureAspect::All,
             offset: 0,
-            bytes_per_row: Some(MultiplierValue::MultiplierByWidth(4)),
+            bytes_per_row: None,
             rows_per_image: MultiplierValue::MultiplierByHeight(1),
             texture_view_descriptor: TextureViewDescriptor::default(),
-            sampler_descriptor: wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::ClampToEdge,
-                address_mode_v: wgpu::AddressMode::ClampToEdge,
-                address_mode_w: wgpu::AddressMode::ClampToEdge,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Linear,
-                mipmap_filter: wgpu::MipmapFilterMode::Nearest,
-                compare: Some(wgpu::CompareFunction::LessEqual), // 5.
-                lod_min_clamp: 0.0,
-                lod_max_clamp: 100.0,
-                ..Default::default()
-
-            }
+            sampler: SurfaceSampler::Preset(SamplerPreset::Comparison),
+            generate_mipmaps: false,
         }
     }
     
+    // Meant for Surface::new_render_target and CatEngine::create_render_target. Uses the
+    // window format so shaders made with Shader::new can draw into it.
+    pub fn default_attributes_render_target(catengine: &CatEngine) -> Self {
+        Self {
+            width_height_attr: WindowWidthHeightAttr::Config(1),
+            format: catengine.config.format,
+            usages: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            label: Some("render target"),
+            bytes_per_row: None,
+            sampler: SurfaceSampler::Preset(SamplerPreset::Linear),
+            ..Self::default_attributes_2d()
+        }
+    }
+
+    pub fn default_attributes_depth_stencil() -> Self {
+        Self {
+            format: TextureFormat::Depth24PlusStencil8,
+            label: Some("depth stencil texture"),
+            // Only the depth aspect can be sampled, render passes use get_attachment_view.
+            texture_view_descriptor: TextureViewDescriptor {
+                aspect: TextureAspect::DepthOnly,
+                ..Default::default()
+            },
+            ..Self::default_attributes_depth()
+        }
+    }
+
     pub fn set_width_height_to_specific(&mut self, width: u32, height: u32) { self.width_height_attr = WindowWidthHeightAttr::Specific(width, height); }
-    pub fn set_width_height_to_config(&mut self) { self.width_height_attr = WindowWidthHeightAttr::Config; }
+    pub fn set_width_height_to_config(&mut self) { self.width_height_attr = WindowWidthHeightAttr::Config(1); }
+    pub fn set_width_height_to_config_divided(&mut self, divisor: u32) { self.width_height_attr = WindowWidthHeightAttr::Config(divisor.max(1)); }
     pub fn set_width_height_to_dimension(&mut self) { self.width_height_attr = WindowWidthHeightAttr::Dimension; }
     pub fn set_depth_or_array_layers(&mut self, depth_or_array_layers: u32) { self.depth_or_array_layers = depth_or_array_layers; }
+    // Images get the levels past the first generated, empty surfaces leave them to the GPU.
     pub fn set_mip_level_count(&mut self, mip_level_count: u32) { self.mip_level_count = mip_level_count; }
     pub fn set_sample_count(&mut self, sample_count: u32) { self.sample_count = sample_count; }
     pub fn set_dimension(&mut self, dimension: TextureDimension) { self.dimension = dimension; }
@@ -114,18 +205,49 @@ impl SurfaceAttributes {
     pub fn set_bytes_per_row(&mut self, bytes_per_row: Option<MultiplierValue>) { self.bytes_per_row = bytes_per_row; }
     pub fn set_rows_per_image(&mut self, rows_per_image: MultiplierValue) { self.rows_per_image = rows_per_image; }
     pub fn set_texture_view_descriptor(&mut self, texture_view_descriptor: TextureViewDescriptor<'static>) { self.texture_view_descriptor = texture_view_descriptor; }
+    pub fn set_sampler_descriptor(&mut self, sampler_descriptor: SamplerDescriptor<'static>) { self.sampler = SurfaceSampler::Descriptor(sampler_descriptor); }
+    pub fn set_sampler_preset(&mut self, preset: SamplerPreset) { self.sampler = SurfaceSampler::Preset(preset); }
+    pub fn set_sampler(&mut self, sampler: Arc<Sampler>) { self.sampler = SurfaceSampler::Shared(sampler); }
+    // Shared samplers aren't touched, the surface gets its own copy with the compare function.
+    pub fn set_sampler_compare(&mut self, compare: Option<wgpu::CompareFunction>) {
+        let mut descriptor = match &self.sampler {
+            SurfaceSampler::Descriptor(descriptor) => descriptor.clone(),
+            SurfaceSampler::Preset(preset) => preset.descriptor(),
+            SurfaceSampler::Shared(sampler) => sampler.get_descriptor().clone(),
+        };
+        descriptor.compare = compare;
+        self.sampler = SurfaceSampler::Descriptor(descriptor);
+    }
+    // When the mip level count is left at 1, the whole chain down to 1x1 is generated.
+    pub fn set_generate_mipmaps(&mut self, generate_mipmaps: bool) { self.generate_mipmaps = generate_mipmaps; }
 }
 
 pub struct Surface {
+    texture: Texture,
+    texture_descriptor: wgpu::TextureDescriptor<'static>,
+    texture_view_descriptor: TextureViewDescriptor<'static>,
     view: TextureView,
-    sampler: Sampler,
+    // Depth/stencil textures are sampled through a single aspect view, attachments need all of them.
+    attachment_view: Option<TextureView>,
+    // How shaders see the texture on this device, 32 bit floats are only filterable with
+    // Features::FLOAT32_FILTERABLE.
+    sample_type: TextureSampleType,
+    sampler: Arc<Sampler>,
+    // Set when the size follows the window, holds the divisor applied to it.
+    config_divisor: Option<u32>,
 }
 
 impl Surface {
+    // The pixels are converted to whatever format the attributes ask for, so HDR,
+    // EXR and 16-bit files keep their precision with a float or 16-bit format.
     pub fn new(file: &str, catengine: &CatEngine, args: SurfaceAttributes) -> Self {
-        let diffuse_image = image::open(file).unwrap().to_rgba8();
+        Self::from_image(image::open(file).unwrap(), catengine, args)
+    }
 
-        let dimensions = diffuse_image.dimensions();
+    // Same as new but for an image that is already in memory.
+    pub fn from_image(diffuse_image: DynamicImage, catengine: &CatEngine, mut args: SurfaceAttributes) -> Self {
+        args.format = supported_format(catengine, args.format);
+        let dimensions = (diffuse_image.width(), diffuse_image.height());
 
 
         let texture_size = match args.width_height_attr {
@@ -143,34 +265,43 @@ impl Surface {
                     depth_or_array_layers: args.depth_or_array_layers,
                 }
             }
-            WindowWidthHeightAttr::Config => {
-                 wgpu::Extent3d {
-                    width: catengine.config.width.max(1),
-                    height: catengine.config.height.max(1),
+            WindowWidthHeightAttr::Config(divisor) => {
+                let (width, height) = config_size(catengine, divisor);
+                wgpu::Extent3d {
+                    width,
+                    height,
                     depth_or_array_layers: args.depth_or_array_layers,
                 }
             }
         };
 
-        let diffuse_texture = catengine.device.create_texture(
-            &wgpu::TextureDescriptor {
-                size: texture_size,
-                mip_level_count: args.mip_level_count,
-                sample_count: args.sample_count,
-                dimension: args.dimension,
-                format: args.format,
-                usage: args.usages,
-                label: args.label,
-                // This is the same as with the SurfaceConfig. It
-                // specifies what texture formats can be used to
-                // create TextureViews for this texture. The base
-                // texture format (Rgba8UnormSrgb in this case) is
-                // always supported. Note that using a different
-                // texture format is not supported on the WebGL2
-                // backend.
-                view_formats: &[],
-            }
-        );
+        // Levels past the first are always generated, nothing else would fill them.
+        let generate_mipmaps = args.generate_mipmaps || args.mip_level_count > 1;
+        let mip_level_count = if generate_mipmaps {
+            let full_chain = full_mip_chain_length(texture_size.width, texture_size.height);
+            if args.mip_level_count > 1 { args.mip_level_count.min(full_chain) } else { full_chain }
+        } else {
+            args.mip_level_count
+        };
+
+        let texture_descriptor = wgpu::TextureDescriptor {
+            size: texture_size,
+            mip_level_count,
+            sample_count: args.sample_count,
+            dimension: args.dimension,
+            format: args.format,
+            usage: args.usages,
+            label: args.label,
+            // This is the same as with the SurfaceConfig. It
+            // specifies what texture formats can be used to
+            // create TextureViews for this texture. The base
+            // texture format (Rgba8UnormSrgb in this case) is
+            // always supported. Note that using a different
+            // texture format is not supported on the WebGL2
+            // backend.
+            view_formats: &[],
+        };
+        let diffuse_texture = catengine.device.create_texture(&texture_descriptor);
         
 
         catengine.queue.write_texture(
@@ -182,39 +313,541 @@ impl Surface {
                 aspect: args.aspect,
             },
             // The actual pixel data
-            &diffuse_image.as_raw(),
+            &image_bytes(&diffuse_image, args.format),
             // The layout of the texture
             wgpu::TexelCopyBufferLayout {
                 offset: args.offset,
-                bytes_per_row: match args.bytes_per_row {
-                    None => {None},
-                    Some(v) => match v {
-                        MultiplierValue::Numer(v) => Some(v),
-                        MultiplierValue::MultiplierByHeight(v) => { Some(v * dimensions.1) }
-                        MultiplierValue::MultiplierByWidth(v) => { Some(v * dimensions.0) }
-                    }
-                },
-                rows_per_image: match args.rows_per_image {
-                        MultiplierValue::Numer(v) => Some(v),
-                        MultiplierValue::MultiplierByHeight(v) => { Some(v * dimensions.1) }
-                        MultiplierValue::MultiplierByWidth(v) => { Some(v * dimensions.0) }
-                    }
+                bytes_per_row: args.bytes_per_row.map(|v| v.bytes_per_row(dimensions, args.format)),
+                rows_per_image: Some(args.rows_per_image.rows_per_image(dimensions, args.format)),
             },
             
             texture_size,
         );
 
+        if generate_mipmaps {
+            let size = (texture_size.width, texture_size.height);
+            write_generated_mips(catengine, &diffuse_texture, diffuse_image, 0, size, args.mip_level, mip_level_count, args.aspect, args.format);
+        }
+
         let diffuse_texture_view = diffuse_texture.create_view(&args.texture_view_descriptor);
-        let diffuse_sampler = catengine.device.create_sampler(&args.sampler_descriptor);
+        let diffuse_sampler = args.sampler.create(catengine);
+        let sample_type = sample_type(catengine, args.format, args.texture_view_descriptor.aspect);
+
+        Self {
+            texture: diffuse_texture,
+            texture_descriptor,
+            texture_view_descriptor: args.texture_view_descriptor,
+            view: diffuse_texture_view,
+            sampler: diffuse_sampler,
+            attachment_view: None,
+            sample_type,
+            config_divisor: None,
+        }
+    }
+
+    // Loads every file into its own layer of a 2D array texture. All images need to
+    // share the same size, the width/height mode of the attributes is ignored.
+    pub fn new_array(files: &[&str], catengine: &CatEngine, args: SurfaceAttributes) -> Self {
+        let layers = files.iter().map(|file| image::open(file).unwrap()).collect();
+        Self::from_layers(layers, catengine, args, TextureDimension::D2, TextureViewDimension::D2Array)
+    }
+
+    // Faces are expected in the +X, -X, +Y, -Y, +Z, -Z order.
+    pub fn new_cube(files: &[&str; 6], catengine: &CatEngine, args: SurfaceAttributes) -> Self {
+        let faces = files.iter().map(|file| image::open(file).unwrap()).collect();
+        Self::from_layers(faces, catengine, args, TextureDimension::D2, TextureViewDimension::Cube)
+    }
+
+    // Reads all six faces out of one image. The layout is picked from the aspect ratio:
+    // 4:3 horizontal cross, 3:4 vertical cross, 6:1 horizontal strip or 1:6 vertical strip.
+    pub fn new_cube_from_layout(file: &str, catengine: &CatEngine, args: SurfaceAttributes) -> Self {
+        let image = image::open(file).unwrap();
+        let faces = split_cube_layout(&image);
+        Self::from_layers(faces, catengine, args, TextureDimension::D2, TextureViewDimension::Cube)
+    }
 
-        Self{ view: diffuse_texture_view, sampler: diffuse_sampler }
+    // Every file is one depth slice of a 3D texture, from front to back. 3D surfaces have no
+    // mipmaps, asking for them gives a single level.
+    pub fn new_3d(files: &[&str], catengine: &CatEngine, args: SurfaceAttributes) -> Self {
+        let slices = files.iter().map(|file| image::open(file).unwrap()).collect();
+        Self::from_layers(slices, catengine, args, TextureDimension::D3, TextureViewDimension::D3)
+    }
+
+    fn from_layers(layers: Vec<DynamicImage>, catengine: &CatEngine, mut args: SurfaceAttributes, dimension: TextureDimension, view_dimension: TextureViewDimension) -> Self {
+        args.format = supported_format(catengine, args.format);
+        assert!(!layers.is_empty(), "a layered surface needs at least one image");
+        let (width, height) = (layers[0].width(), layers[0].height());
+        assert!(layers.iter().all(|layer| (layer.width(), layer.height()) == (width, height)), "all layers of a surface need the same size");
+        if view_dimension == TextureViewDimension::Cube {
+            assert!(layers.len() == 6, "a cube map needs 6 faces, got {}", layers.len());
+            assert!(width == height, "cube map faces need to be square, got {}x{}", width, height);
+        }
+
+        // Mips of a 3D texture also shrink in depth, so they are only generated for layered 2D
+        // textures and 3D surfaces get a single level. Levels past the first are always
+        // generated, nothing else would fill them.
+        if dimension == TextureDimension::D3 && (args.generate_mipmaps || args.mip_level_count > 1) {
+            log::warn!("mipmaps can't be generated for 3D surfaces, using a single level");
+            args.generate_mipmaps = false;
+            args.mip_level_count = 1;
+        }
+        let generate_mipmaps = args.generate_mipmaps || args.mip_level_count > 1;
+        let mip_level_count = if generate_mipmaps {
+            let full_chain = full_mip_chain_length(width, height);
+            if args.mip_level_count > 1 { args.mip_level_count.min(full_chain) } else { full_chain }
+        } else {
+            args.mip_level_count
+        };
+
+        let texture_descriptor = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: layers.len() as u32,
+            },
+            mip_level_count,
+            sample_count: args.sample_count,
+            dimension,
+            format: args.format,
+            usage: args.usages,
+            label: args.label,
+            view_formats: &[],
+        };
+        let texture = catengine.device.create_texture(&texture_descriptor);
+
+        for (layer, image) in layers.into_iter().enumerate() {
+            catengine.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                    aspect: args.aspect,
+                },
+                &image_bytes(&image, args.format),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row_bytes(width, args.format)),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            if generate_mipmaps {
+                write_generated_mips(catengine, &texture, image, layer as u32, (width, height), 0, mip_level_count, args.aspect, args.format);
+            }
+        }
+
+        let texture_view_descriptor = TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..args.texture_view_descriptor
+        };
+        let view = texture.create_view(&texture_view_descriptor);
+        let sampler = args.sampler.create(catengine);
+        let sample_type = sample_type(catengine, args.format, texture_view_descriptor.aspect);
+
+        Self {
+            texture,
+            texture_descriptor,
+            texture_view_descriptor,
+            view,
+            sampler,
+            attachment_view: None,
+            sample_type,
+            config_divisor: None,
+        }
+    }
+
+    // Uploads a KTX2 or DDS file with all of its stored mip levels. Size, format and
+    // mip count come from the file, the rest of the attributes still apply.
+    pub fn new_compressed(file: &str, catengine: &CatEngine, args: SurfaceAttributes) -> Result<Self, anyhow::Error> {
+        let mut image = CompressedImage::open(file)?;
+        if !image.is_supported_by(catengine.device.features()) {
+            image = image.decompress()?;
+        }
+
+        let texture_descriptor = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: image.width,
+                height: image.height,
+                depth_or_array_layers: image.depth_or_array_layers,
+            },
+            mip_level_count: image.mip_level_count,
+            sample_count: 1,
+            dimension: image.dimension,
+            format: image.format,
+            usage: args.usages,
+            label: args.label,
+            view_formats: &[],
+        };
+        let texture = catengine.device.create_texture_with_data(&catengine.queue, &texture_descriptor, image.order, &image.data);
+
+        let view_dimension = match image.dimension {
+            TextureDimension::D3 => TextureViewDimension::D3,
+            _ if image.is_cube && image.depth_or_array_layers > 6 => TextureViewDimension::CubeArray,
+            _ if image.is_cube => TextureViewDimension::Cube,
+            _ if image.depth_or_array_layers > 1 => TextureViewDimension::D2Array,
+            _ => TextureViewDimension::D2,
+        };
+        let texture_view_descriptor = TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..args.texture_view_descriptor
+        };
+        let view = texture.create_view(&texture_view_descriptor);
+        let sampler = args.sampler.create(catengine);
+        let sample_type = sample_type(catengine, image.format, texture_view_descriptor.aspect);
+
+        Ok(Self {
+            texture,
+            texture_descriptor,
+            texture_view_descriptor,
+            view,
+            sampler,
+            attachment_view: None,
+            sample_type,
+            config_divisor: None,
+        })
+    }
+
+    // Creates an empty depth (or depth/stencil) texture, there is no image to read
+    // so the Dimension size mode falls back to the Config one. Depth targets for render
+    // passes are best made through CatEngine::create_depth_target so they follow resizes.
+    pub fn new_depth(catengine: &CatEngine, args: SurfaceAttributes) -> Self {
+        assert!(args.format.is_depth_stencil_format(), "{:?} is not a depth format", args.format);
+        Self::empty(catengine, args)
+    }
+
+    // Creates a texture that draw commands can render into and shaders can sample
+    // afterwards. Window sized targets are best made through CatEngine::create_render_target
+    // so they get recreated on resize.
+    pub fn new_render_target(catengine: &CatEngine, args: SurfaceAttributes) -> Self {
+        assert!(args.usages.contains(TextureUsages::RENDER_ATTACHMENT), "a render target needs TextureUsages::RENDER_ATTACHMENT");
+        Self::empty(catengine, args)
+    }
+
+    fn empty(catengine: &CatEngine, mut args: SurfaceAttributes) -> Self {
+        args.format = supported_format(catengine, args.format);
+        let (width, height, config_divisor) = match args.width_height_attr {
+            WindowWidthHeightAttr::Specific(width, height) => (width, height, None),
+            WindowWidthHeightAttr::Config(divisor) => {
+                let (width, height) = config_size(catengine, divisor);
+                (width, height, Some(divisor))
+            }
+            WindowWidthHeightAttr::Dimension => {
+                let (width, height) = config_size(catengine, 1);
+                (width, height, Some(1))
+            }
+        };
+
+        let texture_descriptor = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: args.depth_or_array_layers,
+            },
+            mip_level_count: args.mip_level_count,
+            sample_count: args.sample_count,
+            dimension: args.dimension,
+            format: args.format,
+            usage: args.usages,
+            label: args.label,
+            view_formats: &[],
+        };
+        let texture = catengine.device.create_texture(&texture_descriptor);
+        let view = texture.create_view(&args.texture_view_descriptor);
+        let attachment_view = create_attachment_view(&texture, &args.texture_view_descriptor);
+        let sampler = args.sampler.create(catengine);
+        let sample_type = sample_type(catengine, args.format, args.texture_view_descriptor.aspect);
+
+        Self {
+            texture,
+            texture_descriptor,
+            texture_view_descriptor: args.texture_view_descriptor,
+            view,
+            attachment_view,
+            sample_type,
+            sampler,
+            config_divisor,
+        }
+    }
+
+    // Recreates the texture when it is sized after the window and the window changed.
+    // Bind groups made from the old view have to be rebuilt by the caller.
+    pub fn resize(&mut self, catengine: &CatEngine) -> bool {
+        match self.resized(catengine) {
+            Some(resized) => {
+                *self = resized;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Same as resize, but leaves this surface alone for whoever still holds it.
+    pub(crate) fn resized(&self, catengine: &CatEngine) -> Option<Self> {
+        let divisor = self.config_divisor?;
+        let (width, height) = config_size(catengine, divisor);
+        if self.texture_descriptor.size.width == width && self.texture_descriptor.size.height == height {
+            return None;
+        }
+        Some(self.with_new_texture(catengine, self.sampler.clone()))
+    }
+
+    // Makes the texture again on the engine's current device, see Program::device_recreated.
+    // The contents are lost, surfaces made from images have to be loaded again instead.
+    pub fn recreate(&mut self, catengine: &CatEngine) {
+        *self = self.recreated(catengine);
+    }
+
+    // Same as recreate, but leaves this surface alone for whoever still holds it.
+    pub(crate) fn recreated(&self, catengine: &CatEngine) -> Self {
+        self.with_new_texture(catengine, Arc::new(self.sampler.recreate(catengine)))
+    }
+
+    fn with_new_texture(&self, catengine: &CatEngine, sampler: Arc<Sampler>) -> Self {
+        let mut texture_descriptor = self.texture_descriptor.clone();
+        if let Some(divisor) = self.config_divisor {
+            (texture_descriptor.size.width, texture_descriptor.size.height) = config_size(catengine, divisor);
+        }
+        let texture = catengine.device.create_texture(&texture_descriptor);
+        let sample_type = sample_type(catengine, texture_descriptor.format, self.texture_view_descriptor.aspect);
+
+        Self {
+            view: texture.create_view(&self.texture_view_descriptor),
+            attachment_view: create_attachment_view(&texture, &self.texture_view_descriptor),
+            texture,
+            texture_descriptor,
+            texture_view_descriptor: self.texture_view_descriptor.clone(),
+            sample_type,
+            sampler,
+            config_divisor: self.config_divisor,
+        }
+    }
+
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn get_format(&self) -> TextureFormat {
+        self.texture_descriptor.format
+    }
+
+    pub fn get_aspect(&self) -> Option<TextureAspect> {
+        Some(self.texture_view_descriptor.aspect)
+    }
+
+    pub fn get_sample_type(&self) -> TextureSampleType {
+        self.sample_type
+    }
+
+    pub fn get_sample_count(&self) -> u32 {
+        self.texture_descriptor.sample_count
+    }
+
+    // Same dimension wgpu picks when the view descriptor leaves it out.
+    pub fn get_view_dimension(&self) -> TextureViewDimension {
+        self.texture_view_descriptor.dimension.unwrap_or(match self.texture_descriptor.dimension {
+            TextureDimension::D1 => TextureViewDimension::D1,
+            TextureDimension::D2 if self.texture_descriptor.size.depth_or_array_layers > 1 => TextureViewDimension::D2Array,
+            TextureDimension::D2 => TextureViewDimension::D2,
+            TextureDimension::D3 => TextureViewDimension::D3,
+        })
     }
 
     pub fn get_view(&self) -> &TextureView {
         &self.view
     }
 
-    pub fn get_sampler(&self) -> &Sampler {
-        &self.sampler
+    // The view to use as a render pass attachment, covers depth and stencil together.
+    pub fn get_attachment_view(&self) -> &TextureView {
+        self.attachment_view.as_ref().unwrap_or(&self.view)
+    }
+
+    pub fn get_sampler(&self) -> &wgpu::Sampler {
+        self.sampler.get_sampler()
+    }
+
+    pub fn get_shared_sampler(&self) -> Arc<Sampler> {
+        self.sampler.clone()
+    }
+}
+
+fn sample_type(catengine: &CatEngine, format: TextureFormat, aspect: TextureAspect) -> TextureSampleType {
+    format.sample_type(Some(aspect), Some(catengine.device.features()))
+        .unwrap_or(if format.has_depth_aspect() { TextureSampleType::Depth } else { TextureSampleType::Float { filterable: true } })
+}
+
+fn create_attachment_view(texture: &Texture, descriptor: &TextureViewDescriptor<'static>) -> Option<TextureView> {
+    if descriptor.aspect == wgpu::TextureAspect::All {
+        return None;
+    }
+    Some(texture.create_view(&TextureViewDescriptor { aspect: wgpu::TextureAspect::All, ..descriptor.clone() }))
+}
+
+fn config_size(catengine: &CatEngine, divisor: u32) -> (u32, u32) {
+    ((catengine.width / divisor).max(1), (catengine.height / divisor).max(1))
+}
+
+fn full_mip_chain_length(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+// Every level after first_level is downsampled from the one above it on the CPU. size is
+// the size of level 0 of the texture, which can differ from the image.
+#[allow(clippy::too_many_arguments)]
+fn write_generated_mips(catengine: &CatEngine, texture: &Texture, base: DynamicImage, layer: u32, size: (u32, u32), first_level: u32, mip_level_count: u32, aspect: TextureAspect, format: TextureFormat) {
+    for (level, mip) in generate_mips(base, size, first_level, mip_level_count, format.is_srgb()) {
+        let (width, height) = (mip.width(), mip.height());
+        catengine.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: level,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                aspect,
+            },
+            &image_bytes(&mip, format),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(row_bytes(width, format)),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+// sRGB images are filtered in linear space, averaging the encoded values darkens them.
+fn generate_mips(base: DynamicImage, size: (u32, u32), first_level: u32, mip_level_count: u32, srgb: bool) -> Vec<(u32, DynamicImage)> {
+    let mut previous = if srgb { map_color_channels(base, srgb_to_linear) } else { base };
+    ((first_level + 1)..mip_level_count).map(|level| {
+        let width = (size.0 >> level).max(1);
+        let height = (size.1 >> level).max(1);
+        previous = previous.resize_exact(width, height, FilterType::Triangle);
+        (level, if srgb { map_color_channels(previous.clone(), linear_to_srgb) } else { previous.clone() })
+    }).collect()
+}
+
+fn map_color_channels(image: DynamicImage, map: fn(f32) -> f32) -> DynamicImage {
+    let mut image = image.into_rgba32f();
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = map(*channel);
+        }
+    }
+    DynamicImage::ImageRgba32F(image)
+}
+
+fn row_bytes(width: u32, format: TextureFormat) -> u32 {
+    let block_size = format.block_copy_size(None).unwrap_or_else(|| panic!("{:?} has no single block size", format));
+    width.div_ceil(format.block_dimensions().0) * block_size
+}
+
+// Converts the decoded image into the texel layout of the texture format.
+// 16 bit normalized formats need Features::TEXTURE_FORMAT_16BIT_NORM, without it they
+// fall back to half floats of the same precision.
+fn supported_format(catengine: &CatEngine, format: TextureFormat) -> TextureFormat {
+    if catengine.device.features().contains(format.required_features()) {
+        return format;
+    }
+    let fallback = match format {
+        TextureFormat::R16Unorm | TextureFormat::R16Snorm => TextureFormat::R16Float,
+        TextureFormat::Rg16Unorm | TextureFormat::Rg16Snorm => TextureFormat::Rg16Float,
+        TextureFormat::Rgba16Unorm | TextureFormat::Rgba16Snorm => TextureFormat::Rgba16Float,
+        other => return other,
+    };
+    log::warn!("{:?} is not supported by the device, using {:?} instead", format, fallback);
+    fallback
+}
+
+fn image_bytes(image: &DynamicImage, format: TextureFormat) -> Vec<u8> {
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image.to_rgba8().into_raw(),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            image.to_rgba8().pixels().flat_map(|p| [p[2], p[1], p[0], p[3]]).collect()
+        }
+        TextureFormat::R8Unorm => image.to_luma8().into_raw(),
+        TextureFormat::Rgba16Unorm => image.to_rgba16().iter().flat_map(|v| v.to_le_bytes()).collect(),
+        TextureFormat::Rgba16Float => image.to_rgba32f().iter().flat_map(|v| half::f16::from_f32(*v).to_le_bytes()).collect(),
+        TextureFormat::Rgba32Float => image.to_rgba32f().iter().flat_map(|v| v.to_le_bytes()).collect(),
+        TextureFormat::R32Float => image.to_luma32f().iter().flat_map(|v| v.to_le_bytes()).collect(),
+        other => panic!("{:?} can't be loaded from an image file", other),
+    }
+}
+
+fn split_cube_layout(image: &DynamicImage) -> Vec<DynamicImage> {
+    let (width, height) = (image.width(), image.height());
+    // Cells (column, row) of the +X, -X, +Y, -Y, +Z, -Z faces.
+    let (face_size, cells, flip_last) = if width * 3 == height * 4 {
+        (width / 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)], false)
+    } else if width * 4 == height * 3 {
+        // The -Z face of a vertical cross sits upside down below -Y.
+        (width / 3, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)], true)
+    } else if width == height * 6 {
+        (height, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)], false)
+    } else if width * 6 == height {
+        (width, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)], false)
+    } else {
+        panic!("{}x{} is not a cube cross or strip layout", width, height);
+    };
+
+    cells.iter().enumerate().map(|(face, (column, row))| {
+        let face_image = image.crop_imm(column * face_size, row * face_size, face_size, face_size);
+        if flip_last && face == 5 { face_image.rotate180() } else { face_image }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn checkerboard(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            if (x + y) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+        }))
+    }
+
+    #[test]
+    fn mips_follow_the_texture_size() {
+        // A 64x32 image in a 16x16 texture.
+        let sizes: Vec<_> = generate_mips(checkerboard(64, 32), (16, 16), 0, 5, false).into_iter()
+            .map(|(level, mip)| (level, mip.width(), mip.height()))
+            .collect();
+        assert_eq!(sizes, [(1, 8, 8), (2, 4, 4), (3, 2, 2), (4, 1, 1)]);
+
+        let sizes: Vec<_> = generate_mips(checkerboard(16, 4), (16, 4), 0, 5, false).into_iter()
+            .map(|(level, mip)| (level, mip.width(), mip.height()))
+            .collect();
+        assert_eq!(sizes, [(1, 8, 2), (2, 4, 1), (3, 2, 1), (4, 1, 1)]);
+    }
+
+    #[test]
+    fn mips_start_after_the_first_level() {
+        let levels: Vec<_> = generate_mips(checkerboard(8, 8), (32, 32), 2, 6, false).into_iter()
+            .map(|(level, mip)| (level, mip.width()))
+            .collect();
+        assert_eq!(levels, [(3, 4), (4, 2), (5, 1)]);
+        assert!(generate_mips(checkerboard(8, 8), (8, 8), 3, 4, false).is_empty());
+    }
+
+    #[test]
+    fn srgb_mips_are_filtered_in_linear_space() {
+        let average = |srgb| {
+            let (_, mip) = generate_mips(checkerboard(2, 2), (2, 2), 0, 2, srgb).pop().unwrap();
+            mip.to_rgba8().get_pixel(0, 0).0
+        };
+        // Half white in linear light is about 188 once encoded, averaging the bytes gives 128.
+        let [r, g, b, a] = average(true);
+        assert!((186..=190).contains(&r) && r == g && g == b, "{:?}", [r, g, b]);
+        assert_eq!(a, 255);
+        let [r, ..] = average(false);
+        assert!((127..=128).contains(&r), "{}", r);
     }
 }
@@ -0,0 +1,171 @@
+use std::time::Instant;
+
+// Frame timing the engine updates right before Program::update. All durations are in seconds.
+pub struct Time {
+    last_frame: Instant,
+    delta: f64,
+    elapsed: f64,
+    frame_count: u64,
+    fps: f64,
+    // Deltas above this are cut down so a breakpoint or a dragged window doesn't make
+    // the simulation jump ahead.
+    max_delta: f64,
+    fixed_timestep: f64,
+    accumulator: f64,
+    fixed_step_count: u64,
+    max_fixed_steps: u32,
+}
+
+impl Time {
+    pub(crate) fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            last_frame: now,
+            delta: 0.0,
+            elapsed: 0.0,
+            frame_count: 0,
+            fps: 0.0,
+            max_delta: 0.25,
+            fixed_timestep: 1.0 / 60.0,
+            accumulator: 0.0,
+            fixed_step_count: 0,
+            max_fixed_steps: 8,
+        }
+    }
+
+    // Starts a frame and returns how many fixed updates have to run before it.
+    pub(crate) fn begin_frame(&mut self) -> u32 {
+        let now = Instant::now();
+        let delta = now.duration_since(self.last_frame).as_secs_f64();
+        self.last_frame = now;
+        self.advance(delta)
+    }
+
+    // begin_frame with the time since the previous frame passed in.
+    pub(crate) fn advance(&mut self, delta: f64) -> u32 {
+        self.delta = delta.min(self.max_delta);
+        self.elapsed += delta;
+        self.frame_count += 1;
+
+        if self.delta > 0.0 {
+            // Exponential moving average so the number stays readable.
+            let fps = 1.0 / self.delta;
+            self.fps = if self.fps == 0.0 { fps } else { self.fps * 0.9 + fps * 0.1 };
+        }
+
+        self.accumulator += self.delta;
+        let steps = (self.accumulator / self.fixed_timestep) as u32;
+        self.accumulator -= steps as f64 * self.fixed_timestep;
+        if steps > self.max_fixed_steps {
+            // Too far behind to catch up, drop the extra steps instead of spiraling.
+            self.accumulator = 0.0;
+        }
+        steps.min(self.max_fixed_steps)
+    }
+
+    pub(crate) fn end_fixed_step(&mut self) {
+        self.fixed_step_count += 1;
+    }
+
+    // Time since the previous frame.
+    pub fn get_delta(&self) -> f64 {
+        self.delta
+    }
+
+    // Time since the engine started.
+    pub fn get_elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // Smoothed frames per second.
+    pub fn get_fps(&self) -> f64 {
+        self.fps
+    }
+
+    pub fn get_fixed_timestep(&self) -> f64 {
+        self.fixed_timestep
+    }
+
+    pub fn get_fixed_step_count(&self) -> u64 {
+        self.fixed_step_count
+    }
+
+    // How far the frame is between the last fixed update and the next one, from 0 to 1.
+    // Render with previous.lerp(current, alpha) to hide the fixed rate.
+    pub fn get_alpha(&self) -> f64 {
+        self.accumulator / self.fixed_timestep
+    }
+
+    pub fn set_fixed_timestep(&mut self, fixed_timestep: f64) { self.fixed_timestep = fixed_timestep.max(1e-4); }
+    pub fn set_max_delta(&mut self, max_delta: f64) { self.max_delta = max_delta; }
+    pub fn set_max_fixed_steps(&mut self, max_fixed_steps: u32) { self.max_fixed_steps = max_fixed_steps; }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn fixed_steps_and_alpha() {
+        let mut time = Time::new();
+        time.set_fixed_timestep(0.01);
+        assert_eq!(time.advance(0.035), 3);
+        assert_close(time.get_alpha(), 0.5);
+        // The leftover carries into the next frame.
+        assert_eq!(time.advance(0.006), 1);
+        assert_close(time.get_alpha(), 0.1);
+        assert_eq!(time.advance(0.0), 0);
+        assert_eq!(time.get_frame_count(), 3);
+    }
+
+    #[test]
+    fn extra_fixed_steps_are_dropped() {
+        let mut time = Time::new();
+        time.set_fixed_timestep(0.01);
+        time.set_max_fixed_steps(2);
+        assert_eq!(time.advance(0.1), 2);
+        assert_eq!(time.get_alpha(), 0.0);
+        assert_eq!(time.advance(0.015), 1);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut time = Time::new();
+        time.set_fixed_timestep(0.1);
+        time.set_max_delta(0.25);
+        assert_eq!(time.advance(1.0), 2);
+        assert_eq!(time.get_delta(), 0.25);
+        assert_close(time.get_alpha(), 0.5);
+        // Elapsed time still follows the clock.
+        assert_eq!(time.get_elapsed(), 1.0);
+    }
+
+    #[test]
+    fn fps_is_a_moving_average() {
+        let mut time = Time::new();
+        time.advance(0.01);
+        assert_close(time.get_fps(), 100.0);
+        time.advance(0.02);
+        assert_close(time.get_fps(), 95.0);
+        // Empty frames leave it alone.
+        time.advance(0.0);
+        assert_close(time.get_fps(), 95.0);
+    }
+
+    #[test]
+    fn fixed_timestep_has_a_floor() {
+        let mut time = Time::new();
+        time.set_fixed_timestep(0.0);
+        assert_eq!(time.get_fixed_timestep(), 1e-4);
+        time.set_fixed_timestep(-1.0);
+        assert_eq!(time.get_fixed_timestep(), 1e-4);
+    }
+}
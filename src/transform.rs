@@ -0,0 +1,337 @@
+use crate::math::{Mat4, Quat, Vec2, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self { translation, ..Self::IDENTITY }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self { rotation, ..Self::IDENTITY }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self { scale, ..Self::IDENTITY }
+    }
+
+    // 2D transforms live on the z = 0 plane and rotate around z.
+    pub fn from_2d(position: Vec2, angle: f32, scale: Vec2) -> Self {
+        Self {
+            translation: position.extend(0.0),
+            rotation: Quat::from_rotation_z(angle),
+            scale: scale.extend(1.0),
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn get_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.rotation * (point * self.scale) + self.translation
+    }
+
+    // Applies other first, then self, like parent * child. Only exact when the parent
+    // scale is uniform, use the matrices otherwise.
+    pub fn mul_transform(&self, other: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(other.translation),
+            rotation: self.rotation * other.rotation,
+            scale: self.scale * other.scale,
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * -Vec3::Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+}
+
+// Handle to a node of a TransformTree. Handles of removed nodes stop working even if
+// the slot gets reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransformId {
+    index: usize,
+    generation: u32,
+}
+
+struct TransformNode {
+    local: Transform,
+    parent: Option<TransformId>,
+    children: Vec<TransformId>,
+    world: Mat4,
+    // When a node is dirty all of its descendants are too.
+    dirty: bool,
+}
+
+// Parent/child hierarchy of transforms. World matrices are cached and only recomputed
+// for nodes that moved, or whose ancestors moved, since they were last read.
+#[derive(Default)]
+pub struct TransformTree {
+    nodes: Vec<Option<TransformNode>>,
+    generations: Vec<u32>,
+    free: Vec<usize>,
+}
+
+impl TransformTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, local: Transform, parent: Option<TransformId>) -> TransformId {
+        let node = TransformNode {
+            local,
+            parent: None,
+            children: vec![],
+            world: Mat4::IDENTITY,
+            dirty: true,
+        };
+
+        let id = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                TransformId { index, generation: self.generations[index] }
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.generations.push(0);
+                TransformId { index: self.nodes.len() - 1, generation: 0 }
+            }
+        };
+
+        if parent.is_some() {
+            self.set_parent(id, parent);
+        }
+        id
+    }
+
+    // Removes the node together with all of its children.
+    pub fn remove(&mut self, id: TransformId) {
+        self.set_parent(id, None);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.nodes[id.index].take().unwrap();
+            stack.extend(node.children);
+            self.generations[id.index] += 1;
+            self.free.push(id.index);
+        }
+    }
+
+    pub fn contains(&self, id: TransformId) -> bool {
+        self.generations.get(id.index) == Some(&id.generation) && self.nodes[id.index].is_some()
+    }
+
+    fn node(&self, id: TransformId) -> &TransformNode {
+        assert!(self.contains(id), "{:?} is not in the transform tree", id);
+        self.nodes[id.index].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, id: TransformId) -> &mut TransformNode {
+        assert!(self.contains(id), "{:?} is not in the transform tree", id);
+        self.nodes[id.index].as_mut().unwrap()
+    }
+
+    // Keeps the local transform, so the node moves along with its new parent.
+    pub fn set_parent(&mut self, id: TransformId, parent: Option<TransformId>) {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                assert!(current != id, "a transform can't be its own ancestor");
+                ancestor = self.node(current).parent;
+            }
+        }
+
+        if let Some(old_parent) = self.node(id).parent {
+            self.node_mut(old_parent).children.retain(|child| *child != id);
+        }
+        if let Some(parent) = parent {
+            self.node_mut(parent).children.push(id);
+        }
+        self.node_mut(id).parent = parent;
+        self.mark_dirty(id);
+    }
+
+    pub fn get_parent(&self, id: TransformId) -> Option<TransformId> {
+        self.node(id).parent
+    }
+
+    pub fn get_children(&self, id: TransformId) -> &[TransformId] {
+        &self.node(id).children
+    }
+
+    pub fn set_local(&mut self, id: TransformId, local: Transform) {
+        self.node_mut(id).local = local;
+        self.mark_dirty(id);
+    }
+
+    pub fn get_local(&self, id: TransformId) -> &Transform {
+        &self.node(id).local
+    }
+
+    // Edits the local transform in place, e.g. tree.update_local(turret, |t| t.rotation *= spin).
+    pub fn update_local(&mut self, id: TransformId, update: impl FnOnce(&mut Transform)) {
+        update(&mut self.node_mut(id).local);
+        self.mark_dirty(id);
+    }
+
+    fn mark_dirty(&mut self, id: TransformId) {
+        let node = self.node_mut(id);
+        node.dirty = true;
+        let mut stack = node.children.clone();
+        while let Some(child) = stack.pop() {
+            let node = self.node_mut(child);
+            // Already dirty children have dirty descendants as well.
+            if !node.dirty {
+                node.dirty = true;
+                stack.extend(node.children.iter().copied());
+            }
+        }
+    }
+
+    pub fn get_world_matrix(&mut self, id: TransformId) -> Mat4 {
+        if !self.node(id).dirty {
+            return self.node(id).world;
+        }
+
+        let parent_world = match self.node(id).parent {
+            Some(parent) => self.get_world_matrix(parent),
+            None => Mat4::IDENTITY,
+        };
+        let node = self.node_mut(id);
+        node.world = parent_world * node.local.get_matrix();
+        node.dirty = false;
+        node.world
+    }
+
+    pub fn get_world_translation(&mut self, id: TransformId) -> Vec3 {
+        self.get_world_matrix(id).cols[3].truncate()
+    }
+
+    // Recomputes every dirty node at once, handy before handing many matrices to the GPU.
+    pub fn update(&mut self) {
+        for index in 0..self.nodes.len() {
+            if self.nodes[index].as_ref().is_some_and(|node| node.dirty) {
+                self.get_world_matrix(TransformId { index, generation: self.generations[index] });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> Transform {
+        Transform::from_translation(Vec3::new(x, 0.0, 0.0))
+    }
+
+    #[test]
+    fn removed_ids_go_stale_when_the_slot_is_reused() {
+        let mut tree = TransformTree::new();
+        let removed = tree.add(at(1.0), None);
+        tree.remove(removed);
+        assert!(!tree.contains(removed));
+
+        let reused = tree.add(at(2.0), None);
+        assert_eq!(reused.index, removed.index);
+        assert_ne!(reused, removed);
+        assert!(tree.contains(reused));
+        assert!(!tree.contains(removed));
+        assert_eq!(tree.get_world_translation(reused), Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not in the transform tree")]
+    fn stale_ids_panic() {
+        let mut tree = TransformTree::new();
+        let removed = tree.add(at(1.0), None);
+        tree.remove(removed);
+        tree.add(at(2.0), None);
+        tree.get_local(removed);
+    }
+
+    #[test]
+    fn remove_takes_the_children_along() {
+        let mut tree = TransformTree::new();
+        let root = tree.add(at(1.0), None);
+        let parent = tree.add(at(1.0), Some(root));
+        let child = tree.add(at(1.0), Some(parent));
+        tree.remove(parent);
+        assert!(!tree.contains(parent) && !tree.contains(child));
+        assert!(tree.get_children(root).is_empty());
+    }
+
+    #[test]
+    fn reparenting_marks_the_subtree_dirty() {
+        let mut tree = TransformTree::new();
+        let first = tree.add(at(1.0), None);
+        let second = tree.add(at(10.0), None);
+        let child = tree.add(at(1.0), Some(first));
+        let grandchild = tree.add(at(1.0), Some(child));
+
+        assert_eq!(tree.get_world_translation(grandchild), Vec3::new(3.0, 0.0, 0.0));
+        assert!(!tree.node(child).dirty && !tree.node(grandchild).dirty);
+
+        tree.set_parent(child, Some(second));
+        assert!(tree.node(child).dirty && tree.node(grandchild).dirty);
+        assert_eq!(tree.get_world_translation(grandchild), Vec3::new(12.0, 0.0, 0.0));
+        assert_eq!(tree.get_children(first), &[]);
+        assert_eq!(tree.get_children(second), &[child]);
+
+        // Only the new parent moves the subtree now.
+        tree.set_local(first, at(100.0));
+        assert!(!tree.node(grandchild).dirty);
+        tree.update_local(second, |transform| transform.translation.x = 20.0);
+        tree.update();
+        assert!(!tree.node(grandchild).dirty);
+        assert_eq!(tree.get_world_translation(grandchild), Vec3::new(22.0, 0.0, 0.0));
+
+        tree.set_parent(child, None);
+        assert_eq!(tree.get_world_translation(grandchild), Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "own ancestor")]
+    fn cycles_are_rejected() {
+        let mut tree = TransformTree::new();
+        let parent = tree.add(at(1.0), None);
+        let child = tree.add(at(1.0), Some(parent));
+        tree.set_parent(parent, Some(child));
+    }
+}
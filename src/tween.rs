@@ -0,0 +1,624 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+use std::rc::Rc;
+use crate::color::Color;
+use crate::math::{Coordinate2D, Coordinate3D, Quat, Vec2, Vec3, Vec4};
+
+// Curves mapping linear progress from 0 to 1 onto eased progress. In curves start slow,
+// Out curves end slow and InOut do both. Back and Elastic overshoot the 0..1 range.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+    // Control points (x1, y1, x2, y2) like CSS cubic-bezier(), x values have to be in 0..1.
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        const BACK: f32 = 1.70158;
+        const BACK_IN_OUT: f32 = BACK * 1.525;
+        const ELASTIC: f32 = 2.0 * PI / 3.0;
+        const ELASTIC_IN_OUT: f32 = 2.0 * PI / 4.5;
+
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadInOut => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 },
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Easing::BackInOut => if t < 0.5 {
+                (2.0 * t).powi(2) * ((BACK_IN_OUT + 1.0) * 2.0 * t - BACK_IN_OUT) / 2.0
+            } else {
+                ((2.0 * t - 2.0).powi(2) * ((BACK_IN_OUT + 1.0) * (2.0 * t - 2.0) + BACK_IN_OUT) + 2.0) / 2.0
+            },
+            // The elastic curves don't reach the ends exactly, so those are pinned.
+            _ if matches!(self, Easing::ElasticIn | Easing::ElasticOut | Easing::ElasticInOut) && (t == 0.0 || t == 1.0) => t,
+            Easing::ElasticIn => -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * ELASTIC).sin(),
+            Easing::ElasticOut => 2f32.powf(-10.0 * t) * ((10.0 * t - 0.75) * ELASTIC).sin() + 1.0,
+            Easing::ElasticInOut => if t < 0.5 {
+                -(2f32.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * ELASTIC_IN_OUT).sin()) / 2.0
+            } else {
+                2f32.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * ELASTIC_IN_OUT).sin() / 2.0 + 1.0
+            },
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => if t < 0.5 {
+                (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+            } else {
+                (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+            },
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let s = solve_bezier(t, x1, x2);
+                bezier(s, y1, y2)
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+// One axis of a cubic bezier running from 0 to 1 with the control points p1 and p2.
+fn bezier(s: f32, p1: f32, p2: f32) -> f32 {
+    let inverse = 1.0 - s;
+    3.0 * inverse * inverse * s * p1 + 3.0 * inverse * s * s * p2 + s * s * s
+}
+
+// Finds the curve parameter whose x is the given value. Newton's method converges in a
+// few steps for most curves, bisection catches the flat ones.
+fn solve_bezier(x: f32, x1: f32, x2: f32) -> f32 {
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(s, x1, x2) - x;
+        if error.abs() < 1e-6 {
+            return s;
+        }
+        let slope = 3.0 * (1.0 - s) * (1.0 - s) * x1 + 6.0 * (1.0 - s) * s * (x2 - x1) + 3.0 * s * s * (1.0 - x2);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    s = x;
+    for _ in 0..32 {
+        let value = bezier(s, x1, x2);
+        if (value - x).abs() < 1e-6 {
+            break;
+        }
+        if value < x { low = s } else { high = s }
+        s = (low + high) / 2.0;
+    }
+    s
+}
+
+// Values a tween can blend between.
+pub trait Tweenable: Clone {
+    fn tween(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Tweenable for f64 {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t as f64
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self.lerp(*to, t)
+    }
+}
+
+impl Tweenable for Vec3 {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self.lerp(*to, t)
+    }
+}
+
+impl Tweenable for Vec4 {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self.lerp(*to, t)
+    }
+}
+
+impl Tweenable for Quat {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self.slerp(*to, t)
+    }
+}
+
+impl Tweenable for Color {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        self.lerp(*to, t)
+    }
+}
+
+impl Tweenable for Coordinate2D {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        Coordinate2D { x: self.x.tween(&to.x, t), y: self.y.tween(&to.y, t) }
+    }
+}
+
+impl Tweenable for Coordinate3D {
+    fn tween(&self, to: &Self, t: f32) -> Self {
+        Coordinate3D { x: self.x.tween(&to.x, t), y: self.y.tween(&to.y, t), z: self.z.tween(&to.z, t) }
+    }
+}
+
+// Shared view of the current value of a tween, so it can still be read after the tween
+// was moved into a Sequence or Parallel.
+#[derive(Debug, Default)]
+pub struct TweenValue<T>(Rc<RefCell<T>>);
+
+impl<T> Clone for TweenValue<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Clone> TweenValue<T> {
+    pub fn new(value: T) -> Self {
+        Self(Rc::new(RefCell::new(value)))
+    }
+
+    pub fn get(&self) -> T {
+        self.0.borrow().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.borrow_mut() = value;
+    }
+}
+
+// Anything that advances with the frame delta. Tweens, delays and groups of them.
+pub trait Animation {
+    // Advances by delta seconds and returns the part of it that was left over after the
+    // animation finished, which sequences hand on to the next animation.
+    fn tick(&mut self, delta: f32) -> f32;
+    fn is_finished(&self) -> bool;
+    // Back to the start, including the value of tweens.
+    fn reset(&mut self);
+    // None when the animation repeats forever.
+    fn get_duration(&self) -> Option<f32>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repeat {
+    // Runs once plus the given number of times.
+    Times(u32),
+    Forever,
+}
+
+pub struct Tween<T: Tweenable> {
+    from: T,
+    to: T,
+    duration: f32,
+    easing: Easing,
+    delay: f32,
+    repeat: Repeat,
+    // Every other run plays backwards.
+    yoyo: bool,
+    elapsed: f32,
+    value: TweenValue<T>,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            value: TweenValue::new(from.clone()),
+            from,
+            to,
+            duration,
+            easing: Easing::Linear,
+            delay: 0.0,
+            repeat: Repeat::Times(0),
+            yoyo: false,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn set_easing(&mut self, easing: Easing) { self.easing = easing; }
+    pub fn set_delay(&mut self, delay: f32) { self.delay = delay; }
+    pub fn set_repeat(&mut self, repeat: Repeat) { self.repeat = repeat; }
+    pub fn set_yoyo(&mut self, yoyo: bool) { self.yoyo = yoyo; }
+
+    // Changes the end points without restarting, e.g. to chase a moving target.
+    pub fn set_range(&mut self, from: T, to: T) {
+        self.from = from;
+        self.to = to;
+        self.apply();
+    }
+
+    pub fn get_value(&self) -> T {
+        self.value.get()
+    }
+
+    pub fn get_value_handle(&self) -> TweenValue<T> {
+        self.value.clone()
+    }
+
+    // Eased progress of the current run, after yoyo is applied.
+    pub fn get_progress(&self) -> f32 {
+        let local = (self.elapsed - self.delay).max(0.0);
+        if self.duration <= 0.0 {
+            return if self.yoyo && matches!(self.repeat, Repeat::Times(times) if times % 2 == 1) { 0.0 } else { 1.0 };
+        }
+
+        let (run, t) = match self.repeat {
+            Repeat::Times(times) if self.is_finished() => (times, 1.0),
+            _ => {
+                let runs = local / self.duration;
+                (runs.floor() as u32, runs.fract())
+            }
+        };
+        let t = if self.yoyo && run % 2 == 1 { 1.0 - t } else { t };
+        self.easing.apply(t)
+    }
+
+    fn apply(&self) {
+        self.value.set(self.from.tween(&self.to, self.get_progress()));
+    }
+}
+
+impl<T: Tweenable> Animation for Tween<T> {
+    fn tick(&mut self, delta: f32) -> f32 {
+        self.elapsed += delta;
+        let leftover = match self.get_duration() {
+            Some(duration) if self.elapsed > duration => {
+                let leftover = self.elapsed - duration;
+                self.elapsed = duration;
+                leftover
+            }
+            _ => 0.0,
+        };
+        self.apply();
+        leftover
+    }
+
+    fn is_finished(&self) -> bool {
+        self.get_duration().is_some_and(|duration| self.elapsed >= duration)
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.apply();
+    }
+
+    fn get_duration(&self) -> Option<f32> {
+        match self.repeat {
+            Repeat::Times(times) => Some(self.delay + self.duration.max(0.0) * (times + 1) as f32),
+            Repeat::Forever => None,
+        }
+    }
+}
+
+// Waits without changing anything, mostly as a gap in a Sequence.
+pub struct Delay {
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Delay {
+    pub fn new(duration: f32) -> Self {
+        Self { duration, elapsed: 0.0 }
+    }
+}
+
+impl Animation for Delay {
+    fn tick(&mut self, delta: f32) -> f32 {
+        self.elapsed += delta;
+        let leftover = (self.elapsed - self.duration).max(0.0);
+        self.elapsed = self.elapsed.min(self.duration);
+        leftover
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    fn get_duration(&self) -> Option<f32> {
+        Some(self.duration)
+    }
+}
+
+// Runs animations one after another.
+#[derive(Default)]
+pub struct Sequence {
+    animations: Vec<Box<dyn Animation>>,
+    current: usize,
+}
+
+impl Sequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then(mut self, animation: impl Animation + 'static) -> Self {
+        self.push(animation);
+        self
+    }
+
+    pub fn push(&mut self, animation: impl Animation + 'static) {
+        self.animations.push(Box::new(animation));
+    }
+}
+
+impl Animation for Sequence {
+    fn tick(&mut self, delta: f32) -> f32 {
+        let mut remaining = delta;
+        while let Some(animation) = self.animations.get_mut(self.current) {
+            remaining = animation.tick(remaining);
+            if !animation.is_finished() {
+                return 0.0;
+            }
+            self.current += 1;
+        }
+        remaining
+    }
+
+    fn is_finished(&self) -> bool {
+        self.current >= self.animations.len()
+    }
+
+    fn reset(&mut self) {
+        // Backwards, so every value ends up where the first animation starts it.
+        for animation in self.animations.iter_mut().rev() {
+            animation.reset();
+        }
+        self.current = 0;
+    }
+
+    fn get_duration(&self) -> Option<f32> {
+        self.animations.iter().map(|animation| animation.get_duration()).sum()
+    }
+}
+
+// Runs animations at the same time, finishing with the longest one.
+#[derive(Default)]
+pub struct Parallel {
+    animations: Vec<Box<dyn Animation>>,
+}
+
+impl Parallel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, animation: impl Animation + 'static) -> Self {
+        self.push(animation);
+        self
+    }
+
+    pub fn push(&mut self, animation: impl Animation + 'static) {
+        self.animations.push(Box::new(animation));
+    }
+}
+
+impl Animation for Parallel {
+    fn tick(&mut self, delta: f32) -> f32 {
+        // Finished animations give back the whole delta, so the longest one decides.
+        self.animations.iter_mut().map(|animation| animation.tick(delta)).fold(delta, f32::min)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.animations.iter().all(|animation| animation.is_finished())
+    }
+
+    fn reset(&mut self) {
+        for animation in &mut self.animations {
+            animation.reset();
+        }
+    }
+
+    fn get_duration(&self) -> Option<f32> {
+        self.animations.iter().map(|animation| animation.get_duration()).try_fold(0.0, |longest: f32, duration| duration.map(|duration| longest.max(duration)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 17] = [
+        Easing::Linear, Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+        Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+        Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+        Easing::ElasticIn, Easing::ElasticOut, Easing::ElasticInOut,
+        Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut,
+        Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+    ];
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn easings_hit_both_ends() {
+        for easing in EASINGS {
+            assert_near(easing.apply(0.0), 0.0);
+            assert_near(easing.apply(1.0), 1.0);
+            assert_near(easing.apply(-1.0), 0.0);
+            assert_near(easing.apply(2.0), 1.0);
+        }
+        assert_near(Easing::QuadIn.apply(0.5), 0.25);
+        assert_near(Easing::CubicInOut.apply(0.5), 0.5);
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+    }
+
+    #[test]
+    fn cubic_bezier_solves_for_x() {
+        // Control points on the diagonal give a straight line.
+        let linear = Easing::CubicBezier(1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0);
+        for t in [0.1, 0.25, 0.5, 0.9] {
+            assert_near(linear.apply(t), t);
+        }
+        // CSS ease-in-out is symmetric around the middle.
+        let ease_in_out = Easing::CubicBezier(0.42, 0.0, 0.58, 1.0);
+        assert_near(ease_in_out.apply(0.5), 0.5);
+        assert_near(ease_in_out.apply(0.2) + ease_in_out.apply(0.8), 1.0);
+        // x is flat at both ends, where Newton's method gives up and bisection takes over.
+        let flat = Easing::CubicBezier(0.0, 0.0, 1.0, 1.0);
+        for t in [0.001, 0.01, 0.5, 0.99, 0.999] {
+            assert_near(flat.apply(t), t);
+        }
+    }
+
+    #[test]
+    fn tween_ends_on_the_target() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0).with_easing(Easing::QuadOut);
+        assert_eq!(tween.get_value(), 0.0);
+        assert_eq!(tween.tick(0.5), 0.0);
+        assert_near(tween.get_value(), 7.5);
+        assert!(!tween.is_finished());
+        assert_near(tween.tick(0.75), 0.25);
+        assert!(tween.is_finished());
+        assert_eq!(tween.get_value(), 10.0);
+
+        let handle = tween.get_value_handle();
+        tween.reset();
+        assert_eq!(handle.get(), 0.0);
+    }
+
+    #[test]
+    fn delay_holds_the_start_value() {
+        let mut tween = Tween::new(0.0, 4.0, 1.0).with_delay(0.5);
+        assert_eq!(tween.get_duration(), Some(1.5));
+        tween.tick(0.25);
+        assert_eq!(tween.get_value(), 0.0);
+        tween.tick(0.5);
+        assert_near(tween.get_value(), 1.0);
+        assert_near(tween.tick(1.0), 0.25);
+        assert_eq!(tween.get_value(), 4.0);
+    }
+
+    #[test]
+    fn repeat_counts_runs() {
+        let mut tween = Tween::new(0.0, 1.0, 1.0).with_repeat(Repeat::Times(2));
+        assert_eq!(tween.get_duration(), Some(3.0));
+        tween.tick(1.25);
+        assert_near(tween.get_value(), 0.25);
+        tween.tick(1.5);
+        assert_near(tween.get_value(), 0.75);
+        assert!(!tween.is_finished());
+        tween.tick(0.5);
+        assert!(tween.is_finished());
+        assert_eq!(tween.get_value(), 1.0);
+
+        let mut forever = Tween::new(0.0, 1.0, 1.0).with_repeat(Repeat::Forever);
+        assert_eq!(forever.get_duration(), None);
+        assert_eq!(forever.tick(100.5), 0.0);
+        assert!(!forever.is_finished());
+        assert_near(forever.get_value(), 0.5);
+    }
+
+    #[test]
+    fn yoyo_plays_every_other_run_backwards() {
+        let mut tween = Tween::new(0.0, 1.0, 1.0).with_repeat(Repeat::Times(1)).with_yoyo(true);
+        tween.tick(0.75);
+        assert_near(tween.get_value(), 0.75);
+        tween.tick(0.5);
+        assert_near(tween.get_value(), 0.75);
+        tween.tick(0.5);
+        assert_near(tween.get_value(), 0.25);
+        tween.tick(1.0);
+        assert!(tween.is_finished());
+        assert_eq!(tween.get_value(), 0.0);
+
+        // An odd number of runs ends back on the target.
+        let mut tween = Tween::new(0.0, 1.0, 1.0).with_repeat(Repeat::Times(2)).with_yoyo(true);
+        tween.tick(5.0);
+        assert_eq!(tween.get_value(), 1.0);
+    }
+
+    #[test]
+    fn sequence_hands_on_leftover_time() {
+        let first = Tween::new(0.0, 1.0, 1.0);
+        let second = Tween::new(0.0, 1.0, 1.0);
+        let (first_value, second_value) = (first.get_value_handle(), second.get_value_handle());
+        let mut sequence = Sequence::new().then(first).then(Delay::new(0.5)).then(second);
+        assert_eq!(sequence.get_duration(), Some(2.5));
+
+        sequence.tick(1.75);
+        assert_eq!(first_value.get(), 1.0);
+        assert_near(second_value.get(), 0.25);
+        assert_near(sequence.tick(1.0), 0.25);
+        assert!(sequence.is_finished());
+
+        sequence.reset();
+        assert_eq!((first_value.get(), second_value.get()), (0.0, 0.0));
+
+        let mut parallel = Parallel::new().with(Tween::new(0.0, 1.0, 1.0)).with(Delay::new(2.0));
+        assert_eq!(parallel.get_duration(), Some(2.0));
+        assert_near(parallel.tick(1.5), 0.0);
+        assert!(!parallel.is_finished());
+        assert_near(parallel.tick(1.0), 0.5);
+        assert!(parallel.is_finished());
+    }
+}
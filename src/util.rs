@@ -0,0 +1,6 @@
+use std::path::Path;
+
+// Lowercase file extension without the dot, empty when there is none.
+pub(crate) fn extension(path: &str) -> String {
+    Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase()
+}
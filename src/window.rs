@@ -0,0 +1,163 @@
+use anyhow::Error;
+use image::DynamicImage;
+use winit::{dpi::PhysicalSize, monitor::MonitorHandle, window::{Fullscreen, Icon, Window, WindowAttributes}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    // A window covering the whole monitor, switches instantly.
+    Borderless,
+    // Takes over the monitor at its largest video mode.
+    Exclusive,
+}
+
+impl FullscreenMode {
+    pub(crate) fn to_winit(self, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+        match self {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            FullscreenMode::Exclusive => {
+                let video_mode = monitor.as_ref().and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|mode| (mode.size().width * mode.size().height, mode.refresh_rate_millihertz()))
+                });
+                // Platforms without video modes (like the web) get the next best thing.
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => Some(Fullscreen::Borderless(monitor)),
+                }
+            }
+        }
+    }
+}
+
+// Everything about the window that is decided before it opens. Sizes are in physical pixels.
+pub struct WindowConfig {
+    title: String,
+    width: u32,
+    height: u32,
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+    resizable: bool,
+    decorations: bool,
+    transparent: bool,
+    maximized: bool,
+    fullscreen: FullscreenMode,
+    icon: Option<DynamicImage>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowConfig {
+    pub fn new() -> Self {
+        Self {
+            title: "CatEngine".to_string(),
+            width: 800,
+            height: 600,
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            decorations: true,
+            transparent: false,
+            maximized: false,
+            fullscreen: FullscreenMode::Windowed,
+            icon: None,
+        }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    pub fn with_max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    // Only shows through where the program clears or draws with alpha below 1, and only
+    // on platforms whose surface supports a non opaque alpha mode.
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn with_maximized(mut self, maximized: bool) -> Self {
+        self.maximized = maximized;
+        self
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: FullscreenMode) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn with_icon(mut self, icon: DynamicImage) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn with_icon_file(self, file: &str) -> Result<Self, Error> {
+        Ok(self.with_icon(image::open(file)?))
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.transparent
+    }
+
+    pub(crate) fn to_attributes(&self, monitor: Option<MonitorHandle>) -> WindowAttributes {
+        let mut attributes = Window::default_attributes()
+            .with_title(self.title.clone())
+            .with_inner_size(PhysicalSize::new(self.width, self.height))
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_transparent(self.transparent)
+            .with_maximized(self.maximized)
+            .with_fullscreen(self.fullscreen.to_winit(monitor));
+
+        if let Some((width, height)) = self.min_size {
+            attributes = attributes.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.max_size {
+            attributes = attributes.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some(icon) = &self.icon {
+            let icon = icon.to_rgba8();
+            let (width, height) = icon.dimensions();
+            match Icon::from_rgba(icon.into_raw(), width, height) {
+                Ok(icon) => attributes = attributes.with_window_icon(Some(icon)),
+                Err(error) => log::warn!("window icon was not set: {}", error),
+            }
+        }
+        attributes
+    }
+}